@@ -1,6 +1,15 @@
+use crate::models::GenerateResult;
+use crate::services::epub_service::{EpubOptions, EpubService};
+use crate::services::export_service::{BatchExportReport, BatchExportRequest, ExportService};
+use crate::services::pptx_service::PptxService;
+use crate::services::site_service::{PublishOptions, PublishReport, SiteService};
+use crate::services::slides_service::{SlideOptions, SlidesService};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -32,7 +41,8 @@ pub async fn search_content(root_path: String, query: String) -> Result<Vec<Sear
     Ok(results)
 }
 
-fn search_recursive(
+#[allow(clippy::unnecessary_map_or)]
+pub(crate) fn search_recursive(
     path: &Path,
     query: &str,
     results: &mut Vec<SearchResult>,
@@ -90,7 +100,7 @@ pub struct FileNode {
     pub children: Option<Vec<FileNode>>,
 }
 
-fn read_dir_recursive(path: &Path, depth: i32) -> Result<Vec<FileNode>, String> {
+pub(crate) fn read_dir_recursive(path: &Path, depth: i32) -> Result<Vec<FileNode>, String> {
     if depth < 0 {
         return Ok(Vec::new());
     }
@@ -181,46 +191,100 @@ pub async fn save_binary_file(path: String, data: Vec<u8>) -> Result<(), String>
 
 #[tauri::command]
 pub async fn export_markdown(path: String, content: String, format: String, is_content_html: Option<bool>) -> Result<(), String> {
-    let output_path = Path::new(&path);
-    let is_html = is_content_html.unwrap_or(false);
+    ExportService::export(
+        Path::new(&path),
+        &content,
+        &format,
+        is_content_html.unwrap_or(false),
+    )
+}
 
-    let html_content = if is_html {
-        content
-    } else {
-        markdown::to_html(&content)
-    };
+#[tauri::command]
+pub async fn export_batch(
+    app: AppHandle,
+    request: BatchExportRequest,
+) -> Result<BatchExportReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ExportService::export_batch(&request, |progress| {
+            if let Err(e) = app.emit("batch-export-progress", &progress) {
+                log::warn!("发送导出进度失败: {}", e);
+            }
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    match format.as_str() {
-        "html" => {
-            let full_html = format!(
-                r#"<!DOCTYPE html><html><head><meta charset="utf-8"><style>body{{font-family:sans-serif;line-height:1.6;padding:2em;max-width:800px;margin:auto;}}pre{{background:#f4f4f4;padding:1em;border-radius:4px;}}code{{font-family:monospace;}}blockquote{{border-left:4px solid #ddd;padding-left:1em;color:#666;}}table{{border-collapse:collapse;width:100%;margin-bottom:1em;}}th,td{{border:1px solid #ddd;padding:8px;text-align:left;}}th{{background-color:#f2f2f2;}}img{{max-width:100%;}}</style></head><body>{}</body></html>"#,
-                html_content
-            );
-            fs::write(output_path, full_html).map_err(|e| e.to_string())?;
-        },
-        "docx" | "doc" => {
-            // 实现一个兼容 Word 的 HTML 导出格式（Word 可以直接打开带 HTML 标记的 .doc 文件）
-            let word_html = format!(
-                r#"<html xmlns:o='urn:schemas-microsoft-com:office:office' xmlns:w='urn:schemas-microsoft-com:office:word' xmlns='http://www.w3.org/TR/REC-html40'>
-                <head><meta charset='utf-8'><title>Export</title>
-                <style>
-                  body {{ font-family: Arial, sans-serif; }}
-                  table {{ border-collapse: collapse; width: 100%; margin-bottom: 15px; }}
-                  th, td {{ border: 1px solid #000; padding: 5px 10px; text-align: left; }}
-                  th {{ background-color: #f2f2f2; }}
-                  img {{ max-width: 100%; height: auto; }}
-                  pre {{ background: #f4f4f4; padding: 10px; border: 1px solid #ddd; }}
-                </style>
-                </head>
-                <body>{}</body></html>"#,
-                html_content
-            );
-            fs::write(output_path, word_html).map_err(|e| e.to_string())?;
-        },
-        _ => return Err(format!("目前后端暂不支持 {} 格式的自动转换，PDF 导出请使用预览页面的打印按钮并选择'另存为 PDF'。", format)),
-    }
+#[tauri::command]
+pub async fn export_epub(
+    source_dir: String,
+    output_path: String,
+    options: Option<EpubOptions>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        EpubService::export_folder(
+            Path::new(&source_dir),
+            Path::new(&output_path),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn export_slides(
+    source_path: String,
+    output_path: String,
+    options: Option<SlideOptions>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        SlidesService::export_file(
+            Path::new(&source_path),
+            Path::new(&output_path),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn export_pptx(source_path: String, output_path: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        PptxService::export_file(Path::new(&source_path), Path::new(&output_path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn save_generate_result_as_pptx(
+    result: GenerateResult,
+    output_path: Option<String>,
+) -> Result<GenerateResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        PptxService::save_generate_result(&result, output_path.as_deref().map(Path::new))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn publish_site(
+    root_path: String,
+    output_dir: String,
+    options: Option<PublishOptions>,
+) -> Result<PublishReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        SiteService::publish(
+            Path::new(&root_path),
+            Path::new(&output_dir),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -269,61 +333,748 @@ pub async fn clear_project_history() -> Result<(), String> {
 }
 
 // 知识库相关命令
-use crate::models::{Document, KnowledgeBase};
+use crate::models::{
+    AiConfig, AiProfile, AiProfileInput, AiSettings, ChatAnswer, ChatRequest, ChunkSearchResult,
+    ChunkingConfig, ConnectionTestResult, Conversation, ConversationMessage, Document,
+    DocumentListQuery, DocumentPage, EmbeddingConfig, FolderImportOptions, GenerateRequest,
+    GenerateType, ImportReport, KnowledgeBase, KnowledgeBaseInput, McpSettings, McpStatus,
+    PromptTemplate, PromptTemplateInput, ReportJob, ReportJobStatus, StorageStatus, SyncReport,
+    TagCount,
+};
+use crate::services::ai_settings_service::{self, AiSettingsService};
+use crate::services::auto_sync::{self, AutoSync};
+use crate::services::bundle_service::BundleService;
+use crate::services::chat_service::ChatService;
+use crate::services::embedding_service;
+use crate::services::generate_service::{GenerateService, GenerationJobs, GenerationToken};
 use crate::services::knowledge_base_service::KnowledgeBaseService;
+use crate::services::mcp_server::{self, McpHttpState, McpServer, McpSettingsStore};
+use crate::services::prompt_template_service::PromptTemplateService;
+use crate::services::report_service::ReportService;
+use crate::services::search_service::{self, SearchService};
+use tauri::{Manager, State};
 
 #[tauri::command]
 pub async fn create_knowledge_base(
+    service: State<'_, KnowledgeBaseService>,
     name: String,
     description: Option<String>,
 ) -> Result<KnowledgeBase, String> {
-    let service = KnowledgeBaseService::new()?;
     service.create_knowledge_base(name, description)
 }
 
 #[tauri::command]
-pub async fn list_knowledge_bases() -> Result<Vec<KnowledgeBase>, String> {
-    let service = KnowledgeBaseService::new()?;
+pub async fn list_knowledge_bases(
+    service: State<'_, KnowledgeBaseService>,
+) -> Result<Vec<KnowledgeBase>, String> {
     service.list_knowledge_bases()
 }
 
 #[tauri::command]
-pub async fn get_knowledge_base(id: String) -> Result<Option<KnowledgeBase>, String> {
-    let service = KnowledgeBaseService::new()?;
+pub async fn get_knowledge_base(
+    service: State<'_, KnowledgeBaseService>,
+    id: String,
+) -> Result<Option<KnowledgeBase>, String> {
     service.get_knowledge_base(&id)
 }
 
+/// 修改知识库的名称、描述、图标、颜色和标签
+#[tauri::command]
+pub async fn update_knowledge_base(
+    service: State<'_, KnowledgeBaseService>,
+    id: String,
+    input: KnowledgeBaseInput,
+) -> Result<KnowledgeBase, String> {
+    service.update_knowledge_base(&id, input)
+}
+
 #[tauri::command]
-pub async fn delete_knowledge_base(id: String) -> Result<(), String> {
-    let service = KnowledgeBaseService::new()?;
+pub async fn delete_knowledge_base(
+    service: State<'_, KnowledgeBaseService>,
+    id: String,
+) -> Result<(), String> {
     service.delete_knowledge_base(&id)
 }
 
 #[tauri::command]
-pub async fn import_documents(kb_id: String, paths: Vec<String>) -> Result<Vec<Document>, String> {
-    let service = KnowledgeBaseService::new()?;
-    let mut documents = Vec::new();
+pub async fn import_documents(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+    paths: Vec<String>,
+) -> Result<Vec<Document>, String> {
+    service.import_documents(&kb_id, &paths)
+}
+
+/// 导入文件夹，处理每个文件后发送 `import-progress` 事件
+#[tauri::command]
+pub async fn import_folder(
+    app: AppHandle,
+    kb_id: String,
+    folder: String,
+    options: Option<FolderImportOptions>,
+) -> Result<ImportReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<KnowledgeBaseService>().import_folder(
+            &kb_id,
+            Path::new(&folder),
+            &options.unwrap_or_default(),
+            &|progress| {
+                let _ = app.emit("import-progress", progress);
+            },
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_documents(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+) -> Result<Vec<Document>, String> {
+    service.get_documents(&kb_id)
+}
+
+/// 分页列出文档摘要（不含正文和文本块）
+#[tauri::command]
+pub async fn list_document_summaries(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+    query: Option<DocumentListQuery>,
+) -> Result<DocumentPage, String> {
+    service.list_document_summaries(&kb_id, &query.unwrap_or_default())
+}
+
+/// 获取文档的正文和文本块
+#[tauri::command]
+pub async fn get_document(
+    service: State<'_, KnowledgeBaseService>,
+    doc_id: String,
+) -> Result<Option<Document>, String> {
+    service.get_document(&doc_id)
+}
+
+/// 统计知识库的标签和关键词
+#[tauri::command]
+pub async fn list_tags(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+) -> Result<Vec<TagCount>, String> {
+    service.list_tags(&kb_id)
+}
+
+/// 按标签或关键词筛选文档
+#[tauri::command]
+pub async fn get_documents_by_tag(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+    tag: String,
+) -> Result<Vec<Document>, String> {
+    service.get_documents_by_tag(&kb_id, &tag)
+}
+
+#[tauri::command]
+pub async fn delete_document(
+    service: State<'_, KnowledgeBaseService>,
+    doc_id: String,
+) -> Result<(), String> {
+    service.delete_document(&doc_id)
+}
+
+/// 将文档移动到另一个知识库
+#[tauri::command]
+pub async fn move_documents(
+    service: State<'_, KnowledgeBaseService>,
+    doc_ids: Vec<String>,
+    target_kb_id: String,
+) -> Result<Vec<Document>, String> {
+    service.move_documents(&doc_ids, &target_kb_id)
+}
+
+/// 将文档复制到另一个知识库
+#[tauri::command]
+pub async fn copy_documents(
+    service: State<'_, KnowledgeBaseService>,
+    doc_ids: Vec<String>,
+    target_kb_id: String,
+) -> Result<Vec<Document>, String> {
+    service.copy_documents(&doc_ids, &target_kb_id)
+}
+
+#[tauri::command]
+pub async fn sync_knowledge_base(app: AppHandle, kb_id: String) -> Result<SyncReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<KnowledgeBaseService>()
+            .sync_knowledge_base(&kb_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 修改知识库的分块配置
+#[tauri::command]
+pub async fn set_chunking_config(
+    app: AppHandle,
+    kb_id: String,
+    config: ChunkingConfig,
+) -> Result<KnowledgeBase, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<KnowledgeBaseService>()
+            .set_chunking_config(&kb_id, config)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 按当前分块配置重新分块知识库中的文档
+#[tauri::command]
+pub async fn rechunk_knowledge_base(app: AppHandle, kb_id: String) -> Result<SyncReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<KnowledgeBaseService>()
+            .rechunk_knowledge_base(&kb_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 将知识库导出为分享包（zip）
+#[tauri::command]
+pub async fn export_knowledge_base(
+    app: AppHandle,
+    kb_id: String,
+    output_path: String,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        BundleService::export(
+            &app.state::<KnowledgeBaseService>(),
+            &kb_id,
+            Path::new(&output_path),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 从分享包导入为新的知识库
+#[tauri::command]
+pub async fn import_knowledge_base(
+    app: AppHandle,
+    bundle_path: String,
+) -> Result<KnowledgeBase, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        BundleService::import(
+            &app.state::<KnowledgeBaseService>(),
+            Path::new(&bundle_path),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    for path in paths {
-        match service.import_document(&kb_id, &path) {
-            Ok(doc) => documents.push(doc),
-            Err(e) => {
-                log::warn!("导入文档失败 {}: {}", path, e);
+/// 知识库数据库版本、启动时隔离的文件和可恢复的备份
+#[tauri::command]
+pub async fn get_storage_status(
+    service: State<'_, KnowledgeBaseService>,
+) -> Result<StorageStatus, String> {
+    service.storage_status()
+}
+
+/// 用备份或隔离的数据库替换当前知识库数据库
+#[tauri::command]
+pub async fn restore_storage_backup(app: AppHandle, name: String) -> Result<StorageStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<KnowledgeBaseService>().restore_backup(&name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 开启或关闭后台自动同步；有变化的知识库发送 `knowledge-base-synced` 事件
+#[tauri::command]
+pub async fn set_auto_sync(
+    app: AppHandle,
+    enabled: bool,
+    interval_secs: Option<u64>,
+) -> Result<bool, String> {
+    let auto_sync = app.state::<AutoSync>();
+    if !enabled {
+        auto_sync.stop();
+        return Ok(false);
+    }
+
+    let interval = interval_secs
+        .unwrap_or(auto_sync::DEFAULT_INTERVAL_SECS)
+        .max(auto_sync::MIN_INTERVAL_SECS);
+    let handle = app.clone();
+    auto_sync.start(
+        std::time::Duration::from_secs(interval),
+        move || match handle.state::<KnowledgeBaseService>().sync_all() {
+            Ok(reports) => {
+                for report in reports.iter().filter(|r| r.has_changes()) {
+                    let _ = handle.emit("knowledge-base-synced", report);
+                }
             }
-        }
+            Err(e) => log::warn!("自动同步失败: {}", e),
+        },
+    );
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn search_knowledge_base(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+    query: String,
+) -> Result<Vec<ChunkSearchResult>, String> {
+    let documents = service.get_documents(&kb_id)?;
+    Ok(SearchService::search(
+        &documents,
+        &query,
+        search_service::DEFAULT_LIMIT,
+    ))
+}
+
+#[tauri::command]
+pub async fn embed_knowledge_base(
+    app: AppHandle,
+    kb_id: String,
+    config: Option<EmbeddingConfig>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = embedding_service::create_provider(&config.unwrap_or_default())?;
+        app.state::<KnowledgeBaseService>()
+            .embed_knowledge_base(&kb_id, provider.as_ref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn search_knowledge_base_hybrid(
+    app: AppHandle,
+    kb_id: String,
+    query: String,
+    config: Option<EmbeddingConfig>,
+    vector_weight: Option<f64>,
+) -> Result<Vec<ChunkSearchResult>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let service = app.state::<KnowledgeBaseService>();
+        let provider = embedding_service::create_provider(&config.unwrap_or_default())?;
+
+        // 补齐新导入文本块的向量
+        service.embed_knowledge_base(&kb_id, provider.as_ref())?;
+        let query_vector = provider
+            .embed(std::slice::from_ref(&query))?
+            .pop()
+            .unwrap_or_default();
+        let embeddings = service.get_embeddings(&kb_id, &provider.id())?;
+        let documents = service.get_documents(&kb_id)?;
+
+        Ok(SearchService::hybrid_search(
+            &documents,
+            &query,
+            &query_vector,
+            &embeddings,
+            vector_weight.unwrap_or(search_service::DEFAULT_VECTOR_WEIGHT),
+            search_service::DEFAULT_LIMIT,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 未显式传入配置时使用当前 AI 配置方案
+fn resolve_ai_config(app: &AppHandle, config: Option<AiConfig>) -> Result<AiConfig, String> {
+    match config {
+        Some(config) => Ok(config),
+        None => app.state::<AiSettingsService>().resolve_config(None),
     }
+}
 
-    Ok(documents)
+#[tauri::command]
+pub async fn generate_content(
+    app: AppHandle,
+    request: GenerateRequest,
+    config: Option<AiConfig>,
+) -> Result<GenerateResult, String> {
+    let template = app
+        .state::<PromptTemplateService>()
+        .get(request.generate_type.template_id())?;
+    let config = resolve_ai_config(&app, config)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let service = app.state::<KnowledgeBaseService>();
+        let output_dir = request
+            .output_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| service.generated_dir());
+        GenerateService::generate(
+            &service,
+            &template,
+            &request,
+            &config,
+            &output_dir,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
+/// 在后台流式生成，返回任务 ID；片段通过 `generation-token` 事件推送，结束后发送 `generation-finished`。
+/// 事件可能先于返回值到达（如生成立即失败），前端应先按 `jobId` 缓存事件，拿到任务 ID 后再匹配
 #[tauri::command]
-pub async fn get_documents(kb_id: String) -> Result<Vec<Document>, String> {
-    let service = KnowledgeBaseService::new()?;
-    service.get_documents(&kb_id)
+pub async fn start_generation(
+    app: AppHandle,
+    request: GenerateRequest,
+    config: Option<AiConfig>,
+) -> Result<String, String> {
+    let template = app
+        .state::<PromptTemplateService>()
+        .get(request.generate_type.template_id())?;
+    let config = resolve_ai_config(&app, config)?;
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = app.state::<GenerationJobs>().register(&job_id);
+
+    let id = job_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let service = app.state::<KnowledgeBaseService>();
+        let output_dir = request
+            .output_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| service.generated_dir());
+
+        let finished = GenerateService::generate_stream(
+            &service,
+            &template,
+            &request,
+            &config,
+            &output_dir,
+            &id,
+            &cancelled,
+            |token| {
+                let token = GenerationToken {
+                    job_id: id.clone(),
+                    token: token.to_string(),
+                };
+                if let Err(e) = app.emit("generation-token", &token) {
+                    log::warn!("发送生成片段失败: {}", e);
+                }
+            },
+        );
+        app.state::<GenerationJobs>().finish(&id);
+        if let Err(e) = app.emit("generation-finished", &finished) {
+            log::warn!("发送生成结束事件失败: {}", e);
+        }
+    });
+
+    Ok(job_id)
 }
 
 #[tauri::command]
-pub async fn delete_document(doc_id: String) -> Result<(), String> {
-    let service = KnowledgeBaseService::new()?;
-    service.delete_document(&doc_id)
+pub async fn cancel_generation(
+    jobs: State<'_, GenerationJobs>,
+    job_id: String,
+) -> Result<bool, String> {
+    Ok(jobs.cancel(&job_id))
+}
+
+/// 分步生成专题报告，返回已完成检索的任务；进度通过 `report-progress` 事件推送，
+/// 结束后发送 `report-finished`，可用 `cancel_generation` 取消。事件同样可能先于返回值到达，需按 `jobId` 匹配
+#[tauri::command]
+pub async fn start_topic_report(
+    app: AppHandle,
+    request: GenerateRequest,
+    config: Option<AiConfig>,
+) -> Result<ReportJob, String> {
+    let config = resolve_ai_config(&app, config)?;
+    let handle = app.clone();
+    let job = tauri::async_runtime::spawn_blocking(move || {
+        let service = handle.state::<KnowledgeBaseService>();
+        let output_dir = request
+            .output_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| service.generated_dir());
+        ReportService::create_job(&service, &request, &output_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let cancelled = app.state::<GenerationJobs>().register(&job.id);
+    spawn_topic_report(app, job.clone(), config, cancelled)?;
+    Ok(job)
+}
+
+/// 从未完成的步骤继续生成专题报告
+#[tauri::command]
+pub async fn resume_topic_report(
+    app: AppHandle,
+    job_id: String,
+    config: Option<AiConfig>,
+) -> Result<ReportJob, String> {
+    let config = resolve_ai_config(&app, config)?;
+    // 检查与登记一次完成，同一任务不会被重复继续；登记后再读取任务，确保看到上次运行保存的状态
+    let cancelled = app
+        .state::<GenerationJobs>()
+        .try_register(&job_id)
+        .ok_or("专题报告正在生成")?;
+    let job = app
+        .state::<KnowledgeBaseService>()
+        .get_report_job(&job_id)
+        .and_then(|job| job.ok_or_else(|| "专题报告任务不存在".to_string()))
+        .and_then(|job| match job.status {
+            ReportJobStatus::Completed => Err("专题报告已生成完成".to_string()),
+            _ => Ok(job),
+        });
+    let job = match job {
+        Ok(job) => job,
+        Err(e) => {
+            app.state::<GenerationJobs>().finish(&job_id);
+            return Err(e);
+        }
+    };
+
+    spawn_topic_report(app, job.clone(), config, cancelled)?;
+    Ok(job)
+}
+
+/// 在后台执行已登记的任务，结束后注销
+fn spawn_topic_report(
+    app: AppHandle,
+    mut job: ReportJob,
+    config: AiConfig,
+    cancelled: Arc<AtomicBool>,
+) -> Result<(), String> {
+    let template = match app
+        .state::<PromptTemplateService>()
+        .get(GenerateType::TopicReport.template_id())
+    {
+        Ok(template) => template,
+        Err(e) => {
+            app.state::<GenerationJobs>().finish(&job.id);
+            return Err(e);
+        }
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let service = app.state::<KnowledgeBaseService>();
+        ReportService::run(
+            &service,
+            &mut job,
+            &template,
+            &config,
+            &cancelled,
+            |progress| {
+                if let Err(e) = app.emit("report-progress", &progress) {
+                    log::warn!("发送报告进度失败: {}", e);
+                }
+            },
+        );
+        app.state::<GenerationJobs>().finish(&job.id);
+        if let Err(e) = app.emit("report-finished", &job) {
+            log::warn!("发送报告结束事件失败: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_topic_reports(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+) -> Result<Vec<ReportJob>, String> {
+    service.list_report_jobs(&kb_id)
+}
+
+#[tauri::command]
+pub async fn delete_topic_report(app: AppHandle, job_id: String) -> Result<(), String> {
+    // 删除期间占用任务，避免同时被继续生成
+    app.state::<GenerationJobs>()
+        .try_register(&job_id)
+        .ok_or("专题报告正在生成，请先取消")?;
+    let result = app
+        .state::<KnowledgeBaseService>()
+        .delete_report_job(&job_id);
+    app.state::<GenerationJobs>().finish(&job_id);
+    result
+}
+
+#[tauri::command]
+pub async fn ask_knowledge_base(
+    app: AppHandle,
+    request: ChatRequest,
+    config: Option<AiConfig>,
+) -> Result<ChatAnswer, String> {
+    let config = resolve_ai_config(&app, config)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatService::ask(&app.state::<KnowledgeBaseService>(), &request, &config)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_conversations(
+    service: State<'_, KnowledgeBaseService>,
+    kb_id: String,
+) -> Result<Vec<Conversation>, String> {
+    service.list_conversations(&kb_id)
+}
+
+#[tauri::command]
+pub async fn get_conversation_messages(
+    service: State<'_, KnowledgeBaseService>,
+    conversation_id: String,
+) -> Result<Vec<ConversationMessage>, String> {
+    service.get_conversation_messages(&conversation_id)
+}
+
+#[tauri::command]
+pub async fn delete_conversation(
+    service: State<'_, KnowledgeBaseService>,
+    conversation_id: String,
+) -> Result<(), String> {
+    service.delete_conversation(&conversation_id)
+}
+
+#[tauri::command]
+pub async fn list_prompt_templates(
+    templates: State<'_, PromptTemplateService>,
+) -> Result<Vec<PromptTemplate>, String> {
+    templates.list()
+}
+
+#[tauri::command]
+pub async fn create_prompt_template(
+    templates: State<'_, PromptTemplateService>,
+    input: PromptTemplateInput,
+) -> Result<PromptTemplate, String> {
+    templates.create(input)
+}
+
+#[tauri::command]
+pub async fn update_prompt_template(
+    templates: State<'_, PromptTemplateService>,
+    id: String,
+    input: PromptTemplateInput,
+) -> Result<PromptTemplate, String> {
+    templates.update(&id, input)
+}
+
+#[tauri::command]
+pub async fn delete_prompt_template(
+    templates: State<'_, PromptTemplateService>,
+    id: String,
+) -> Result<(), String> {
+    templates.delete(&id)
+}
+
+#[tauri::command]
+pub async fn list_ai_profiles(
+    settings: State<'_, AiSettingsService>,
+) -> Result<AiSettings, String> {
+    settings.list()
+}
+
+#[tauri::command]
+pub async fn save_ai_profile(
+    settings: State<'_, AiSettingsService>,
+    id: Option<String>,
+    input: AiProfileInput,
+) -> Result<AiProfile, String> {
+    settings.save_profile(id.as_deref(), input)
+}
+
+#[tauri::command]
+pub async fn delete_ai_profile(
+    settings: State<'_, AiSettingsService>,
+    id: String,
+) -> Result<(), String> {
+    settings.delete_profile(&id)
+}
+
+#[tauri::command]
+pub async fn set_active_ai_profile(
+    settings: State<'_, AiSettingsService>,
+    id: String,
+) -> Result<(), String> {
+    settings.set_active_profile(&id)
+}
+
+/// 测试连接：传入 `config` 时测试未保存的配置，否则测试指定（或当前）方案
+#[tauri::command]
+pub async fn test_ai_connection(
+    app: AppHandle,
+    profile_id: Option<String>,
+    config: Option<AiConfig>,
+) -> Result<ConnectionTestResult, String> {
+    let config = match config {
+        Some(config) => config,
+        None => app
+            .state::<AiSettingsService>()
+            .resolve_config(profile_id.as_deref())?,
+    };
+    tauri::async_runtime::spawn_blocking(move || ai_settings_service::test_connection(&config))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// MCP 服务命令
+fn mcp_status(app: &AppHandle) -> Result<McpStatus, String> {
+    let settings = app.state::<McpSettingsStore>().get()?;
+    Ok(McpStatus {
+        running: app.state::<McpHttpState>().url().is_some(),
+        url: app.state::<McpHttpState>().url(),
+        tools: mcp_server::tool_infos(&settings),
+        settings,
+    })
+}
+
+#[tauri::command]
+pub async fn get_mcp_status(app: AppHandle) -> Result<McpStatus, String> {
+    mcp_status(&app)
+}
+
+/// 保存设置；服务运行中时按新端口重启，工具和目录设置对后续请求立即生效
+#[tauri::command]
+pub async fn save_mcp_settings(app: AppHandle, settings: McpSettings) -> Result<McpStatus, String> {
+    let previous = app.state::<McpSettingsStore>().get()?;
+    let settings = app.state::<McpSettingsStore>().save(settings)?;
+    if previous.port != settings.port && app.state::<McpHttpState>().url().is_some() {
+        start_mcp_http(&app, &settings)?;
+    }
+    mcp_status(&app)
+}
+
+#[tauri::command]
+pub async fn start_mcp_server(app: AppHandle) -> Result<McpStatus, String> {
+    let settings = app.state::<McpSettingsStore>().get()?;
+    start_mcp_http(&app, &settings)?;
+    mcp_status(&app)
+}
+
+#[tauri::command]
+pub async fn stop_mcp_server(app: AppHandle) -> Result<McpStatus, String> {
+    app.state::<McpHttpState>().stop();
+    mcp_status(&app)
+}
+
+fn start_mcp_http(app: &AppHandle, settings: &McpSettings) -> Result<String, String> {
+    let handle = app.clone();
+    let token = settings.token.clone();
+    app.state::<McpHttpState>()
+        .start(settings.port, token, move |message| {
+            // 每个请求读取最新设置，读取失败时使用默认设置
+            let settings = handle
+                .state::<McpSettingsStore>()
+                .get()
+                .unwrap_or_else(|e| {
+                    log::warn!("读取 MCP 设置失败: {}", e);
+                    McpSettings::default()
+                });
+            McpServer::new(&handle.state::<KnowledgeBaseService>(), settings)
+                .handle_message(message)
+        })
 }
@@ -2,6 +2,14 @@ pub mod commands;
 pub mod models;
 pub mod services;
 
+use services::ai_settings_service::AiSettingsService;
+use services::auto_sync::AutoSync;
+use services::generate_service::GenerationJobs;
+use services::knowledge_base_service::KnowledgeBaseService;
+use services::mcp_server::{self, McpHttpState, McpServer, McpSettingsStore};
+use services::prompt_template_service::PromptTemplateService;
+use tauri::Manager;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -15,6 +23,13 @@ pub fn run() {
                     .level(log::LevelFilter::Info)
                     .build(),
             )?;
+            app.manage(KnowledgeBaseService::new()?);
+            app.manage(GenerationJobs::default());
+            app.manage(PromptTemplateService::new()?);
+            app.manage(AiSettingsService::new()?);
+            app.manage(AutoSync::default());
+            app.manage(McpSettingsStore::new()?);
+            app.manage(McpHttpState::default());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -30,6 +45,12 @@ pub fn run() {
             commands::save_binary_file,
             commands::search_content,
             commands::export_markdown,
+            commands::export_batch,
+            commands::export_epub,
+            commands::export_slides,
+            commands::export_pptx,
+            commands::save_generate_result_as_pptx,
+            commands::publish_site,
             commands::create_new_file,
             commands::copy_file,
             commands::rename_file,
@@ -38,11 +59,69 @@ pub fn run() {
             commands::create_knowledge_base,
             commands::list_knowledge_bases,
             commands::get_knowledge_base,
+            commands::update_knowledge_base,
             commands::delete_knowledge_base,
             commands::import_documents,
+            commands::import_folder,
             commands::get_documents,
-            commands::delete_document
+            commands::list_document_summaries,
+            commands::get_document,
+            commands::list_tags,
+            commands::get_documents_by_tag,
+            commands::delete_document,
+            commands::move_documents,
+            commands::copy_documents,
+            commands::sync_knowledge_base,
+            commands::set_chunking_config,
+            commands::rechunk_knowledge_base,
+            commands::export_knowledge_base,
+            commands::import_knowledge_base,
+            commands::get_storage_status,
+            commands::restore_storage_backup,
+            commands::set_auto_sync,
+            commands::search_knowledge_base,
+            commands::embed_knowledge_base,
+            commands::search_knowledge_base_hybrid,
+            commands::generate_content,
+            commands::start_generation,
+            commands::cancel_generation,
+            commands::start_topic_report,
+            commands::resume_topic_report,
+            commands::list_topic_reports,
+            commands::delete_topic_report,
+            commands::ask_knowledge_base,
+            commands::list_conversations,
+            commands::get_conversation_messages,
+            commands::delete_conversation,
+            commands::list_prompt_templates,
+            commands::create_prompt_template,
+            commands::update_prompt_template,
+            commands::delete_prompt_template,
+            // AI settings commands
+            commands::list_ai_profiles,
+            commands::save_ai_profile,
+            commands::delete_ai_profile,
+            commands::set_active_ai_profile,
+            commands::test_ai_connection,
+            // MCP server commands
+            commands::get_mcp_status,
+            commands::save_mcp_settings,
+            commands::start_mcp_server,
+            commands::stop_mcp_server
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// 以标准输入输出运行 MCP 服务（`--mcp` 启动参数），不打开窗口
+pub fn run_mcp_stdio() -> Result<(), String> {
+    // 应用可能同时在运行，只读打开，不迁移或隔离数据库
+    let kb_service = KnowledgeBaseService::new_read_only()?;
+    let settings = McpSettingsStore::new()?.get()?;
+    let server = McpServer::new(&kb_service, settings);
+    mcp_server::serve_stdio(
+        |message| server.handle_message(message),
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+    )
+}
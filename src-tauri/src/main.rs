@@ -2,5 +2,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+  // 供外部 AI 代理以子进程方式启动 MCP 服务
+  if std::env::args().any(|arg| arg == "--mcp") {
+    if let Err(e) = bun_codeview_lib::run_mcp_stdio() {
+      eprintln!("MCP 服务异常退出: {}", e);
+      std::process::exit(1);
+    }
+    return;
+  }
   bun_codeview_lib::run();
 }
@@ -12,6 +12,63 @@ pub struct KnowledgeBase {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub document_count: usize,
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+    /// 图标（emoji 或图标名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// 标识颜色，`#RGB` 或 `#RRGGBB`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// 修改知识库信息的输入
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeBaseInput {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// 分块策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChunkingStrategyKind {
+    /// 按 Markdown 结构分块，不拆分代码块和表格
+    #[default]
+    Markdown,
+    /// 按空行分隔的段落分块
+    Paragraph,
+}
+
+/// 知识库的分块配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChunkingConfig {
+    pub strategy: ChunkingStrategyKind,
+    /// 每块的估算 token 上限
+    pub max_tokens: usize,
+    /// 相邻文本块重叠的估算 token 数
+    pub overlap_tokens: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkingStrategyKind::Markdown,
+            max_tokens: 512,
+            overlap_tokens: 64,
+        }
+    }
 }
 
 /// 文档
@@ -26,6 +83,93 @@ pub struct Document {
     pub chunks: Vec<Chunk>,
     pub metadata: DocumentMetadata,
     pub created_at: DateTime<Utc>,
+    /// 导入时文件内容的 SHA-256
+    #[serde(default)]
+    pub content_hash: String,
+    /// 导入时文件的修改时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Utc>>,
+    /// 源文件已被删除或移动
+    #[serde(default)]
+    pub source_missing: bool,
+}
+
+/// 文档摘要，列表中不包含正文和文本块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSummary {
+    pub id: String,
+    pub kb_id: String,
+    pub path: String,
+    pub title: String,
+    pub word_count: usize,
+    pub line_count: usize,
+    pub chunk_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<usize>,
+    /// 标签和关键词
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Utc>>,
+    pub source_missing: bool,
+}
+
+/// 文档列表的排序字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DocumentSortField {
+    Title,
+    Path,
+    #[default]
+    CreatedAt,
+    ModifiedAt,
+    WordCount,
+}
+
+/// 文档列表的分页、排序和筛选条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DocumentListQuery {
+    pub offset: usize,
+    /// 每页数量，取值 1-500
+    pub limit: usize,
+    pub sort_by: DocumentSortField,
+    pub descending: bool,
+    /// 按标题或路径筛选（不区分大小写）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    /// 按标签或关键词筛选（不区分大小写）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// 只返回源文件缺失（`true`）或存在（`false`）的文档
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_missing: Option<bool>,
+}
+
+impl Default for DocumentListQuery {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 50,
+            sort_by: DocumentSortField::CreatedAt,
+            descending: false,
+            search: None,
+            tag: None,
+            source_missing: None,
+        }
+    }
+}
+
+/// 一页文档摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentPage {
+    pub items: Vec<DocumentSummary>,
+    /// 符合筛选条件的文档总数
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
 }
 
 /// 文本块
@@ -37,6 +181,9 @@ pub struct Chunk {
     pub start_line: usize,
     pub end_line: usize,
     pub headings: Vec<String>,
+    /// 所在页码（从 1 开始），仅分页文档（PDF）有值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
 }
 
 /// 文档元数据
@@ -48,6 +195,34 @@ pub struct DocumentMetadata {
     pub headings: Vec<HeadingInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keywords: Option<Vec<String>>,
+    /// 页数，仅分页文档（PDF）有值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<usize>,
+    /// Front Matter 中声明的标签
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl DocumentMetadata {
+    /// 标签和自动提取的关键词，按小写去重
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.iter().chain(self.keywords.iter().flatten()) {
+            let tag = tag.to_lowercase();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+}
+
+/// 标签及其文档数量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
 }
 
 /// 标题信息
@@ -59,6 +234,160 @@ pub struct HeadingInfo {
     pub line: usize,
 }
 
+/// 文件夹导入选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FolderImportOptions {
+    /// 包含的文件（相对于文件夹的 glob），为空时导入所有支持的格式
+    pub include: Vec<String>,
+    /// 排除的文件或目录（glob）
+    pub exclude: Vec<String>,
+    /// 遵循 .gitignore / .ignore 规则
+    pub respect_ignore: bool,
+    /// 包含隐藏文件和目录
+    pub include_hidden: bool,
+}
+
+impl Default for FolderImportOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore: true,
+            include_hidden: false,
+        }
+    }
+}
+
+/// 单个文件的导入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatus {
+    Imported,
+    /// 已导入过且内容有变化，已更新
+    Updated,
+    /// 已导入过且内容未变，跳过
+    Duplicate,
+    Failed,
+}
+
+/// 文件导入明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFileResult {
+    pub path: String,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 文件夹导入结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub kb_id: String,
+    pub files: Vec<ImportFileResult>,
+}
+
+impl ImportReport {
+    /// 指定状态的文件数量
+    pub fn count(&self, status: ImportStatus) -> usize {
+        self.files.iter().filter(|f| f.status == status).count()
+    }
+}
+
+/// 导入进度事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub kb_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub path: String,
+}
+
+/// 同步失败的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedFile {
+    pub path: String,
+    pub error: String,
+}
+
+/// 数据库备份类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageBackupKind {
+    /// 迁移或恢复前自动生成的备份
+    Backup,
+    /// 无法打开而被隔离的数据库
+    Quarantined,
+}
+
+/// 知识库数据库备份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageBackup {
+    pub name: String,
+    pub kind: StorageBackupKind,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 知识库存储状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStatus {
+    pub schema_version: i32,
+    /// 本次启动时因损坏或无法迁移而被隔离的数据库
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantined: Option<String>,
+    pub backups: Vec<StorageBackup>,
+}
+
+/// 知识库同步结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub kb_id: String,
+    /// 内容有变化并已重新分块的文档路径
+    pub updated: Vec<String>,
+    /// 源文件缺失的文档路径
+    pub missing: Vec<String>,
+    /// 源文件重新出现的文档路径
+    pub restored: Vec<String>,
+    pub unchanged: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+impl SyncReport {
+    /// 本次同步是否改变了知识库内容或状态
+    pub fn has_changes(&self) -> bool {
+        !self.updated.is_empty() || !self.missing.is_empty() || !self.restored.is_empty()
+    }
+}
+
+/// 文本块搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkSearchResult {
+    pub document_id: String,
+    pub document_title: String,
+    pub path: String,
+    pub chunk_id: String,
+    pub content: String,
+    /// 带 `<mark>` 高亮的 HTML 摘要
+    pub snippets: Vec<String>,
+    pub headings: Vec<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    pub score: f64,
+}
+
 /// 生成请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -67,15 +396,86 @@ pub struct GenerateRequest {
     pub generate_type: GenerateType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub topic: Option<String>,
+    /// 输出语言，默认中文
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 篇幅要求，如“简短”“500 字左右”
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<String>,
+    /// 结果保存目录，默认为知识库数据目录下的 generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
 }
 
 /// 生成类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+///
+/// 序列化为提示词模板 ID：内置类型为 `summary`、`pptOutline`、`topicReport`，
+/// 其他值对应用户自定义模板。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum GenerateType {
     Summary,
     PptOutline,
     TopicReport,
+    Custom(String),
+}
+
+impl GenerateType {
+    /// 对应的提示词模板 ID
+    pub fn template_id(&self) -> &str {
+        match self {
+            GenerateType::Summary => "summary",
+            GenerateType::PptOutline => "pptOutline",
+            GenerateType::TopicReport => "topicReport",
+            GenerateType::Custom(id) => id,
+        }
+    }
+}
+
+impl From<String> for GenerateType {
+    fn from(id: String) -> Self {
+        match id.as_str() {
+            "summary" => GenerateType::Summary,
+            "pptOutline" => GenerateType::PptOutline,
+            "topicReport" => GenerateType::TopicReport,
+            _ => GenerateType::Custom(id),
+        }
+    }
+}
+
+impl From<GenerateType> for String {
+    fn from(generate_type: GenerateType) -> Self {
+        generate_type.template_id().to_string()
+    }
+}
+
+/// 提示词模板
+///
+/// 模板中可使用变量 `{{topic}}`、`{{context}}`、`{{language}}`、`{{length}}`、`{{kb_name}}`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub system_prompt: String,
+    pub user_prompt: String,
+    #[serde(default)]
+    pub built_in: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// 创建或更新提示词模板的输入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplateInput {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub system_prompt: String,
+    pub user_prompt: String,
 }
 
 /// 生成结果
@@ -87,6 +487,117 @@ pub struct GenerateResult {
     pub content: String,
 }
 
+/// 专题报告任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportJobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// 报告大纲中的章节，`content` 为空表示尚未撰写
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSection {
+    pub title: String,
+    #[serde(default)]
+    pub points: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// 报告大纲
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportOutline {
+    pub title: String,
+    pub sections: Vec<ReportSection>,
+}
+
+/// 专题报告任务
+///
+/// 每完成一步（检索、大纲、章节、汇总）保存一次，失败或取消后从未完成的步骤继续。
+/// `sources` 的顺序即正文中 `[n]` 的编号。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportJob {
+    pub id: String,
+    pub request: GenerateRequest,
+    pub output_dir: String,
+    pub sources: Vec<ChunkSearchResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outline: Option<ReportOutline>,
+    pub status: ReportJobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 问答请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatRequest {
+    pub kb_id: String,
+    /// 为空时新建对话
+    pub conversation_id: Option<String>,
+    pub question: String,
+    /// 检索的文本块数量
+    pub top_k: Option<usize>,
+}
+
+/// 回答中的引用，`index` 对应正文中的 `[n]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    pub index: usize,
+    pub chunk_id: String,
+    pub document_id: String,
+    pub document_title: String,
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub headings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+}
+
+/// 对话消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationMessage {
+    pub id: String,
+    /// `user` 或 `assistant`
+    pub role: String,
+    pub content: String,
+    pub citations: Vec<Citation>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 知识库对话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: String,
+    pub kb_id: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 问答结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatAnswer {
+    pub conversation: Conversation,
+    pub question: ConversationMessage,
+    pub answer: ConversationMessage,
+}
+
 /// AI 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -98,6 +609,9 @@ pub struct AiConfig {
     pub model: String,
     pub max_tokens: u32,
     pub temperature: f32,
+    /// Azure OpenAI 的 `api-version` 参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
 }
 
 impl Default for AiConfig {
@@ -109,6 +623,118 @@ impl Default for AiConfig {
             model: "gpt-4o".to_string(),
             max_tokens: 4096,
             temperature: 0.7,
+            api_version: None,
         }
     }
 }
+
+/// 嵌入配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingConfig {
+    /// `hashing`（离线）或 `openai`（OpenAI 兼容接口）
+    pub provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    pub base_url: String,
+    pub model: String,
+    /// 哈希嵌入的向量维度
+    pub dimensions: usize,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            provider: "hashing".to_string(),
+            api_key: None,
+            base_url: "https://api.openai.com/v1".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            dimensions: 512,
+        }
+    }
+}
+
+/// AI 配置方案，API Key 单独保存在密钥存储中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiProfile {
+    pub id: String,
+    pub name: String,
+    pub config: AiConfig,
+    #[serde(default)]
+    pub has_api_key: bool,
+}
+
+/// 保存 AI 配置方案的输入
+///
+/// `config.api_key` 为 `None` 时保留原有密钥，为空字符串时清除密钥。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiProfileInput {
+    pub name: String,
+    pub config: AiConfig,
+}
+
+/// AI 设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiSettings {
+    pub active_profile_id: Option<String>,
+    pub profiles: Vec<AiProfile>,
+}
+
+/// 连接测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTestResult {
+    pub success: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// MCP 服务设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpSettings {
+    /// 对外提供的工具，未列出的工具不可调用
+    pub enabled_tools: Vec<String>,
+    /// 允许搜索和读取的目录，为空时文件工具不可用
+    pub workspace_roots: Vec<String>,
+    /// HTTP 服务端口，只监听 127.0.0.1
+    pub port: u16,
+    /// HTTP 请求需携带的访问令牌（`Authorization: Bearer <token>`），首次读取设置时生成
+    pub token: String,
+}
+
+impl Default for McpSettings {
+    fn default() -> Self {
+        Self {
+            enabled_tools: Vec::new(),
+            workspace_roots: Vec::new(),
+            port: 7331,
+            token: String::new(),
+        }
+    }
+}
+
+/// MCP 工具说明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+}
+
+/// MCP HTTP 服务状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpStatus {
+    pub running: bool,
+    /// 运行中时的服务地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub settings: McpSettings,
+    pub tools: Vec<McpToolInfo>,
+}
@@ -0,0 +1,316 @@
+//! AI 设置服务
+//! 管理多个 AI 配置方案，方案保存在 ai_settings.json，API Key 交给密钥存储
+
+use crate::models::{AiConfig, AiProfile, AiProfileInput, AiSettings, ConnectionTestResult};
+use crate::services::llm_client::LlmClient;
+use crate::services::secret_store::{FallbackSecretStore, SecretStore};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+const SETTINGS_FILE: &str = "ai_settings.json";
+
+/// 默认提供的配置方案
+fn default_settings() -> AiSettings {
+    AiSettings {
+        active_profile_id: Some("openai".to_string()),
+        profiles: vec![
+            AiProfile {
+                id: "openai".to_string(),
+                name: "OpenAI".to_string(),
+                config: AiConfig::default(),
+                has_api_key: false,
+            },
+            AiProfile {
+                id: "ollama".to_string(),
+                name: "Ollama（本地）".to_string(),
+                config: AiConfig {
+                    provider: "ollama".to_string(),
+                    base_url: "http://localhost:11434/v1".to_string(),
+                    model: "llama3.1".to_string(),
+                    ..AiConfig::default()
+                },
+                has_api_key: false,
+            },
+        ],
+    }
+}
+
+fn secret_id(profile_id: &str) -> String {
+    format!("ai-profile:{}", profile_id)
+}
+
+/// AI 设置服务
+pub struct AiSettingsService {
+    path: PathBuf,
+    secrets: Box<dyn SecretStore>,
+    lock: Mutex<()>,
+}
+
+impl AiSettingsService {
+    /// 创建服务实例
+    pub fn new() -> Result<Self, String> {
+        let storage_dir = dirs::data_dir()
+            .ok_or("无法获取数据目录")?
+            .join("bun-codeview");
+
+        let secrets = Box::new(FallbackSecretStore::new(&storage_dir));
+        Self::open(&storage_dir, secrets)
+    }
+
+    /// 使用指定目录和密钥存储
+    pub fn open(storage_dir: &Path, secrets: Box<dyn SecretStore>) -> Result<Self, String> {
+        fs::create_dir_all(storage_dir).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path: storage_dir.join(SETTINGS_FILE),
+            secrets,
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn load(&self) -> Result<AiSettings, String> {
+        if !self.path.exists() {
+            return Ok(default_settings());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("AI 设置解析失败: {}", e))
+    }
+
+    fn save(&self, settings: &AiSettings) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+
+    /// 获取全部配置方案
+    pub fn list(&self) -> Result<AiSettings, String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "AI 设置锁已损坏".to_string())?;
+        self.load()
+    }
+
+    /// 新建（`id` 为空）或更新配置方案
+    pub fn save_profile(
+        &self,
+        id: Option<&str>,
+        input: AiProfileInput,
+    ) -> Result<AiProfile, String> {
+        if input.name.trim().is_empty() {
+            return Err("配置名称不能为空".to_string());
+        }
+        if input.config.base_url.trim().is_empty() {
+            return Err("接口地址不能为空".to_string());
+        }
+        if input.config.model.trim().is_empty() {
+            return Err("模型名称不能为空".to_string());
+        }
+
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "AI 设置锁已损坏".to_string())?;
+        let mut settings = self.load()?;
+
+        let position = match id {
+            Some(id) => Some(
+                settings
+                    .profiles
+                    .iter()
+                    .position(|p| p.id == id)
+                    .ok_or_else(|| format!("配置不存在: {}", id))?,
+            ),
+            None => None,
+        };
+        let id = id
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let mut config = input.config;
+        let mut has_api_key = position.is_some_and(|idx| settings.profiles[idx].has_api_key);
+        match config.api_key.take() {
+            Some(key) if !key.trim().is_empty() => {
+                self.secrets.set(&secret_id(&id), key.trim())?;
+                has_api_key = true;
+            }
+            Some(_) => {
+                self.secrets.delete(&secret_id(&id))?;
+                has_api_key = false;
+            }
+            None => {}
+        }
+
+        let profile = AiProfile {
+            id: id.clone(),
+            name: input.name.trim().to_string(),
+            config,
+            has_api_key,
+        };
+        match position {
+            Some(idx) => settings.profiles[idx] = profile.clone(),
+            None => settings.profiles.push(profile.clone()),
+        }
+        if settings.active_profile_id.is_none() {
+            settings.active_profile_id = Some(id);
+        }
+        self.save(&settings)?;
+        Ok(profile)
+    }
+
+    /// 删除配置方案及其密钥
+    pub fn delete_profile(&self, id: &str) -> Result<(), String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "AI 设置锁已损坏".to_string())?;
+        let mut settings = self.load()?;
+
+        let before = settings.profiles.len();
+        settings.profiles.retain(|p| p.id != id);
+        if settings.profiles.len() == before {
+            return Err(format!("配置不存在: {}", id));
+        }
+        self.secrets.delete(&secret_id(id))?;
+
+        if settings.active_profile_id.as_deref() == Some(id) {
+            settings.active_profile_id = settings.profiles.first().map(|p| p.id.clone());
+        }
+        self.save(&settings)
+    }
+
+    /// 设置当前使用的配置方案
+    pub fn set_active_profile(&self, id: &str) -> Result<(), String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "AI 设置锁已损坏".to_string())?;
+        let mut settings = self.load()?;
+        if !settings.profiles.iter().any(|p| p.id == id) {
+            return Err(format!("配置不存在: {}", id));
+        }
+        settings.active_profile_id = Some(id.to_string());
+        self.save(&settings)
+    }
+
+    /// 取出指定（默认为当前）配置方案的完整配置，包含 API Key
+    pub fn resolve_config(&self, id: Option<&str>) -> Result<AiConfig, String> {
+        let settings = self.list()?;
+        let id = id
+            .or(settings.active_profile_id.as_deref())
+            .ok_or("尚未配置 AI 服务")?;
+        let profile = settings
+            .profiles
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("配置不存在: {}", id))?;
+
+        let mut config = profile.config.clone();
+        if profile.has_api_key {
+            config.api_key = self.secrets.get(&secret_id(id))?;
+        }
+        Ok(config)
+    }
+}
+
+/// 测试 AI 服务连接
+pub fn test_connection(config: &AiConfig) -> ConnectionTestResult {
+    let started = Instant::now();
+    let result = LlmClient::new(config).ping();
+    ConnectionTestResult {
+        success: result.is_ok(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: result.err(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::secret_store::FileSecretStore;
+    use crate::services::test_support::{StubResponse, StubServer};
+
+    fn open_service(dir: &Path) -> AiSettingsService {
+        AiSettingsService::open(dir, Box::new(FileSecretStore::new(dir))).unwrap()
+    }
+
+    #[test]
+    fn test_profiles_keep_keys_out_of_settings_file() {
+        let dir = std::env::temp_dir().join(format!("ai-settings-test-{}", Uuid::new_v4()));
+        let service = open_service(&dir);
+        assert_eq!(service.list().unwrap().profiles.len(), 2);
+
+        let azure = service
+            .save_profile(
+                None,
+                AiProfileInput {
+                    name: "Azure".to_string(),
+                    config: AiConfig {
+                        provider: "azure".to_string(),
+                        base_url: "https://demo.openai.azure.com/openai/deployments/gpt-4o"
+                            .to_string(),
+                        api_key: Some("azure-secret".to_string()),
+                        ..AiConfig::default()
+                    },
+                },
+            )
+            .unwrap();
+        assert!(azure.has_api_key);
+        assert!(!fs::read_to_string(dir.join(SETTINGS_FILE))
+            .unwrap()
+            .contains("azure-secret"));
+
+        // 未提供密钥时保留原值，空字符串清除
+        let mut input = AiProfileInput {
+            name: "Azure 东区".to_string(),
+            config: azure.config.clone(),
+        };
+        service
+            .save_profile(Some(&azure.id), input.clone())
+            .unwrap();
+        service.set_active_profile(&azure.id).unwrap();
+        let config = open_service(&dir).resolve_config(None).unwrap();
+        assert_eq!(config.api_key.as_deref(), Some("azure-secret"));
+        assert_eq!(config.provider, "azure");
+
+        input.config.api_key = Some(String::new());
+        assert!(
+            !service
+                .save_profile(Some(&azure.id), input)
+                .unwrap()
+                .has_api_key
+        );
+        assert_eq!(service.resolve_config(None).unwrap().api_key, None);
+
+        service.delete_profile(&azure.id).unwrap();
+        let settings = service.list().unwrap();
+        assert_eq!(settings.active_profile_id.as_deref(), Some("openai"));
+        assert!(service.set_active_profile(&azure.id).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_connection_against_stub_server() {
+        let server = StubServer::start(vec![
+            StubResponse::json(r#"{"choices":[{"message":{"role":"assistant","content":"p"}}]}"#),
+            StubResponse::status(404, r#"{"error":"model not found"}"#),
+        ]);
+        let config = AiConfig {
+            provider: "ollama".to_string(),
+            base_url: format!("{}/v1", server.url),
+            model: "llama3.1".to_string(),
+            ..AiConfig::default()
+        };
+
+        assert!(test_connection(&config).success);
+        let failed = test_connection(&config);
+        assert!(!failed.success);
+        assert!(failed.error.unwrap().contains("model not found"));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/chat/completions");
+        assert_eq!(requests[0].header("authorization"), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! 知识库后台自动同步
+//! 按固定间隔在后台线程中执行同步，可随时停止或以新间隔重启
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 默认同步间隔（秒）
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+/// 最短同步间隔（秒）
+pub const MIN_INTERVAL_SECS: u64 = 10;
+/// 检查停止标记的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 自动同步状态，作为 Tauri 托管状态保存当前后台线程的停止标记
+#[derive(Default)]
+pub struct AutoSync {
+    stop: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl AutoSync {
+    /// 启动后台同步，已在运行时先停止旧的线程
+    pub fn start(&self, interval: Duration, mut tick: impl FnMut() + Send + 'static) {
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Ok(mut current) = self.stop.lock() {
+            if let Some(previous) = current.replace(stop.clone()) {
+                previous.store(true, Ordering::Relaxed);
+            }
+        }
+
+        std::thread::spawn(move || loop {
+            let deadline = Instant::now() + interval;
+            while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                std::thread::sleep(remaining.min(POLL_INTERVAL));
+            }
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            tick();
+        });
+    }
+
+    /// 停止后台同步，未在运行时返回 false
+    pub fn stop(&self) -> bool {
+        self.stop
+            .lock()
+            .ok()
+            .and_then(|mut current| current.take())
+            .map(|stop| stop.store(true, Ordering::Relaxed))
+            .is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stop
+            .lock()
+            .map(|current| current.is_some())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_auto_sync_ticks_until_stopped() {
+        let auto_sync = AutoSync::default();
+        let (tx, rx) = mpsc::channel();
+        auto_sync.start(Duration::from_millis(10), move || {
+            let _ = tx.send(());
+        });
+        assert!(auto_sync.is_running());
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert!(auto_sync.stop());
+        assert!(!auto_sync.stop());
+        // 停止后线程退出，发送端随之释放
+        while rx.recv_timeout(Duration::from_secs(5)).is_ok() {}
+        assert!(matches!(
+            rx.try_recv(),
+            Err(mpsc::TryRecvError::Disconnected)
+        ));
+    }
+}
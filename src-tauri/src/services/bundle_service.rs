@@ -0,0 +1,499 @@
+//! 知识库分享包
+//! 将知识库导出为单个 zip 文件，包含清单、文档与文本块、向量和源文件；
+//! 导入时重新分配所有 ID，源文件解压到数据目录下
+
+use crate::models::{ChunkingConfig, Document, KnowledgeBase};
+use crate::services::embedding_service;
+use crate::services::knowledge_base_service::{KnowledgeBaseService, StoredEmbedding};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// 分享包格式版本，格式不兼容时递增
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DOCUMENTS_FILE: &str = "documents.json";
+const EMBEDDINGS_FILE: &str = "embeddings.json";
+const SOURCES_DIR: &str = "sources/";
+
+/// 分享包清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleManifest {
+    schema_version: u32,
+    app_version: String,
+    exported_at: DateTime<Utc>,
+    knowledge_base: BundleKnowledgeBase,
+    documents: Vec<BundleDocument>,
+    /// 包含向量的嵌入提供方
+    #[serde(default)]
+    embedding_providers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleKnowledgeBase {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    chunking: ChunkingConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+/// 文档与其源文件在包内的位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleDocument {
+    id: String,
+    /// 导出时的源文件路径
+    path: String,
+    /// 源文件在包内的路径，导出时源文件不可读则为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+/// 文本块向量，小端 f32 字节按 Base64 编码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleEmbedding {
+    chunk_id: String,
+    provider: String,
+    vector: String,
+}
+
+pub struct BundleService;
+
+impl BundleService {
+    /// 将知识库导出为分享包
+    pub fn export(
+        kb_service: &KnowledgeBaseService,
+        kb_id: &str,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let kb = kb_service
+            .get_knowledge_base(kb_id)?
+            .ok_or("知识库不存在")?;
+        let documents = kb_service.get_documents(kb_id)?;
+        let embeddings = kb_service.list_embeddings(kb_id)?;
+
+        let root = common_parent(documents.iter().map(|doc| Path::new(&doc.path)));
+        let mut sources = Vec::new();
+        let mut manifest_documents = Vec::with_capacity(documents.len());
+        for (index, doc) in documents.iter().enumerate() {
+            let path = Path::new(&doc.path);
+            let source = match fs::read(path) {
+                Ok(data) => {
+                    let entry = source_entry(root.as_deref(), path, index);
+                    sources.push((entry.clone(), data));
+                    Some(entry)
+                }
+                Err(e) => {
+                    log::warn!("源文件无法读取，仅导出解析后的内容 {}: {}", doc.path, e);
+                    None
+                }
+            };
+            manifest_documents.push(BundleDocument {
+                id: doc.id.clone(),
+                path: doc.path.clone(),
+                source,
+            });
+        }
+
+        let mut providers: Vec<String> = embeddings.iter().map(|e| e.provider.clone()).collect();
+        providers.dedup();
+        let manifest = BundleManifest {
+            schema_version: BUNDLE_SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Utc::now(),
+            knowledge_base: BundleKnowledgeBase {
+                name: kb.name,
+                description: kb.description,
+                created_at: kb.created_at,
+                chunking: kb.chunking,
+                icon: kb.icon,
+                color: kb.color,
+                tags: kb.tags,
+            },
+            documents: manifest_documents,
+            embedding_providers: providers,
+        };
+        let embeddings: Vec<BundleEmbedding> = embeddings
+            .into_iter()
+            .map(|e| BundleEmbedding {
+                chunk_id: e.chunk_id,
+                provider: e.provider,
+                vector: STANDARD.encode(embedding_service::vector_to_bytes(&e.vector)),
+            })
+            .collect();
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = fs::File::create(output_path).map_err(|e| e.to_string())?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default();
+        let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        write_entry(&mut zip, MANIFEST_FILE, options, &manifest)?;
+        let documents = serde_json::to_vec(&documents).map_err(|e| e.to_string())?;
+        write_entry(&mut zip, DOCUMENTS_FILE, options, &documents)?;
+        if !embeddings.is_empty() {
+            let embeddings = serde_json::to_vec(&embeddings).map_err(|e| e.to_string())?;
+            write_entry(&mut zip, EMBEDDINGS_FILE, options, &embeddings)?;
+        }
+        for (entry, data) in &sources {
+            write_entry(&mut zip, entry, options, data)?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 从分享包导入为新的知识库
+    pub fn import(
+        kb_service: &KnowledgeBaseService,
+        bundle_path: &Path,
+    ) -> Result<KnowledgeBase, String> {
+        let file = fs::File::open(bundle_path).map_err(|e| e.to_string())?;
+        let mut archive = ZipArchive::new(file).map_err(|e| format!("分享包无效: {}", e))?;
+
+        let manifest: BundleManifest =
+            serde_json::from_slice(&read_entry(&mut archive, MANIFEST_FILE)?)
+                .map_err(|e| format!("分享包清单无效: {}", e))?;
+        if manifest.schema_version > BUNDLE_SCHEMA_VERSION {
+            return Err(format!(
+                "分享包版本 {} 高于当前支持的版本 {}，请升级应用",
+                manifest.schema_version, BUNDLE_SCHEMA_VERSION
+            ));
+        }
+        let mut documents: Vec<Document> =
+            serde_json::from_slice(&read_entry(&mut archive, DOCUMENTS_FILE)?)
+                .map_err(|e| format!("分享包文档数据无效: {}", e))?;
+        let embeddings: Vec<BundleEmbedding> = if archive.index_for_name(EMBEDDINGS_FILE).is_some()
+        {
+            serde_json::from_slice(&read_entry(&mut archive, EMBEDDINGS_FILE)?)
+                .map_err(|e| format!("分享包向量数据无效: {}", e))?
+        } else {
+            Vec::new()
+        };
+
+        let now = Utc::now();
+        let kb = KnowledgeBase {
+            id: Uuid::new_v4().to_string(),
+            name: manifest.knowledge_base.name,
+            description: manifest.knowledge_base.description,
+            created_at: manifest.knowledge_base.created_at,
+            updated_at: now,
+            document_count: 0,
+            chunking: manifest.knowledge_base.chunking,
+            icon: manifest.knowledge_base.icon,
+            color: manifest.knowledge_base.color,
+            tags: manifest.knowledge_base.tags,
+        };
+        let sources: HashMap<&str, &str> = manifest
+            .documents
+            .iter()
+            .filter_map(|doc| Some((doc.id.as_str(), doc.source.as_deref()?)))
+            .collect();
+
+        let sources_dir = kb_service.shared_sources_dir(&kb.id);
+        let result = remap_documents(&mut archive, &kb.id, &mut documents, &sources, &sources_dir)
+            .and_then(|chunk_ids| {
+                let embeddings: Vec<StoredEmbedding> = embeddings
+                    .iter()
+                    .filter_map(|e| {
+                        let bytes = STANDARD.decode(&e.vector).ok()?;
+                        Some(StoredEmbedding {
+                            chunk_id: chunk_ids.get(&e.chunk_id)?.clone(),
+                            provider: e.provider.clone(),
+                            vector: embedding_service::vector_from_bytes(&bytes),
+                        })
+                    })
+                    .collect();
+                kb_service.restore_knowledge_base(kb, &documents, &embeddings)
+            });
+        if result.is_err() && sources_dir.exists() {
+            let _ = fs::remove_dir_all(&sources_dir);
+        }
+        result
+    }
+}
+
+/// 为文档和文本块重新分配 ID 并解压源文件，返回旧文本块 ID 到新 ID 的映射
+fn remap_documents(
+    archive: &mut ZipArchive<fs::File>,
+    kb_id: &str,
+    documents: &mut [Document],
+    sources: &HashMap<&str, &str>,
+    sources_dir: &Path,
+) -> Result<HashMap<String, String>, String> {
+    let mut chunk_ids = HashMap::new();
+    for doc in documents {
+        if let Some(entry) = sources.get(doc.id.as_str()) {
+            let target = extract_source(archive, entry, sources_dir)?;
+            doc.modified_at = fs::metadata(&target)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .map(DateTime::<Utc>::from);
+            doc.path = target.to_string_lossy().to_string();
+        }
+        doc.source_missing = !Path::new(&doc.path).exists();
+        doc.id = Uuid::new_v4().to_string();
+        doc.kb_id = kb_id.to_string();
+        for chunk in &mut doc.chunks {
+            let id = Uuid::new_v4().to_string();
+            chunk_ids.insert(std::mem::replace(&mut chunk.id, id.clone()), id);
+        }
+    }
+    Ok(chunk_ids)
+}
+
+fn write_entry(
+    zip: &mut ZipWriter<fs::File>,
+    name: &str,
+    options: SimpleFileOptions,
+    data: &[u8],
+) -> Result<(), String> {
+    zip.start_file(name, options).map_err(|e| e.to_string())?;
+    zip.write_all(data).map_err(|e| e.to_string())
+}
+
+fn read_entry(archive: &mut ZipArchive<fs::File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("分享包缺少 {}", name))?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+/// 将包内的源文件解压到目录下，只接受不含 `..` 的相对路径
+fn extract_source(
+    archive: &mut ZipArchive<fs::File>,
+    entry: &str,
+    dir: &Path,
+) -> Result<PathBuf, String> {
+    let relative = entry
+        .strip_prefix(SOURCES_DIR)
+        .filter(|rest| {
+            !rest.is_empty()
+                && Path::new(rest)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+        })
+        .ok_or_else(|| format!("分享包中的源文件路径无效: {}", entry))?;
+    let target = dir.join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&target, read_entry(archive, entry)?).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+/// 所有源文件的公共上级目录
+fn common_parent<'a>(paths: impl Iterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut common: Option<PathBuf> = None;
+    for path in paths {
+        let parent = path.parent()?;
+        common = Some(match common {
+            None => parent.to_path_buf(),
+            Some(current) => current
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common
+}
+
+/// 源文件在包内的路径：保留相对于公共目录的结构，无法取得相对路径时按序号命名
+fn source_entry(root: Option<&Path>, path: &Path, index: usize) -> String {
+    let relative: Vec<String> = root
+        .and_then(|root| path.strip_prefix(root).ok())
+        .map(|rest| {
+            rest.components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if relative.is_empty() {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "document".to_string());
+        format!("{}{}/{}", SOURCES_DIR, index, name)
+    } else {
+        format!("{}{}", SOURCES_DIR, relative.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::embedding_service::{EmbeddingProvider, HashingEmbedder};
+
+    #[test]
+    fn test_export_and_import_bundle() {
+        let dir = std::env::temp_dir().join(format!("bundle-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("notes/sub")).unwrap();
+        let rust = dir.join("notes/rust.md");
+        let sqlite = dir.join("notes/sub/sqlite.md");
+        fs::write(&rust, "# 所有权\n\n所有权规则由编译器检查。").unwrap();
+        fs::write(&sqlite, "# 数据库\n\nSQLite 的 WAL 模式。").unwrap();
+
+        let source = KnowledgeBaseService::open(&dir.join("source")).unwrap();
+        let kb = source
+            .create_knowledge_base("Rust".to_string(), Some("笔记".to_string()))
+            .unwrap();
+        source
+            .import_documents(
+                &kb.id,
+                &[
+                    rust.to_string_lossy().to_string(),
+                    sqlite.to_string_lossy().to_string(),
+                ],
+            )
+            .unwrap();
+        let provider = HashingEmbedder::new(16);
+        let embedded = source.embed_knowledge_base(&kb.id, &provider).unwrap();
+
+        let bundle = dir.join("out/rust.zip");
+        BundleService::export(&source, &kb.id, &bundle).unwrap();
+        let mut archive = ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+        assert!(archive.index_for_name("sources/rust.md").is_some());
+        assert!(archive.index_for_name("sources/sub/sqlite.md").is_some());
+        let manifest: BundleManifest =
+            serde_json::from_slice(&read_entry(&mut archive, MANIFEST_FILE).unwrap()).unwrap();
+        assert_eq!(manifest.schema_version, BUNDLE_SCHEMA_VERSION);
+        assert_eq!(manifest.embedding_providers, vec![provider.id()]);
+
+        // 导入到另一个数据目录，重名时自动加序号
+        let target = KnowledgeBaseService::open(&dir.join("target")).unwrap();
+        target
+            .create_knowledge_base("Rust".to_string(), None)
+            .unwrap();
+        let imported = BundleService::import(&target, &bundle).unwrap();
+        assert_eq!(imported.name, "Rust (2)");
+        assert_eq!(imported.description.as_deref(), Some("笔记"));
+        assert_eq!(imported.document_count, 2);
+        assert_ne!(imported.id, kb.id);
+
+        let original = source.get_documents(&kb.id).unwrap();
+        let docs = target.get_documents(&imported.id).unwrap();
+        let sources_dir = target.shared_sources_dir(&imported.id);
+        for doc in &docs {
+            assert!(Path::new(&doc.path).starts_with(&sources_dir));
+            assert!(!doc.source_missing);
+            assert!(original.iter().all(|o| o.id != doc.id));
+            let before = original.iter().find(|o| o.title == doc.title).unwrap();
+            assert_eq!(doc.content, before.content);
+            assert_eq!(doc.chunks.len(), before.chunks.len());
+            assert!(doc
+                .chunks
+                .iter()
+                .all(|c| before.chunks.iter().all(|b| b.id != c.id)));
+        }
+        let vectors = target.get_embeddings(&imported.id, &provider.id()).unwrap();
+        assert_eq!(vectors.len(), embedded);
+        assert!(docs
+            .iter()
+            .flat_map(|doc| &doc.chunks)
+            .all(|chunk| vectors.contains_key(&chunk.id)));
+
+        // 源文件已随包导入，同步时没有变化
+        let report = target.sync_knowledge_base(&imported.id).unwrap();
+        assert!(report.updated.is_empty() && report.missing.is_empty());
+
+        // 删除知识库时一并删除解压的源文件
+        target.delete_knowledge_base(&imported.id).unwrap();
+        assert!(!sources_dir.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_newer_schema_and_unsafe_paths() {
+        let dir = std::env::temp_dir().join(format!("bundle-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+
+        let write_bundle = |path: &Path, schema_version: u32, source: &str| {
+            let manifest = BundleManifest {
+                schema_version,
+                app_version: "0.0.0".to_string(),
+                exported_at: Utc::now(),
+                knowledge_base: BundleKnowledgeBase {
+                    name: "KB".to_string(),
+                    description: None,
+                    created_at: Utc::now(),
+                    chunking: ChunkingConfig::default(),
+                    icon: None,
+                    color: None,
+                    tags: Vec::new(),
+                },
+                documents: vec![BundleDocument {
+                    id: "doc".to_string(),
+                    path: "/tmp/a.md".to_string(),
+                    source: Some(source.to_string()),
+                }],
+                embedding_providers: Vec::new(),
+            };
+            let document = serde_json::json!([{
+                "id": "doc", "kbId": "kb", "path": "/tmp/a.md", "title": "a", "content": "x",
+                "chunks": [], "createdAt": Utc::now(), "contentHash": "",
+                "metadata": { "wordCount": 1, "lineCount": 1, "headings": [] }
+            }]);
+            let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+            let options = SimpleFileOptions::default();
+            write_entry(
+                &mut zip,
+                MANIFEST_FILE,
+                options,
+                &serde_json::to_vec(&manifest).unwrap(),
+            )
+            .unwrap();
+            write_entry(
+                &mut zip,
+                DOCUMENTS_FILE,
+                options,
+                &serde_json::to_vec(&document).unwrap(),
+            )
+            .unwrap();
+            write_entry(&mut zip, source, options, b"x").unwrap();
+            zip.finish().unwrap();
+        };
+
+        let newer = dir.join("newer.zip");
+        write_bundle(&newer, BUNDLE_SCHEMA_VERSION + 1, "sources/a.md");
+        assert!(BundleService::import(&service, &newer)
+            .unwrap_err()
+            .contains("版本"));
+
+        let unsafe_path = dir.join("unsafe.zip");
+        write_bundle(&unsafe_path, BUNDLE_SCHEMA_VERSION, "sources/../../evil.md");
+        assert!(BundleService::import(&service, &unsafe_path).is_err());
+        assert!(service.list_knowledge_bases().unwrap().is_empty());
+        assert!(!dir.join("evil.md").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+//! 知识库问答服务
+//! 检索与问题相关的文本块，连同对话历史发送给大模型，并将回答中的 `[n]` 引用映射回源文件位置
+
+use crate::models::{
+    AiConfig, ChatAnswer, ChatRequest, ChunkSearchResult, Citation, ConversationMessage,
+};
+use crate::services::generate_service::{GenerateService, MAX_CONTEXT_CHARS};
+use crate::services::knowledge_base_service::KnowledgeBaseService;
+use crate::services::llm_client::{ChatMessage, LlmClient};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// 默认检索的文本块数量
+pub const DEFAULT_TOP_K: usize = 6;
+/// 随问题发送的历史消息条数上限
+const MAX_HISTORY_MESSAGES: usize = 10;
+/// 对话标题的字符上限
+const TITLE_CHARS: usize = 30;
+
+const SYSTEM_PROMPT: &str =
+    "你是一名知识库问答助理。只依据本轮提供的资料回答问题，资料不足以回答时直接说明，不要编造。\
+回答中的每一处事实陈述都要在句末用 [编号] 标注来源，编号对应资料前的序号，多个来源写作 [1][3]。";
+
+pub struct ChatService;
+
+impl ChatService {
+    /// 回答问题并保存本轮问答
+    pub fn ask(
+        kb_service: &KnowledgeBaseService,
+        request: &ChatRequest,
+        config: &AiConfig,
+    ) -> Result<ChatAnswer, String> {
+        let question = request.question.trim();
+        if question.is_empty() {
+            return Err("问题不能为空".to_string());
+        }
+        let kb = kb_service
+            .get_knowledge_base(&request.kb_id)?
+            .ok_or("知识库不存在")?;
+
+        let history = match request.conversation_id.as_deref() {
+            Some(id) => {
+                let conversation = kb_service
+                    .get_conversation(id)?
+                    .filter(|c| c.kb_id == kb.id)
+                    .ok_or("对话不存在")?;
+                kb_service.get_conversation_messages(&conversation.id)?
+            }
+            None => Vec::new(),
+        };
+
+        let documents = kb_service.get_documents(&kb.id)?;
+        let chunks: Vec<ChunkSearchResult> =
+            GenerateService::select_chunks(&documents, Some(question), MAX_CONTEXT_CHARS)
+                .into_iter()
+                .take(request.top_k.unwrap_or(DEFAULT_TOP_K).max(1))
+                .collect();
+        if chunks.is_empty() {
+            return Err("知识库中没有可用的文档内容".to_string());
+        }
+
+        let messages = Self::build_messages(&history, question, &chunks);
+        let content = LlmClient::new(config).chat(&messages)?;
+        let citations = extract_citations(&content, &chunks);
+
+        let question = ConversationMessage {
+            id: Uuid::new_v4().to_string(),
+            role: "user".to_string(),
+            content: question.to_string(),
+            citations: Vec::new(),
+            created_at: Utc::now(),
+        };
+        let answer = ConversationMessage {
+            id: Uuid::new_v4().to_string(),
+            role: "assistant".to_string(),
+            content,
+            citations,
+            created_at: Utc::now(),
+        };
+        let conversation = kb_service.append_messages(
+            &kb.id,
+            request.conversation_id.as_deref(),
+            &conversation_title(&question.content),
+            &[question.clone(), answer.clone()],
+        )?;
+
+        Ok(ChatAnswer {
+            conversation,
+            question,
+            answer,
+        })
+    }
+
+    /// 组装消息：系统提示、最近的对话历史、本轮资料和问题
+    pub fn build_messages(
+        history: &[ConversationMessage],
+        question: &str,
+        chunks: &[ChunkSearchResult],
+    ) -> Vec<ChatMessage> {
+        let mut messages = vec![ChatMessage::system(SYSTEM_PROMPT)];
+        let skip = history.len().saturating_sub(MAX_HISTORY_MESSAGES);
+        messages.extend(history[skip..].iter().map(|m| match m.role.as_str() {
+            "assistant" => ChatMessage::assistant(m.content.clone()),
+            _ => ChatMessage::user(m.content.clone()),
+        }));
+        messages.push(ChatMessage::user(format!(
+            "资料：\n\n{}\n\n问题：{}",
+            GenerateService::format_context(chunks),
+            question
+        )));
+        messages
+    }
+}
+
+/// 提取回答中的 `[n]`（含 `[1, 2]`、`[1、2]`）引用，按首次出现顺序去重并映射到文本块
+pub fn extract_citations(answer: &str, chunks: &[ChunkSearchResult]) -> Vec<Citation> {
+    let mut indexes: Vec<usize> = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let inner = &rest[..end];
+        let numbers: Option<Vec<usize>> = inner
+            .split([',', '，', '、'])
+            .map(|n| n.trim().parse().ok())
+            .collect();
+        if let Some(numbers) = numbers {
+            for n in numbers {
+                if (1..=chunks.len()).contains(&n) && !indexes.contains(&n) {
+                    indexes.push(n);
+                }
+            }
+        }
+    }
+
+    indexes
+        .into_iter()
+        .map(|index| {
+            let chunk = &chunks[index - 1];
+            Citation {
+                index,
+                chunk_id: chunk.chunk_id.clone(),
+                document_id: chunk.document_id.clone(),
+                document_title: chunk.document_title.clone(),
+                path: chunk.path.clone(),
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                headings: chunk.headings.clone(),
+                page: chunk.page,
+            }
+        })
+        .collect()
+}
+
+/// 用第一个问题作为对话标题
+fn conversation_title(question: &str) -> String {
+    let line = question.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > TITLE_CHARS {
+        format!("{}…", line.chars().take(TITLE_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{StubResponse, StubServer};
+    use std::fs;
+
+    #[test]
+    fn test_extract_citations() {
+        let chunk = |id: &str| ChunkSearchResult {
+            document_id: "doc".to_string(),
+            document_title: "笔记".to_string(),
+            path: "/notes/a.md".to_string(),
+            chunk_id: id.to_string(),
+            content: String::new(),
+            snippets: Vec::new(),
+            headings: Vec::new(),
+            start_line: 0,
+            end_line: 3,
+            page: None,
+            score: 0.0,
+        };
+        let chunks = vec![chunk("a"), chunk("b"), chunk("c")];
+
+        let citations = extract_citations(
+            "甲 [2]。乙 [1, 2]，丙 [3、9]。见 [链接](http://x) 和 [0][x]",
+            &chunks,
+        );
+        let ids: Vec<(usize, &str)> = citations
+            .iter()
+            .map(|c| (c.index, c.chunk_id.as_str()))
+            .collect();
+        assert_eq!(ids, vec![(2, "b"), (1, "a"), (3, "c")]);
+    }
+
+    #[test]
+    fn test_ask_persists_conversation() {
+        let dir = std::env::temp_dir().join(format!("chat-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("rust.md");
+        fs::write(
+            &note,
+            "# Rust\n\n所有权系统保证内存安全。\n---\n## 并发\n\n借用检查器阻止数据竞争。",
+        )
+        .unwrap();
+
+        let kb_service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = kb_service
+            .create_knowledge_base("Rust".to_string(), None)
+            .unwrap();
+        kb_service
+            .import_document(&kb.id, &note.to_string_lossy())
+            .unwrap();
+
+        let server = StubServer::start(vec![
+            StubResponse::json(
+                r#"{"choices":[{"message":{"role":"assistant","content":"借用检查器阻止数据竞争 [1]。"}}]}"#,
+            ),
+            StubResponse::json(
+                r#"{"choices":[{"message":{"role":"assistant","content":"所有权保证内存安全 [1]。"}}]}"#,
+            ),
+        ]);
+        let config = AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        };
+
+        let mut request = ChatRequest {
+            kb_id: kb.id.clone(),
+            conversation_id: None,
+            question: "如何避免数据竞争？".to_string(),
+            top_k: None,
+        };
+        let first = ChatService::ask(&kb_service, &request, &config).unwrap();
+        assert_eq!(first.conversation.title, "如何避免数据竞争？");
+        let citation = &first.answer.citations[0];
+        assert_eq!(citation.path, note.to_string_lossy());
+        assert_eq!((citation.start_line, citation.end_line), (4, 6));
+        assert_eq!(citation.headings, vec!["Rust", "并发"]);
+
+        request.conversation_id = Some(first.conversation.id.clone());
+        request.question = "内存安全呢？".to_string();
+        let second = ChatService::ask(&kb_service, &request, &config).unwrap();
+        assert_eq!(second.conversation.id, first.conversation.id);
+        assert_eq!(second.answer.citations[0].start_line, 0);
+
+        // 第二轮请求携带上一轮问答
+        let requests = server.requests();
+        let body = requests[1].json();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1]["content"], "如何避免数据竞争？");
+        assert_eq!(messages[2]["role"], "assistant");
+        let prompt = messages[3]["content"].as_str().unwrap();
+        assert!(prompt.ends_with("问题：内存安全呢？"));
+
+        let stored = kb_service
+            .get_conversation_messages(&first.conversation.id)
+            .unwrap();
+        assert_eq!(stored.len(), 4);
+        assert_eq!(stored[3].citations, second.answer.citations);
+        assert_eq!(kb_service.list_conversations(&kb.id).unwrap().len(), 1);
+
+        // 删除知识库时级联删除对话
+        kb_service.delete_knowledge_base(&kb.id).unwrap();
+        assert!(kb_service
+            .get_conversation(&first.conversation.id)
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
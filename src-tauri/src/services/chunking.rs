@@ -0,0 +1,838 @@
+//! 文本分块
+//! 按知识库的分块配置选择策略。Markdown 策略识别标题、代码块、表格和 Front Matter，
+//! 以估算的 token 数控制块大小，同一小节内相邻文本块保留重叠
+
+use crate::models::{Chunk, ChunkingConfig, ChunkingStrategyKind, HeadingInfo};
+use crate::services::search_service::is_cjk;
+use uuid::Uuid;
+
+/// 分块 token 上限的可选范围
+pub const MIN_CHUNK_TOKENS: usize = 64;
+pub const MAX_CHUNK_TOKENS: usize = 8192;
+
+/// 分块策略
+pub trait ChunkingStrategy: Send + Sync {
+    /// 将文本切分为文本块并提取标题；`page_starts` 为每页起始行号（从 0 开始），文本块不跨页
+    fn chunk(&self, content: &str, page_starts: &[usize]) -> (Vec<Chunk>, Vec<HeadingInfo>);
+}
+
+/// 按配置创建分块策略
+pub fn strategy_for(config: &ChunkingConfig) -> Box<dyn ChunkingStrategy> {
+    match config.strategy {
+        ChunkingStrategyKind::Markdown => Box::new(MarkdownChunker::new(config)),
+        ChunkingStrategyKind::Paragraph => Box::new(ParagraphChunker::new(config)),
+    }
+}
+
+/// 校验分块配置
+pub fn validate_config(config: &ChunkingConfig) -> Result<(), String> {
+    if !(MIN_CHUNK_TOKENS..=MAX_CHUNK_TOKENS).contains(&config.max_tokens) {
+        return Err(format!(
+            "分块大小需在 {} 到 {} token 之间",
+            MIN_CHUNK_TOKENS, MAX_CHUNK_TOKENS
+        ));
+    }
+    if config.overlap_tokens * 2 > config.max_tokens {
+        return Err("重叠 token 数不能超过分块大小的一半".to_string());
+    }
+    Ok(())
+}
+
+/// 估算 token 数：中日韩字符和标点各计 1，其余连续字母数字每 4 个字符计 1
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word = 0usize;
+    for c in text.chars() {
+        if c.is_alphanumeric() && !is_cjk(c) {
+            word += 1;
+            continue;
+        }
+        tokens += word.div_ceil(4);
+        word = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word.div_ceil(4)
+}
+
+/// Markdown 结构感知分块：以标题为小节边界，代码块和表格整体保留，跳过 Front Matter
+pub struct MarkdownChunker {
+    config: ChunkingConfig,
+}
+
+impl MarkdownChunker {
+    pub fn new(config: &ChunkingConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl ChunkingStrategy for MarkdownChunker {
+    fn chunk(&self, content: &str, page_starts: &[usize]) -> (Vec<Chunk>, Vec<HeadingInfo>) {
+        let lines: Vec<&str> = content.lines().collect();
+        let blocks = markdown_blocks(&lines);
+        assemble(&lines, &blocks, page_starts, &self.config)
+    }
+}
+
+/// 按空行分隔的段落分块，用于纯文本和 PDF
+pub struct ParagraphChunker {
+    config: ChunkingConfig,
+}
+
+impl ParagraphChunker {
+    pub fn new(config: &ChunkingConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl ChunkingStrategy for ParagraphChunker {
+    fn chunk(&self, content: &str, page_starts: &[usize]) -> (Vec<Chunk>, Vec<HeadingInfo>) {
+        let lines: Vec<&str> = content.lines().collect();
+        let blocks = paragraph_blocks(&lines, page_starts);
+        assemble(&lines, &blocks, page_starts, &self.config)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BlockKind {
+    Heading(u8, String),
+    /// 普通文本，超长时可按行拆分
+    Text,
+    /// 代码块或表格，不拆分
+    Atomic,
+    /// 分隔线，强制断开
+    Break,
+}
+
+/// 文本中的一个结构块，起止行号均包含在内
+#[derive(Debug, Clone)]
+struct Block {
+    kind: BlockKind,
+    start: usize,
+    end: usize,
+}
+
+/// 将 Markdown 按行切分为结构块
+fn markdown_blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut idx = front_matter_end(lines).map_or(0, |end| end + 1);
+
+    while idx < lines.len() {
+        let line = lines[idx];
+        if line.trim().is_empty() {
+            idx += 1;
+            continue;
+        }
+
+        let (kind, end) = if let Some(fence) = fence_marker(line) {
+            let end = (idx + 1..lines.len())
+                .find(|&i| closes_fence(lines[i], fence))
+                .unwrap_or(lines.len() - 1);
+            (BlockKind::Atomic, end)
+        } else if let Some((level, text)) = parse_heading(line) {
+            (BlockKind::Heading(level, text), idx)
+        } else if is_thematic_break(line) {
+            (BlockKind::Break, idx)
+        } else if starts_table(lines, idx) {
+            let end = (idx + 2..lines.len())
+                .take_while(|&i| !lines[i].trim().is_empty() && lines[i].contains('|'))
+                .last()
+                .unwrap_or(idx + 1);
+            (BlockKind::Atomic, end)
+        } else {
+            // 段落延续到空行或下一个结构块之前
+            let end = (idx + 1..lines.len())
+                .find(|&i| {
+                    let line = lines[i];
+                    line.trim().is_empty()
+                        || fence_marker(line).is_some()
+                        || parse_heading(line).is_some()
+                        || is_thematic_break(line)
+                        || starts_table(lines, i)
+                })
+                .unwrap_or(lines.len());
+            (BlockKind::Text, end - 1)
+        };
+
+        blocks.push(Block {
+            kind,
+            start: idx,
+            end,
+        });
+        idx = end + 1;
+    }
+    blocks
+}
+
+/// 按空行切分段落，段落在分页处断开
+fn paragraph_blocks(lines: &[&str], page_starts: &[usize]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut start: Option<usize> = None;
+    for (idx, line) in lines.iter().enumerate() {
+        if let Some(s) = start {
+            if line.trim().is_empty() || page_starts.contains(&idx) {
+                blocks.push(Block {
+                    kind: BlockKind::Text,
+                    start: s,
+                    end: idx - 1,
+                });
+                start = None;
+            }
+        }
+        if start.is_none() && !line.trim().is_empty() {
+            start = Some(idx);
+        }
+    }
+    if let Some(s) = start {
+        blocks.push(Block {
+            kind: BlockKind::Text,
+            start: s,
+            end: lines.len() - 1,
+        });
+    }
+    blocks
+}
+
+/// 文档开头以 `---` 包围的 YAML Front Matter 的结束行
+pub(crate) fn front_matter_end(lines: &[&str]) -> Option<usize> {
+    if lines.first()?.trim_end() != "---" {
+        return None;
+    }
+    (1..lines.len()).find(|&i| matches!(lines[i].trim_end(), "---" | "..."))
+}
+
+/// 代码块开始标记：(字符, 长度)
+pub(crate) fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let count = trimmed.chars().take_while(|c| *c == marker).count();
+    (count >= 3).then_some((marker, count))
+}
+
+pub(crate) fn closes_fence(line: &str, (marker, count): (char, usize)) -> bool {
+    let trimmed = line.trim();
+    trimmed.chars().take_while(|c| *c == marker).count() >= count
+        && trimmed.chars().all(|c| c == marker)
+}
+
+/// 解析 ATX 标题行：`#` 后需跟空白
+fn parse_heading(line: &str) -> Option<(u8, String)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then(|| (level as u8, text.to_string()))
+}
+
+/// 分隔线：三个及以上的 `-`、`*` 或 `_`
+fn is_thematic_break(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && matches!(chars[0], '-' | '*' | '_') && chars.iter().all(|c| *c == chars[0])
+}
+
+/// 表格：含 `|` 的表头行后紧跟分隔行
+fn starts_table(lines: &[&str], idx: usize) -> bool {
+    lines[idx].contains('|')
+        && lines
+            .get(idx + 1)
+            .is_some_and(|next| is_table_separator(next))
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let trimmed = line.trim().trim_matches('|');
+    trimmed.contains('-')
+        && trimmed.split('|').all(|cell| {
+            let cell = cell.trim().trim_start_matches(':').trim_end_matches(':');
+            !cell.is_empty() && cell.chars().all(|c| c == '-')
+        })
+}
+
+/// 正在累积的文本块
+struct Pending {
+    start: usize,
+    end: usize,
+    tokens: usize,
+    page: Option<usize>,
+    /// 含有重叠以外的新内容
+    fresh: bool,
+    /// 含有标题以外的正文
+    has_body: bool,
+    /// 末尾连续普通文本的起始行，可作为下一块的重叠内容
+    text_tail: Option<usize>,
+    /// 从 `start` 行末尾截取的重叠文本，代替该行整行
+    lead: Option<String>,
+}
+
+/// 将结构块合并为文本块
+struct Assembler<'a> {
+    lines: &'a [&'a str],
+    page_starts: &'a [usize],
+    config: &'a ChunkingConfig,
+    chunks: Vec<Chunk>,
+    headings: Vec<HeadingInfo>,
+    path: Vec<String>,
+    current: Option<Pending>,
+}
+
+fn assemble(
+    lines: &[&str],
+    blocks: &[Block],
+    page_starts: &[usize],
+    config: &ChunkingConfig,
+) -> (Vec<Chunk>, Vec<HeadingInfo>) {
+    let mut assembler = Assembler {
+        lines,
+        page_starts,
+        config,
+        chunks: Vec::new(),
+        headings: Vec::new(),
+        path: Vec::new(),
+        current: None,
+    };
+    for block in blocks {
+        assembler.push(block);
+    }
+    assembler.flush();
+    (assembler.chunks, assembler.headings)
+}
+
+impl Assembler<'_> {
+    fn page_of(&self, line: usize) -> Option<usize> {
+        (!self.page_starts.is_empty()).then(|| {
+            self.page_starts
+                .partition_point(|&start| start <= line)
+                .max(1)
+        })
+    }
+
+    fn tokens(&self, start: usize, end: usize) -> usize {
+        self.lines[start..=end]
+            .iter()
+            .map(|line| estimate_tokens(line))
+            .sum()
+    }
+
+    fn push(&mut self, block: &Block) {
+        match &block.kind {
+            BlockKind::Break => self.flush(),
+            BlockKind::Heading(level, text) => {
+                self.flush();
+                self.headings.push(HeadingInfo {
+                    level: *level,
+                    text: text.clone(),
+                    line: block.start,
+                });
+                self.path.truncate(*level as usize - 1);
+                self.path.push(text.clone());
+                self.current = Some(Pending {
+                    start: block.start,
+                    end: block.end,
+                    tokens: self.tokens(block.start, block.end),
+                    page: self.page_of(block.start),
+                    fresh: true,
+                    has_body: false,
+                    text_tail: None,
+                    lead: None,
+                });
+            }
+            BlockKind::Text | BlockKind::Atomic => {
+                let is_text = block.kind == BlockKind::Text;
+                if self.current.as_ref().map(|p| p.page) != Some(self.page_of(block.start)) {
+                    self.flush();
+                }
+                let tokens = self.tokens(block.start, block.end);
+                if is_text && tokens > self.config.max_tokens {
+                    // 超长段落按行拆分，单行仍超长时再拆开这一行
+                    for line in block.start..=block.end {
+                        let line_tokens = self.tokens(line, line);
+                        if line_tokens > self.config.max_tokens {
+                            self.push_long_line(line);
+                        } else {
+                            self.add(line, line, line_tokens, true);
+                        }
+                    }
+                } else {
+                    self.add(block.start, block.end, tokens, is_text);
+                }
+            }
+        }
+    }
+
+    /// 超长的单行单独拆成若干文本块，行号都指向这一行；
+    /// 每块以上一块末尾的重叠文本开头，最后一块的末尾留作下一块的重叠
+    fn push_long_line(&mut self, line: usize) {
+        let mut overlap = self.current.as_ref().and_then(|pending| {
+            if !pending.fresh {
+                // 只有上一块留下的重叠内容
+                Some(self.text(pending))
+            } else if pending.has_body {
+                self.overlap(pending).map(|overlap| self.text(&overlap))
+            } else {
+                None
+            }
+        });
+        self.flush();
+        let page = self.page_of(line);
+        for piece in split_long_line(self.lines[line], self.config.max_tokens) {
+            let piece = piece.trim();
+            if piece.is_empty() {
+                continue;
+            }
+            let content = match overlap.take() {
+                Some(overlap) => format!("{}\n{}", overlap, piece),
+                None => piece.to_string(),
+            };
+            self.chunks.push(Chunk {
+                id: Uuid::new_v4().to_string(),
+                content,
+                start_line: line,
+                end_line: line,
+                headings: self.path.clone(),
+                page,
+            });
+            overlap = Some(overlap_suffix(piece, self.config.overlap_tokens))
+                .filter(|suffix| !suffix.is_empty())
+                .map(str::to_string);
+        }
+        self.current = overlap.map(|lead| Pending {
+            start: line,
+            end: line,
+            tokens: estimate_tokens(&lead),
+            page,
+            fresh: false,
+            has_body: false,
+            text_tail: Some(line),
+            lead: Some(lead),
+        });
+    }
+
+    fn add(&mut self, start: usize, end: usize, tokens: usize, is_text: bool) {
+        if let Some(pending) = &self.current {
+            if pending.has_body && pending.tokens + tokens > self.config.max_tokens {
+                let overlap = self.overlap(pending);
+                self.flush();
+                self.current = overlap;
+            }
+        }
+
+        let page = self.page_of(start);
+        let pending = self.current.get_or_insert(Pending {
+            start,
+            end,
+            tokens: 0,
+            page,
+            fresh: true,
+            has_body: false,
+            text_tail: None,
+            lead: None,
+        });
+        pending.end = end;
+        pending.tokens += tokens;
+        pending.fresh = true;
+        pending.has_body = true;
+        pending.text_tail = if is_text {
+            pending.text_tail.or(Some(start))
+        } else {
+            None
+        };
+    }
+
+    /// 从文本块末尾的普通文本中取不超过重叠上限的若干行，作为下一块的开头；
+    /// 一整行都放不下时取最后一行末尾的若干句，仍放不下时按字符截取
+    fn overlap(&self, pending: &Pending) -> Option<Pending> {
+        let text_tail = pending.text_tail?;
+        let tail = text_tail.max(pending.start + 1);
+        let mut start = None;
+        let mut tokens = 0;
+        for line in (tail..=pending.end).rev() {
+            let line_tokens = estimate_tokens(self.lines[line]);
+            if tokens + line_tokens > self.config.overlap_tokens {
+                break;
+            }
+            tokens += line_tokens;
+            start = Some(line);
+        }
+        // 跳过开头的空行
+        if let Some(start) = start
+            .and_then(|start| (start..=pending.end).find(|&i| !self.lines[i].trim().is_empty()))
+        {
+            return Some(Pending {
+                start,
+                end: pending.end,
+                tokens,
+                page: pending.page,
+                fresh: false,
+                has_body: false,
+                text_tail: Some(start),
+                lead: None,
+            });
+        }
+
+        let line = (text_tail..=pending.end)
+            .rev()
+            .find(|&i| !self.lines[i].trim().is_empty())?;
+        let suffix = overlap_suffix(self.lines[line], self.config.overlap_tokens);
+        // 整行都能放下时只可能是文本块的首行，不重复整块内容
+        if suffix.is_empty() || suffix == self.lines[line].trim() {
+            return None;
+        }
+        Some(Pending {
+            start: line,
+            end: pending.end,
+            tokens: estimate_tokens(suffix),
+            page: pending.page,
+            fresh: false,
+            has_body: false,
+            text_tail: Some(line),
+            lead: Some(suffix.to_string()),
+        })
+    }
+
+    /// 文本块的内容，开头为截取的重叠文本时用它代替首行
+    fn text(&self, pending: &Pending) -> String {
+        match &pending.lead {
+            Some(lead) => std::iter::once(lead.as_str())
+                .chain(
+                    self.lines[pending.start + 1..pending.end + 1]
+                        .iter()
+                        .copied(),
+                )
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => self.lines[pending.start..=pending.end].join("\n"),
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(pending) = self.current.take() else {
+            return;
+        };
+        if !pending.fresh {
+            return;
+        }
+        let content = self.text(&pending);
+        let content = content.trim();
+        if content.is_empty() {
+            return;
+        }
+        self.chunks.push(Chunk {
+            id: Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            start_line: pending.start,
+            end_line: pending.end,
+            headings: self.path.clone(),
+            page: pending.page,
+        });
+    }
+}
+
+/// 把一行拆成不超过 `max_tokens` 的片段：先在句末标点处断开，单句仍超长时按字符断开
+fn split_long_line(line: &str, max_tokens: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let (mut start, mut end) = (0, 0);
+    for sentence_end in sentence_ends(line) {
+        if estimate_tokens(&line[start..sentence_end]) <= max_tokens {
+            end = sentence_end;
+            continue;
+        }
+        if end > start {
+            pieces.push(&line[start..end]);
+            start = end;
+        }
+        if estimate_tokens(&line[start..sentence_end]) <= max_tokens {
+            end = sentence_end;
+        } else {
+            pieces.extend(split_by_chars(&line[start..sentence_end], max_tokens));
+            start = sentence_end;
+            end = sentence_end;
+        }
+    }
+    if end > start {
+        pieces.push(&line[start..end]);
+    }
+    pieces
+}
+
+/// 取文本末尾不超过 `max_tokens` 的部分作为重叠：优先从句子开头截取，
+/// 最后一句仍超出时按字符截取
+fn overlap_suffix(text: &str, max_tokens: usize) -> &str {
+    let text = text.trim_end();
+    if let Some(start) = std::iter::once(0)
+        .chain(sentence_ends(text))
+        .find(|&start| estimate_tokens(&text[start..]) <= max_tokens)
+    {
+        if start < text.len() {
+            return text[start..].trim_start();
+        }
+    }
+    let mut start = text.len();
+    for (idx, _) in text.char_indices().rev() {
+        if estimate_tokens(&text[idx..]) > max_tokens {
+            break;
+        }
+        start = idx;
+    }
+    text[start..].trim_start()
+}
+
+/// 各句末尾的字节位置，最后一个总是行尾
+fn sentence_ends(line: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let next_is_space = chars.peek().map_or(true, |(_, next)| next.is_whitespace());
+        if matches!(c, '。' | '！' | '？' | '；' | '…')
+            || (matches!(c, '.' | '!' | '?' | ';') && next_is_space)
+        {
+            ends.push(idx + c.len_utf8());
+        }
+    }
+    if ends.last() != Some(&line.len()) {
+        ends.push(line.len());
+    }
+    ends
+}
+
+/// 按字符断开，与 `estimate_tokens` 的计数方式一致
+fn split_by_chars(text: &str, max_tokens: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let (mut tokens, mut word) = (0, 0usize);
+    for (idx, c) in text.char_indices() {
+        let (next_tokens, next_word) = if c.is_alphanumeric() && !is_cjk(c) {
+            (tokens, word + 1)
+        } else {
+            (
+                tokens + word.div_ceil(4) + usize::from(!c.is_whitespace()),
+                0,
+            )
+        };
+        if idx > start && next_tokens + next_word.div_ceil(4) > max_tokens {
+            pieces.push(&text[start..idx]);
+            start = idx;
+            // 新片段从当前字符重新计数
+            (tokens, word) = if c.is_alphanumeric() && !is_cjk(c) {
+                (0, 1)
+            } else {
+                (usize::from(!c.is_whitespace()), 0)
+            };
+        } else {
+            (tokens, word) = (next_tokens, next_word);
+        }
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_tokens: usize, overlap_tokens: usize) -> ChunkingConfig {
+        ChunkingConfig {
+            strategy: ChunkingStrategyKind::Markdown,
+            max_tokens,
+            overlap_tokens,
+        }
+    }
+
+    #[test]
+    fn test_parse_heading() {
+        assert_eq!(parse_heading("# Title"), Some((1, "Title".to_string())));
+        assert_eq!(
+            parse_heading("## Section ##"),
+            Some((2, "Section".to_string()))
+        );
+        assert_eq!(parse_heading("No heading"), None);
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("####### Too deep"), None);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hello world"), 4);
+        assert_eq!(estimate_tokens("所有权，借用"), 6);
+        assert_eq!(estimate_tokens("fn main() {}"), 6);
+    }
+
+    #[test]
+    fn test_markdown_keeps_code_blocks_and_tables() {
+        let content = "\
+---
+title: 笔记
+tags: [rust, 并发]
+---
+# 所有权
+
+所有权规则说明。
+
+```bash
+# 这不是标题
+cargo build
+
+---
+```
+
+| 名称 | 说明 |
+| :--- | ---: |
+| Box | 堆分配 |
+| Rc | 引用计数 |
+
+---
+
+## 借用
+#标签 不是标题
+借用规则。";
+        let (chunks, headings) = MarkdownChunker::new(&config(12, 0)).chunk(content, &[]);
+
+        let titles: Vec<(u8, &str, usize)> = headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.line))
+            .collect();
+        assert_eq!(titles, vec![(1, "所有权", 4), (2, "借用", 22)]);
+        assert!(chunks.iter().all(|c| !c.content.contains("title:")));
+
+        // 代码块和表格各自完整地出现在同一个文本块中
+        let fence = chunks
+            .iter()
+            .find(|c| c.content.contains("cargo build"))
+            .unwrap();
+        assert!(fence.content.starts_with("```bash") && fence.content.ends_with("```"));
+        assert_eq!((fence.start_line, fence.end_line), (8, 13));
+        let table = chunks.iter().find(|c| c.content.contains("Box")).unwrap();
+        assert!(table.content.starts_with("| 名称") && table.content.ends_with("引用计数 |"));
+        assert_eq!(table.headings, vec!["所有权"]);
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last.content, "## 借用\n#标签 不是标题\n借用规则。");
+        assert_eq!(last.headings, vec!["所有权", "借用"]);
+        // 分隔线本身不进入文本块
+        assert!(chunks.iter().all(|c| c.content.trim() != "---"));
+    }
+
+    #[test]
+    fn test_token_limit_with_overlap() {
+        let content = (1..=12)
+            .map(|i| format!("第{}行内容", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let content = format!("# 标题\n\n{}", content);
+        let (chunks, _) = MarkdownChunker::new(&config(20, 6)).chunk(&content, &[]);
+
+        assert!(chunks.len() > 2);
+        assert!(chunks[0].content.starts_with("# 标题"));
+        for pair in chunks.windows(2) {
+            // 相邻文本块重叠一行
+            assert_eq!(pair[1].start_line, pair[0].end_line);
+            assert!(estimate_tokens(&pair[1].content) <= 20 + 6);
+        }
+        assert_eq!(chunks.last().unwrap().end_line, 13);
+        assert!(chunks.iter().all(|c| c.headings == vec!["标题"]));
+    }
+
+    #[test]
+    fn test_long_line_is_split() {
+        let max_tokens = 32;
+        let config = config(max_tokens, 0);
+
+        // 没有标点的单行按字符拆分
+        let line = "数".repeat(max_tokens * 5);
+        let content = format!("# 标题\n\n{}", line);
+        let (chunks, _) = MarkdownChunker::new(&config).chunk(&content, &[]);
+        let body: Vec<&Chunk> = chunks.iter().filter(|c| c.start_line == 2).collect();
+        assert_eq!(body.len(), 5);
+        assert!(chunks
+            .iter()
+            .all(|c| estimate_tokens(&c.content) <= max_tokens));
+        assert_eq!(
+            body.iter().map(|c| c.content.as_str()).collect::<String>(),
+            line
+        );
+        assert!(body.iter().all(|c| c.headings == vec!["标题"]));
+
+        // 有句末标点时在标点处断开
+        let line = "所有权规则由编译器在编译期检查。".repeat(10);
+        let (chunks, _) = ParagraphChunker::new(&config).chunk(&line, &[]);
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| estimate_tokens(&c.content) <= max_tokens && c.content.ends_with('。')));
+
+        let words = "borrow checker ".repeat(100);
+        assert!(split_long_line(&words, max_tokens)
+            .iter()
+            .all(|piece| estimate_tokens(piece) <= max_tokens));
+    }
+
+    #[test]
+    fn test_overlap_within_long_lines() {
+        let config = config(32, 8);
+
+        // 整行放不下时从末尾的句子开始重叠
+        let first = "借用检查器在编译期工作。它阻止数据竞争。";
+        let second = "生命周期标注描述引用之间的关系。";
+        let content = format!("{}\n\n{}", first, second);
+        let (chunks, _) = ParagraphChunker::new(&config).chunk(&content, &[]);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, first);
+        assert_eq!(chunks[1].content, format!("它阻止数据竞争。\n\n{}", second));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (0, 2));
+
+        // 超长单行拆开的各块之间按字符重叠
+        let line = "数".repeat(80);
+        let (chunks, _) = ParagraphChunker::new(&config).chunk(&line, &[]);
+        assert_eq!(chunks.len(), 3);
+        for pair in chunks.windows(2) {
+            let (overlap, rest) = pair[1].content.split_once('\n').unwrap();
+            assert_eq!(estimate_tokens(overlap), 8);
+            assert!(pair[0].content.ends_with(overlap));
+            assert!(estimate_tokens(rest) <= 32);
+        }
+    }
+
+    #[test]
+    fn test_paragraphs_stay_within_pages() {
+        let chunker = ParagraphChunker::new(&config(512, 0));
+        let content = "第一段\n续行\n\n第二段\n\n\n第三段";
+        let (chunks, headings) = chunker.chunk(content, &[]);
+        assert!(headings.is_empty());
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].content, content);
+        assert_eq!(chunks[0].page, None);
+
+        // 分页文本：文本块不跨页，即使页内没有空行
+        let content = "第一页甲\n第一页乙\n\n第二页\n\n第三页";
+        let (chunks, _) = chunker.chunk(content, &[0, 3, 5]);
+        let pages: Vec<(Option<usize>, usize, usize)> = chunks
+            .iter()
+            .map(|c| (c.page, c.start_line, c.end_line))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![(Some(1), 0, 1), (Some(2), 3, 3), (Some(3), 5, 5)]
+        );
+
+        let (chunks, _) = chunker.chunk("甲\n乙", &[0, 1]);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].page, Some(2));
+
+        assert!(validate_config(&config(512, 64)).is_ok());
+        assert!(validate_config(&config(16, 0)).is_err());
+        assert!(validate_config(&config(128, 100)).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! 文本向量嵌入服务
+//! 通过 `EmbeddingProvider` 接入不同的嵌入来源：OpenAI 兼容接口或离线哈希嵌入
+
+use crate::models::EmbeddingConfig;
+use crate::services::search_service::tokenize;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// 单次请求最多提交的文本数
+pub const BATCH_SIZE: usize = 32;
+
+/// 嵌入提供方
+pub trait EmbeddingProvider: Send + Sync {
+    /// 提供方标识，向量按此标识分别持久化
+    fn id(&self) -> String;
+
+    /// 计算一批文本的向量
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// 根据配置创建嵌入提供方
+pub fn create_provider(config: &EmbeddingConfig) -> Result<Box<dyn EmbeddingProvider>, String> {
+    match config.provider.as_str() {
+        "hashing" => Ok(Box::new(HashingEmbedder::new(config.dimensions))),
+        "openai" => Ok(Box::new(OpenAiEmbedder::new(
+            &config.base_url,
+            config.api_key.clone(),
+            &config.model,
+        ))),
+        other => Err(format!("不支持的嵌入提供方: {}", other)),
+    }
+}
+
+/// 离线哈希嵌入：将分词结果散列到固定维度，结果确定且无需网络
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0f32; self.dimensions];
+        for token in tokenize(text) {
+            let hash = fnv1a(token.as_bytes());
+            let idx = (hash % self.dimensions as u64) as usize;
+            // 最高位决定符号，减少哈希冲突带来的偏差
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[idx] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl EmbeddingProvider for HashingEmbedder {
+    fn id(&self) -> String {
+        format!("hashing-{}", self.dimensions)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// OpenAI 兼容的 `/embeddings` 接口（OpenAI、Ollama、LM Studio 等）
+pub struct OpenAiEmbedder {
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(base_url: &str, api_key: Option<String>, model: &str) -> Self {
+        Self {
+            endpoint: format!("{}/embeddings", base_url.trim_end_matches('/')),
+            api_key: api_key.filter(|k| !k.is_empty()),
+            model: model.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(60))
+                .build(),
+        }
+    }
+}
+
+impl EmbeddingProvider for OpenAiEmbedder {
+    fn id(&self) -> String {
+        format!("openai:{}", self.model)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut request = self.agent.post(&self.endpoint);
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+
+        let response: EmbeddingResponse = request
+            .send_json(json!({ "model": self.model, "input": texts }))
+            .map_err(|e| format!("嵌入请求失败: {}", e))?
+            .into_json()
+            .map_err(|e| format!("嵌入响应解析失败: {}", e))?;
+
+        if response.data.len() != texts.len() {
+            return Err(format!(
+                "嵌入数量不匹配: 期望 {}，实际 {}",
+                texts.len(),
+                response.data.len()
+            ));
+        }
+
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data
+            .into_iter()
+            .map(|d| {
+                let mut vector = d.embedding;
+                normalize(&mut vector);
+                vector
+            })
+            .collect())
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 归一化为单位向量
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// 余弦相似度
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm =
+        a.iter().map(|v| v * v).sum::<f32>().sqrt() * b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+/// 向量序列化为小端字节
+pub fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// 从小端字节还原向量
+pub fn vector_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{StubResponse, StubServer};
+
+    #[test]
+    fn test_hashing_embedder_is_deterministic() {
+        let embedder = HashingEmbedder::new(64);
+        let texts = vec![
+            "知识库 全文搜索".to_string(),
+            "知识库搜索".to_string(),
+            "EPUB export".to_string(),
+        ];
+        let first = embedder.embed(&texts).unwrap();
+        assert_eq!(first, embedder.embed(&texts).unwrap());
+        assert_eq!(first[0].len(), 64);
+        assert!(cosine_similarity(&first[0], &first[1]) > cosine_similarity(&first[0], &first[2]));
+        assert_eq!(vector_from_bytes(&vector_to_bytes(&first[0])), first[0]);
+    }
+
+    #[test]
+    fn test_openai_embedder_against_stub_server() {
+        // 故意打乱顺序，验证按 index 还原
+        let server = StubServer::start(vec![StubResponse::json(
+            r#"{"data":[{"index":1,"embedding":[0.0,2.0]},{"index":0,"embedding":[3.0,0.0]}]}"#,
+        )]);
+
+        let embedder = OpenAiEmbedder::new(
+            &format!("{}/v1/", server.url),
+            Some("sk-test".to_string()),
+            "text-embedding-3-small",
+        );
+        let vectors = embedder.embed(&["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/v1/embeddings");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer sk-test"));
+        let body = requests[0].json();
+        assert_eq!(body["model"], "text-embedding-3-small");
+        assert_eq!(body["input"], json!(["a", "b"]));
+    }
+}
@@ -0,0 +1,653 @@
+//! EPUB 导出服务
+//! 将一个 Markdown 文档目录打包为 EPUB 3 电子书
+
+use crate::services::markdown_renderer::{
+    self, escape_xml, is_local_url, LinkKind, RenderedHeading,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const SUMMARY_FILE: &str = "SUMMARY.md";
+
+const DEFAULT_CSS: &str = r#"body { font-family: serif; line-height: 1.6; margin: 0 5%; }
+h1, h2, h3, h4 { font-family: sans-serif; line-height: 1.3; }
+pre { background: #f4f4f4; padding: 0.8em; white-space: pre-wrap; }
+code { font-family: monospace; }
+blockquote { border-left: 4px solid #ddd; padding-left: 1em; color: #555; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; }
+img { max-width: 100%; }
+"#;
+
+/// EPUB 导出选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 额外的样式表，默认使用目录下的 `epub.css` 或 `style.css`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub css_path: Option<String>,
+}
+
+/// 章节
+struct Chapter {
+    source: PathBuf,
+    title: Option<String>,
+}
+
+/// 渲染后的章节
+struct RenderedChapter {
+    file_name: String,
+    title: String,
+    body: String,
+    headings: Vec<RenderedHeading>,
+}
+
+/// 嵌入的图片资源
+struct Resource {
+    href: String,
+    media_type: &'static str,
+    source: PathBuf,
+}
+
+/// 目录树节点
+struct NavPoint {
+    label: String,
+    href: String,
+    children: Vec<NavPoint>,
+}
+
+pub struct EpubService;
+
+impl EpubService {
+    /// 将目录中的 Markdown 章节导出为 EPUB
+    pub fn export_folder(
+        source_dir: &Path,
+        output_path: &Path,
+        options: &EpubOptions,
+    ) -> Result<(), String> {
+        if !source_dir.is_dir() {
+            return Err(format!("目录不存在: {}", source_dir.display()));
+        }
+
+        let chapters = Self::collect_chapters(source_dir)?;
+        if chapters.is_empty() {
+            return Err("目录中没有可导出的 Markdown 文件".to_string());
+        }
+
+        // 章节源文件到输出文件名的映射，用于改写章节间链接
+        let chapter_files: HashMap<PathBuf, String> = chapters
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| (normalize_path(&c.source), chapter_file_name(idx)))
+            .collect();
+
+        let mut resources: Vec<Resource> = Vec::new();
+        let mut rendered = Vec::new();
+
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let content = fs::read_to_string(&chapter.source).map_err(|e| e.to_string())?;
+            let (front_matter, body) = markdown_renderer::split_front_matter(&content);
+            let base_dir = chapter.source.parent().unwrap_or(source_dir);
+
+            let result = markdown_renderer::render(body, |kind, url| match kind {
+                LinkKind::Image => Self::embed_image(base_dir, url, &mut resources),
+                LinkKind::Link => Self::rewrite_chapter_link(base_dir, url, &chapter_files),
+            });
+
+            let title = chapter
+                .title
+                .clone()
+                .or(front_matter.title)
+                .or_else(|| result.title().map(str::to_string))
+                .unwrap_or_else(|| file_stem(&chapter.source));
+
+            rendered.push(RenderedChapter {
+                file_name: chapter_file_name(idx),
+                title,
+                body: result.html,
+                headings: result.headings,
+            });
+        }
+
+        let title = options
+            .title
+            .clone()
+            .unwrap_or_else(|| file_stem(source_dir));
+        let language = options
+            .language
+            .clone()
+            .unwrap_or_else(|| "zh-CN".to_string());
+
+        let mut css = DEFAULT_CSS.to_string();
+        if let Some(extra) = Self::find_stylesheet(source_dir, options) {
+            css.push_str(&fs::read_to_string(&extra).map_err(|e| e.to_string())?);
+        }
+
+        let file = fs::File::create(output_path).map_err(|e| e.to_string())?;
+        let mut zip = ZipWriter::new(file);
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // mimetype 必须是第一个且不压缩的条目
+        write_entry(&mut zip, "mimetype", stored, b"application/epub+zip")?;
+        write_entry(
+            &mut zip,
+            "META-INF/container.xml",
+            deflated,
+            CONTAINER_XML.as_bytes(),
+        )?;
+        write_entry(&mut zip, "OEBPS/style.css", deflated, css.as_bytes())?;
+
+        for chapter in &rendered {
+            let xhtml = chapter_xhtml(&chapter.title, &language, &chapter.body);
+            write_entry(
+                &mut zip,
+                &format!("OEBPS/{}", chapter.file_name),
+                deflated,
+                xhtml.as_bytes(),
+            )?;
+        }
+
+        for resource in &resources {
+            let data = fs::read(&resource.source).map_err(|e| e.to_string())?;
+            write_entry(
+                &mut zip,
+                &format!("OEBPS/{}", resource.href),
+                deflated,
+                &data,
+            )?;
+        }
+
+        let nav = nav_xhtml(&title, &language, &Self::build_nav(&rendered));
+        write_entry(&mut zip, "OEBPS/nav.xhtml", deflated, nav.as_bytes())?;
+
+        let opf = Self::package_document(&title, &language, options, &rendered, &resources);
+        write_entry(&mut zip, "OEBPS/content.opf", deflated, opf.as_bytes())?;
+
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 确定章节顺序：优先使用 SUMMARY.md，否则按 front matter 权重和路径排序
+    fn collect_chapters(source_dir: &Path) -> Result<Vec<Chapter>, String> {
+        let summary_path = source_dir.join(SUMMARY_FILE);
+        if summary_path.is_file() {
+            let summary = fs::read_to_string(&summary_path).map_err(|e| e.to_string())?;
+            let chapters = parse_summary(source_dir, &summary);
+            if !chapters.is_empty() {
+                return Ok(chapters);
+            }
+        }
+
+        let mut files = Vec::new();
+        collect_markdown_files(source_dir, &mut files)?;
+
+        let mut weighted = Vec::new();
+        for path in files {
+            if path.file_name().is_some_and(|n| n == SUMMARY_FILE) {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let (front_matter, _) = markdown_renderer::split_front_matter(&content);
+            weighted.push((front_matter.weight, path));
+        }
+
+        // 有权重的章节在前，其余按路径排序
+        weighted.sort_by(|a, b| match (a.0, b.0) {
+            (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.1.cmp(&b.1)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.1.cmp(&b.1),
+        });
+
+        Ok(weighted
+            .into_iter()
+            .map(|(_, source)| Chapter {
+                source,
+                title: None,
+            })
+            .collect())
+    }
+
+    /// 复制本地图片到电子书中并返回新地址
+    fn embed_image(base_dir: &Path, url: &str, resources: &mut Vec<Resource>) -> Option<String> {
+        if !is_local_url(url) {
+            return None;
+        }
+        let source = normalize_path(&base_dir.join(strip_fragment(url)));
+        if !source.is_file() {
+            return None;
+        }
+        if let Some(existing) = resources.iter().find(|r| r.source == source) {
+            return Some(existing.href.clone());
+        }
+
+        let extension = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let media_type = image_media_type(&extension)?;
+        let href = format!("images/image-{:03}.{}", resources.len() + 1, extension);
+        resources.push(Resource {
+            href: href.clone(),
+            media_type,
+            source,
+        });
+        Some(href)
+    }
+
+    /// 将指向其他章节的 `.md` 链接改写为对应的 xhtml 文件
+    fn rewrite_chapter_link(
+        base_dir: &Path,
+        url: &str,
+        chapter_files: &HashMap<PathBuf, String>,
+    ) -> Option<String> {
+        if !is_local_url(url) {
+            return None;
+        }
+        let (path, fragment) = match url.split_once('#') {
+            Some((p, f)) => (p, Some(f)),
+            None => (url, None),
+        };
+        let target = normalize_path(&base_dir.join(path));
+        let file_name = chapter_files.get(&target)?;
+        Some(match fragment {
+            Some(f) => format!("{}#{}", file_name, f),
+            None => file_name.clone(),
+        })
+    }
+
+    fn find_stylesheet(source_dir: &Path, options: &EpubOptions) -> Option<PathBuf> {
+        if let Some(path) = &options.css_path {
+            return Some(PathBuf::from(path));
+        }
+        ["epub.css", "style.css"]
+            .iter()
+            .map(|name| source_dir.join(name))
+            .find(|p| p.is_file())
+    }
+
+    /// 根据章节标题和二、三级标题生成目录树
+    fn build_nav(chapters: &[RenderedChapter]) -> Vec<NavPoint> {
+        chapters
+            .iter()
+            .map(|chapter| {
+                let mut root = NavPoint {
+                    label: chapter.title.clone(),
+                    href: chapter.file_name.clone(),
+                    children: Vec::new(),
+                };
+                for heading in chapter
+                    .headings
+                    .iter()
+                    .filter(|h| (2..=3).contains(&h.level))
+                {
+                    let point = NavPoint {
+                        label: heading.text.clone(),
+                        href: format!("{}#{}", chapter.file_name, heading.anchor),
+                        children: Vec::new(),
+                    };
+                    match root.children.last_mut() {
+                        Some(parent) if heading.level == 3 => parent.children.push(point),
+                        _ => root.children.push(point),
+                    }
+                }
+                root
+            })
+            .collect()
+    }
+
+    fn package_document(
+        title: &str,
+        language: &str,
+        options: &EpubOptions,
+        chapters: &[RenderedChapter],
+        resources: &[Resource],
+    ) -> String {
+        let mut manifest = String::from(
+            "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n",
+        );
+        let mut spine = String::new();
+        for (idx, chapter) in chapters.iter().enumerate() {
+            manifest.push_str(&format!(
+                "    <item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+                idx + 1,
+                chapter.file_name
+            ));
+            spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", idx + 1));
+        }
+        for (idx, resource) in resources.iter().enumerate() {
+            manifest.push_str(&format!(
+                "    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                idx + 1,
+                resource.href,
+                resource.media_type
+            ));
+        }
+
+        let creator = options
+            .author
+            .as_ref()
+            .map(|a| format!("    <dc:creator>{}</dc:creator>\n", escape_xml(a)))
+            .unwrap_or_default();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:uuid:{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>
+{}    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+{}  </manifest>
+  <spine>
+{}  </spine>
+</package>
+"#,
+            Uuid::new_v4(),
+            escape_xml(title),
+            escape_xml(language),
+            creator,
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            manifest,
+            spine
+        )
+    }
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn chapter_xhtml(title: &str, language: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+  <meta charset="utf-8"/>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        lang = escape_xml(language),
+        title = escape_xml(title),
+        body = body
+    )
+}
+
+fn nav_xhtml(title: &str, language: &str, points: &[NavPoint]) -> String {
+    let mut list = String::new();
+    render_nav_list(points, 2, &mut list);
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+  <meta charset="utf-8"/>
+  <title>{title}</title>
+</head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h1>{title}</h1>
+{list}  </nav>
+</body>
+</html>
+"#,
+        lang = escape_xml(language),
+        title = escape_xml(title),
+        list = list
+    )
+}
+
+fn render_nav_list(points: &[NavPoint], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}<ol>\n", indent));
+    for point in points {
+        out.push_str(&format!(
+            "{}  <li><a href=\"{}\">{}</a>",
+            indent,
+            escape_xml(&point.href),
+            escape_xml(&point.label)
+        ));
+        if !point.children.is_empty() {
+            out.push('\n');
+            render_nav_list(&point.children, depth + 2, out);
+            out.push_str(&format!("{}  ", indent));
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str(&format!("{}</ol>\n", indent));
+}
+
+fn write_entry(
+    zip: &mut ZipWriter<fs::File>,
+    name: &str,
+    options: SimpleFileOptions,
+    data: &[u8],
+) -> Result<(), String> {
+    zip.start_file(name, options).map_err(|e| e.to_string())?;
+    zip.write_all(data).map_err(|e| e.to_string())
+}
+
+/// 解析 mdBook 风格的 SUMMARY.md：`- [标题](路径.md)`
+fn parse_summary(source_dir: &Path, summary: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for line in summary.lines() {
+        let Some(start) = line.find('[') else {
+            continue;
+        };
+        let rest = &line[start + 1..];
+        let Some((title, rest)) = rest.split_once("](") else {
+            continue;
+        };
+        let Some((target, _)) = rest.split_once(')') else {
+            continue;
+        };
+        let target = strip_fragment(target.trim());
+        if target.is_empty() || !is_local_url(target) {
+            continue;
+        }
+
+        let source = normalize_path(&source_dir.join(target));
+        if source.is_file() && !chapters.iter().any(|c| c.source == source) {
+            chapters.push(Chapter {
+                source,
+                title: Some(title.trim().to_string()).filter(|t| !t.is_empty()),
+            });
+        }
+    }
+    chapters
+}
+
+fn collect_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(normalize_path(&path));
+        }
+    }
+    Ok(())
+}
+
+/// 消除路径中的 `.` 和 `..`，便于比较
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn strip_fragment(url: &str) -> &str {
+    url.split(['#', '?']).next().unwrap_or(url)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+fn chapter_file_name(idx: usize) -> String {
+    format!("chapter-{:03}.xhtml", idx + 1)
+}
+
+fn image_media_type(extension: &str) -> Option<&'static str> {
+    match extension {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use std::io::Read;
+
+    /// 检查 XML 是否格式良好
+    fn assert_well_formed(name: &str, xml: &str) {
+        let mut reader = Reader::from_str(xml);
+        let mut depth = 0i32;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(_)) => depth += 1,
+                Ok(Event::End(_)) => depth -= 1,
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("{} 不是格式良好的 XML: {}", name, e),
+            }
+        }
+        assert_eq!(depth, 0, "{} 存在未闭合的元素", name);
+    }
+
+    #[test]
+    fn test_export_folder_structure() {
+        let dir = std::env::temp_dir().join(format!("epub-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        fs::write(
+            dir.join(SUMMARY_FILE),
+            "# Summary\n\n- [Intro](intro.md)\n- [Usage & Tips](usage.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("intro.md"),
+            "# Intro\n\nSee [usage](usage.md#setup).\n\n![logo](images/logo.png)\n\n<br>\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("usage.md"),
+            "---\ntitle: Usage\n---\n# Usage\n\n## Setup\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n### Details\n\ntext\n",
+        )
+        .unwrap();
+        fs::write(dir.join("images/logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        fs::write(dir.join("style.css"), "h1 { color: red; }").unwrap();
+
+        let output = dir.join("book.epub");
+        EpubService::export_folder(&dir, &output, &EpubOptions::default()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+
+        // mimetype 为首个条目且未压缩
+        {
+            let mut first = archive.by_index(0).unwrap();
+            assert_eq!(first.name(), "mimetype");
+            assert_eq!(first.compression(), CompressionMethod::Stored);
+            let mut mimetype = String::new();
+            first.read_to_string(&mut mimetype).unwrap();
+            assert_eq!(mimetype, "application/epub+zip");
+        }
+
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap_or_else(|_| panic!("缺少 {}", name))
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        let container = read("META-INF/container.xml");
+        assert!(container.contains("OEBPS/content.opf"));
+        let opf = read("OEBPS/content.opf");
+        let nav = read("OEBPS/nav.xhtml");
+        let intro = read("OEBPS/chapter-001.xhtml");
+        let usage = read("OEBPS/chapter-002.xhtml");
+        let css = read("OEBPS/style.css");
+
+        for (name, xml) in [
+            ("container.xml", &container),
+            ("content.opf", &opf),
+            ("nav.xhtml", &nav),
+            ("chapter-001.xhtml", &intro),
+            ("chapter-002.xhtml", &usage),
+        ] {
+            assert_well_formed(name, xml);
+        }
+
+        // 清单中的每个资源都必须存在
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        for href in opf.split("href=\"").skip(1) {
+            let href = href.split('"').next().unwrap();
+            assert!(names.contains(&format!("OEBPS/{}", href)), "缺少 {}", href);
+        }
+
+        // 章节顺序遵循 SUMMARY.md
+        let first = opf.find("idref=\"chapter-1\"").unwrap();
+        let second = opf.find("idref=\"chapter-2\"").unwrap();
+        assert!(first < second);
+
+        assert!(nav.contains("<a href=\"chapter-001.xhtml\">Intro</a>"));
+        assert!(nav.contains("Usage &amp; Tips"));
+        assert!(nav.contains("chapter-002.xhtml#setup"));
+        assert!(nav.contains("chapter-002.xhtml#details"));
+        assert!(intro.contains("href=\"chapter-002.xhtml#setup\""));
+        assert!(intro.contains("src=\"images/image-001.png\""));
+        assert!(usage.contains("<table>"));
+        assert!(css.contains("color: red"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
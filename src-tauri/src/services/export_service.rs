@@ -0,0 +1,475 @@
+//! 文档导出服务
+//! 单文件导出以及多文件、整个目录的批量导出
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 批量导出时的最大并发数
+const MAX_WORKERS: usize = 4;
+
+/// 批量导出请求
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchExportRequest {
+    /// 要导出的文件，合并导出时按此顺序拼接
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// 要导出的目录，其中的 Markdown 文件会全部导出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    pub format: String,
+    pub output_dir: String,
+    /// 设置后将所有文件合并为一个文档，值为输出文件名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_into: Option<String>,
+}
+
+/// 批量导出进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchExportProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 成功导出的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedFile {
+    pub source: String,
+    pub output: String,
+}
+
+/// 导出失败的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFailure {
+    pub source: String,
+    pub error: String,
+}
+
+/// 批量导出结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchExportReport {
+    pub total: usize,
+    pub exported: Vec<ExportedFile>,
+    pub failed: Vec<ExportFailure>,
+}
+
+pub struct ExportService;
+
+impl ExportService {
+    /// 导出单个文档
+    pub fn export(
+        output_path: &Path,
+        content: &str,
+        format: &str,
+        is_content_html: bool,
+    ) -> Result<(), String> {
+        let body = Self::to_html_fragment(content, is_content_html);
+        let document = Self::wrap_document(&body, format)?;
+        fs::write(output_path, document).map_err(|e| e.to_string())
+    }
+
+    /// 将 Markdown 转换为 HTML 片段
+    pub fn to_html_fragment(content: &str, is_content_html: bool) -> String {
+        if is_content_html {
+            content.to_string()
+        } else {
+            markdown::to_html(content)
+        }
+    }
+
+    /// 为 HTML 片段套上目标格式的文档外壳
+    pub fn wrap_document(html_content: &str, format: &str) -> Result<String, String> {
+        match format {
+            "html" => Ok(format!(
+                r#"<!DOCTYPE html><html><head><meta charset="utf-8"><style>body{{font-family:sans-serif;line-height:1.6;padding:2em;max-width:800px;margin:auto;}}pre{{background:#f4f4f4;padding:1em;border-radius:4px;}}code{{font-family:monospace;}}blockquote{{border-left:4px solid #ddd;padding-left:1em;color:#666;}}table{{border-collapse:collapse;width:100%;margin-bottom:1em;}}th,td{{border:1px solid #ddd;padding:8px;text-align:left;}}th{{background-color:#f2f2f2;}}img{{max-width:100%;}}</style></head><body>{}</body></html>"#,
+                html_content
+            )),
+            "docx" | "doc" => {
+                // 实现一个兼容 Word 的 HTML 导出格式（Word 可以直接打开带 HTML 标记的 .doc 文件）
+                Ok(format!(
+                    r#"<html xmlns:o='urn:schemas-microsoft-com:office:office' xmlns:w='urn:schemas-microsoft-com:office:word' xmlns='http://www.w3.org/TR/REC-html40'>
+                <head><meta charset='utf-8'><title>Export</title>
+                <style>
+                  body {{ font-family: Arial, sans-serif; }}
+                  table {{ border-collapse: collapse; width: 100%; margin-bottom: 15px; }}
+                  th, td {{ border: 1px solid #000; padding: 5px 10px; text-align: left; }}
+                  th {{ background-color: #f2f2f2; }}
+                  img {{ max-width: 100%; height: auto; }}
+                  pre {{ background: #f4f4f4; padding: 10px; border: 1px solid #ddd; }}
+                </style>
+                </head>
+                <body>{}</body></html>"#,
+                    html_content
+                ))
+            }
+            _ => Err(format!("目前后端暂不支持 {} 格式的自动转换，PDF 导出请使用预览页面的打印按钮并选择'另存为 PDF'。", format)),
+        }
+    }
+
+    /// 批量导出，`on_progress` 在每个文件处理完成后调用
+    pub fn export_batch<F>(
+        request: &BatchExportRequest,
+        on_progress: F,
+    ) -> Result<BatchExportReport, String>
+    where
+        F: Fn(BatchExportProgress) + Sync,
+    {
+        // 先校验格式，避免逐个文件报同样的错误
+        Self::wrap_document("", &request.format)?;
+
+        let (sources, base_dir) = Self::resolve_sources(request)?;
+        if sources.is_empty() {
+            return Err("没有需要导出的文件".to_string());
+        }
+
+        if let Some(file_name) = &request.merge_into {
+            if !is_plain_file_name(file_name) {
+                return Err("合并文件名不能包含路径".to_string());
+            }
+        }
+
+        let output_dir = PathBuf::from(&request.output_dir);
+        fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+
+        match &request.merge_into {
+            Some(file_name) => Self::export_merged(
+                &sources,
+                &output_dir.join(file_name),
+                &request.format,
+                &on_progress,
+            ),
+            None => Ok(Self::export_mirrored(
+                &sources,
+                &base_dir,
+                &output_dir,
+                &request.format,
+                &on_progress,
+            )),
+        }
+    }
+
+    /// 确定导出的文件列表和用于还原目录结构的根目录
+    fn resolve_sources(request: &BatchExportRequest) -> Result<(Vec<PathBuf>, PathBuf), String> {
+        let mut sources: Vec<PathBuf> = request.paths.iter().map(PathBuf::from).collect();
+
+        if let Some(directory) = &request.directory {
+            let dir = PathBuf::from(directory);
+            if !dir.is_dir() {
+                return Err("路径不存在".to_string());
+            }
+            let mut files = Vec::new();
+            collect_markdown_files(&dir, &mut files)?;
+            files.sort();
+            for file in files {
+                if !sources.contains(&file) {
+                    sources.push(file);
+                }
+            }
+            return Ok((sources, dir));
+        }
+
+        let base_dir = common_parent(&sources);
+        Ok((sources, base_dir))
+    }
+
+    /// 逐个导出并在输出目录中保持原有的目录结构
+    fn export_mirrored<F>(
+        sources: &[PathBuf],
+        base_dir: &Path,
+        output_dir: &Path,
+        format: &str,
+        on_progress: &F,
+    ) -> BatchExportReport
+    where
+        F: Fn(BatchExportProgress) + Sync,
+    {
+        let extension = if format == "docx" { "doc" } else { format };
+        let next = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+        let report = Mutex::new(BatchExportReport {
+            total: sources.len(),
+            ..Default::default()
+        });
+
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .clamp(1, MAX_WORKERS)
+            .min(sources.len());
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(source) = sources.get(idx) else {
+                        break;
+                    };
+
+                    // 不在根目录下的文件无法还原目录结构，直接报告失败，避免写到输出目录之外
+                    let output = mirrored_path(source, base_dir)
+                        .map(|relative| output_dir.join(relative).with_extension(extension));
+                    let result = match &output {
+                        Some(output) => Self::export_file(source, output, format),
+                        None => Err("文件不在导出目录中".to_string()),
+                    };
+                    let output = output.unwrap_or_default();
+
+                    let error = result.as_ref().err().cloned();
+                    {
+                        let mut report = report.lock().unwrap_or_else(|e| e.into_inner());
+                        match result {
+                            Ok(()) => report.exported.push(ExportedFile {
+                                source: source.to_string_lossy().to_string(),
+                                output: output.to_string_lossy().to_string(),
+                            }),
+                            Err(error) => report.failed.push(ExportFailure {
+                                source: source.to_string_lossy().to_string(),
+                                error,
+                            }),
+                        }
+                    }
+
+                    on_progress(BatchExportProgress {
+                        completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                        total: sources.len(),
+                        path: source.to_string_lossy().to_string(),
+                        error,
+                    });
+                });
+            }
+        });
+
+        report.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn export_file(source: &Path, output: &Path, format: &str) -> Result<(), String> {
+        let content = fs::read_to_string(source).map_err(|e| e.to_string())?;
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        Self::export(output, &content, format, false)
+    }
+
+    /// 按顺序合并为一个文档
+    fn export_merged<F>(
+        sources: &[PathBuf],
+        output: &Path,
+        format: &str,
+        on_progress: &F,
+    ) -> Result<BatchExportReport, String>
+    where
+        F: Fn(BatchExportProgress) + Sync,
+    {
+        let mut report = BatchExportReport {
+            total: sources.len(),
+            ..Default::default()
+        };
+        let mut sections = Vec::new();
+
+        for (idx, source) in sources.iter().enumerate() {
+            let error = match fs::read_to_string(source) {
+                Ok(content) => {
+                    sections.push(Self::to_html_fragment(&content, false));
+                    report.exported.push(ExportedFile {
+                        source: source.to_string_lossy().to_string(),
+                        output: output.to_string_lossy().to_string(),
+                    });
+                    None
+                }
+                Err(e) => {
+                    report.failed.push(ExportFailure {
+                        source: source.to_string_lossy().to_string(),
+                        error: e.to_string(),
+                    });
+                    Some(e.to_string())
+                }
+            };
+
+            on_progress(BatchExportProgress {
+                completed: idx + 1,
+                total: sources.len(),
+                path: source.to_string_lossy().to_string(),
+                error,
+            });
+        }
+
+        if sections.is_empty() {
+            return Err("没有可合并的文件".to_string());
+        }
+
+        // 每个文件另起一页
+        let body = sections.join("\n<div style=\"page-break-before: always\"></div>\n");
+        let document = Self::wrap_document(&body, format)?;
+        fs::write(output, document).map_err(|e| e.to_string())?;
+        Ok(report)
+    }
+}
+
+fn collect_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.')
+            || name == "node_modules"
+            || name == "target"
+            || name == "dist"
+            || name == "build"
+        {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 所有文件的公共父目录
+fn common_parent(paths: &[PathBuf]) -> PathBuf {
+    let mut parents = paths.iter().filter_map(|p| p.parent());
+    let Some(first) = parents.next() else {
+        return PathBuf::new();
+    };
+
+    let mut common = first.to_path_buf();
+    for parent in parents {
+        while !parent.starts_with(&common) {
+            if !common.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    common
+}
+
+/// 源文件相对根目录的路径，只允许普通的路径组成部分
+fn mirrored_path<'a>(source: &'a Path, base_dir: &Path) -> Option<&'a Path> {
+    let relative = source.strip_prefix(base_dir).ok()?;
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    (plain && relative.components().next().is_some()).then_some(relative)
+}
+
+/// 是否为不含目录的普通文件名
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_export_batch_mirrors_and_merges() {
+        let dir = std::env::temp_dir().join(format!("batch-export-test-{}", Uuid::new_v4()));
+        let docs = dir.join("docs");
+        fs::create_dir_all(docs.join("guide")).unwrap();
+        fs::write(docs.join("a.md"), "# Alpha").unwrap();
+        fs::write(docs.join("guide/b.md"), "# Beta").unwrap();
+
+        let progress = Mutex::new(Vec::new());
+        let report = ExportService::export_batch(
+            &BatchExportRequest {
+                directory: Some(docs.to_string_lossy().to_string()),
+                format: "html".to_string(),
+                output_dir: dir.join("out").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            |p| progress.lock().unwrap().push(p.completed),
+        )
+        .unwrap();
+
+        assert_eq!(report.exported.len(), 2);
+        assert!(dir.join("out/a.html").exists());
+        assert!(dir.join("out/guide/b.html").exists());
+        let mut completed = progress.into_inner().unwrap();
+        completed.sort();
+        assert_eq!(completed, vec![1, 2]);
+
+        // 合并时按给定顺序拼接，缺失的文件单独报告
+        let report = ExportService::export_batch(
+            &BatchExportRequest {
+                paths: vec![
+                    docs.join("guide/b.md").to_string_lossy().to_string(),
+                    docs.join("missing.md").to_string_lossy().to_string(),
+                    docs.join("a.md").to_string_lossy().to_string(),
+                ],
+                format: "html".to_string(),
+                output_dir: dir.join("merged").to_string_lossy().to_string(),
+                merge_into: Some("all.html".to_string()),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(report.exported.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        let merged = fs::read_to_string(dir.join("merged/all.html")).unwrap();
+        assert!(merged.find("Beta").unwrap() < merged.find("Alpha").unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_batch_stays_in_output_dir() {
+        let dir = std::env::temp_dir().join(format!("batch-export-escape-{}", Uuid::new_v4()));
+        let docs = dir.join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(docs.join("a.md"), "# Alpha").unwrap();
+        fs::write(dir.join("outside.md"), "# Outside").unwrap();
+
+        // 不在目录中的文件报告为失败，不会写到输出目录之外
+        let report = ExportService::export_batch(
+            &BatchExportRequest {
+                paths: vec![dir.join("outside.md").to_string_lossy().to_string()],
+                directory: Some(docs.to_string_lossy().to_string()),
+                format: "html".to_string(),
+                output_dir: dir.join("out/nested").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(report.exported.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].source.ends_with("outside.md"));
+        assert!(dir.join("out/nested/a.html").exists());
+        assert!(!dir.join("outside.html").exists());
+
+        // 合并文件名不能带路径
+        let result = ExportService::export_batch(
+            &BatchExportRequest {
+                paths: vec![docs.join("a.md").to_string_lossy().to_string()],
+                format: "html".to_string(),
+                output_dir: dir.join("merged").to_string_lossy().to_string(),
+                merge_into: Some("../all.html".to_string()),
+                ..Default::default()
+            },
+            |_| {},
+        );
+        assert!(result.is_err());
+        assert!(!dir.join("all.html").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
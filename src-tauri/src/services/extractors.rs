@@ -0,0 +1,524 @@
+//! 文档内容提取
+//! 按扩展名选择提取器，将 Markdown、纯文本、HTML、DOCX 和 PDF 转换为可分块的文本
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// 提取结果的文本格式，决定分块方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// 按标题分块
+    Markdown,
+    /// 按段落分块
+    Plain,
+}
+
+/// 提取结果
+#[derive(Debug, Clone)]
+pub struct ExtractedText {
+    pub content: String,
+    pub format: TextFormat,
+    /// 每页在 `content` 中的起始行号（从 0 开始），无分页时为空
+    pub page_starts: Vec<usize>,
+}
+
+impl ExtractedText {
+    fn new(content: String, format: TextFormat) -> Self {
+        Self {
+            content,
+            format,
+            page_starts: Vec::new(),
+        }
+    }
+}
+
+/// 文档提取器
+pub trait DocumentExtractor: Send + Sync {
+    /// 支持的扩展名（小写，不含点）
+    fn extensions(&self) -> &'static [&'static str];
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String>;
+}
+
+static EXTRACTORS: &[&dyn DocumentExtractor] = &[
+    &MarkdownExtractor,
+    &TextExtractor,
+    &HtmlExtractor,
+    &DocxExtractor,
+    &PdfExtractor,
+];
+
+/// 所有支持的扩展名
+pub fn supported_extensions() -> Vec<&'static str> {
+    EXTRACTORS
+        .iter()
+        .flat_map(|extractor| extractor.extensions().iter().copied())
+        .collect()
+}
+
+/// 按扩展名查找提取器
+pub fn extractor_for(path: &Path) -> Option<&'static dyn DocumentExtractor> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    EXTRACTORS
+        .iter()
+        .copied()
+        .find(|extractor| extractor.extensions().contains(&extension.as_str()))
+}
+
+/// 提取文档内容
+pub fn extract(path: &Path) -> Result<ExtractedText, String> {
+    extractor_for(path)
+        .ok_or_else(|| format!("不支持的文件格式: {}", path.display()))?
+        .extract(path)
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(content
+        .strip_prefix('\u{feff}')
+        .map(str::to_string)
+        .unwrap_or(content))
+}
+
+pub struct MarkdownExtractor;
+
+impl DocumentExtractor for MarkdownExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md", "markdown"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String> {
+        Ok(ExtractedText::new(read_text(path)?, TextFormat::Markdown))
+    }
+}
+
+pub struct TextExtractor;
+
+impl DocumentExtractor for TextExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt", "text"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String> {
+        Ok(ExtractedText::new(read_text(path)?, TextFormat::Plain))
+    }
+}
+
+/// HTML 转换为 Markdown 后按标题分块
+pub struct HtmlExtractor;
+
+impl DocumentExtractor for HtmlExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String> {
+        let markdown = html2md::parse_html(&read_text(path)?);
+        Ok(ExtractedText::new(
+            markdown.trim().to_string(),
+            TextFormat::Markdown,
+        ))
+    }
+}
+
+/// DOCX 段落按样式转换为 Markdown 标题、列表和表格
+pub struct DocxExtractor;
+
+impl DocumentExtractor for DocxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String> {
+        let file = fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut archive =
+            zip::ZipArchive::new(file).map_err(|e| format!("DOCX 文件无效: {}", e))?;
+
+        let styles = match read_zip_entry(&mut archive, "word/styles.xml") {
+            Ok(xml) => heading_styles(&xml)?,
+            Err(_) => HashMap::new(),
+        };
+        let document = read_zip_entry(&mut archive, "word/document.xml")
+            .map_err(|e| format!("DOCX 文件无效: {}", e))?;
+        Ok(ExtractedText::new(
+            docx_to_markdown(&document, &styles)?,
+            TextFormat::Markdown,
+        ))
+    }
+}
+
+fn read_zip_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<String, String> {
+    let mut entry = archive.by_name(name).map_err(|e| e.to_string())?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| e.to_string())?;
+    Ok(content)
+}
+
+/// 读取元素的属性（忽略命名空间前缀）
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == name)
+        .map(|attr| String::from_utf8_lossy(&attr.value).to_string())
+}
+
+/// 从 styles.xml 中找出标题样式：样式 ID → 标题级别
+fn heading_styles(xml: &str) -> Result<HashMap<String, usize>, String> {
+    let mut styles = HashMap::new();
+    let mut reader = Reader::from_str(xml);
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(e) if e.local_name().as_ref() == b"style" => {
+                current = attribute(&e, b"styleId");
+            }
+            Event::Empty(e) | Event::Start(e) => {
+                let level = match e.local_name().as_ref() {
+                    b"name" => attribute(&e, b"val").and_then(|name| {
+                        let name = name.to_lowercase();
+                        if name == "title" {
+                            Some(1)
+                        } else {
+                            name.strip_prefix("heading ")?.parse().ok()
+                        }
+                    }),
+                    b"outlineLvl" => attribute(&e, b"val")
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .map(|v| v + 1),
+                    _ => None,
+                };
+                if let (Some(id), Some(level)) = (&current, level) {
+                    styles.insert(id.clone(), level.clamp(1, 6));
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"style" => current = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(styles)
+}
+
+/// 将 document.xml 转换为 Markdown
+fn docx_to_markdown(xml: &str, styles: &HashMap<String, usize>) -> Result<String, String> {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut reader = Reader::from_str(xml);
+
+    let mut text = String::new();
+    let mut in_text = false;
+    // 段落属性中的 w:tab 是制表位定义，不是文本
+    let mut in_properties = false;
+    let mut heading: Option<usize> = None;
+    let mut list_item = false;
+    let mut table_depth = 0;
+    let mut cells: Vec<String> = Vec::new();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| e.to_string())?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                let empty = matches!(event, Event::Empty(_));
+                match e.local_name().as_ref() {
+                    b"p" if !empty => {
+                        heading = None;
+                        list_item = false;
+                        if table_depth == 0 {
+                            text.clear();
+                        }
+                    }
+                    b"pPr" if !empty => in_properties = true,
+                    b"t" if !empty => in_text = true,
+                    b"tbl" if !empty => {
+                        table_depth += 1;
+                        if table_depth == 1 {
+                            rows.clear();
+                        }
+                    }
+                    b"tr" if !empty => cells.clear(),
+                    b"tc" if !empty => text.clear(),
+                    b"pStyle" => {
+                        heading = attribute(e, b"val").and_then(|id| {
+                            styles.get(&id).copied().or_else(|| {
+                                id.strip_prefix("Heading")
+                                    .and_then(|n| n.parse().ok())
+                                    .or((id == "Title").then_some(1))
+                            })
+                        });
+                    }
+                    b"outlineLvl" => {
+                        heading = attribute(e, b"val")
+                            .and_then(|v| v.parse::<usize>().ok())
+                            .filter(|v| *v < 9)
+                            .map(|v| v + 1);
+                    }
+                    b"numPr" => list_item = true,
+                    b"tab" if !in_properties => text.push('\t'),
+                    b"br" | b"cr" => text.push(if table_depth > 0 { ' ' } else { '\n' }),
+                    _ => {}
+                }
+            }
+            Event::Text(e) if in_text => {
+                text.push_str(&e.decode().map_err(|e| e.to_string())?);
+            }
+            Event::GeneralRef(e) if in_text => {
+                if let Some(c) = e.resolve_char_ref().map_err(|e| e.to_string())? {
+                    text.push(c);
+                } else {
+                    let name = e.decode().map_err(|e| e.to_string())?;
+                    text.push_str(
+                        quick_xml::escape::resolve_predefined_entity(&name).unwrap_or(""),
+                    );
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"pPr" => in_properties = false,
+                b"p" if table_depth > 0 => text.push(' '),
+                b"p" => {
+                    let content = text.trim();
+                    if !content.is_empty() {
+                        blocks.push(match (heading, list_item) {
+                            (Some(level), _) => {
+                                format!("{} {}", "#".repeat(level.min(6)), content)
+                            }
+                            (None, true) => format!("- {}", content),
+                            (None, false) => content.to_string(),
+                        });
+                    }
+                    text.clear();
+                }
+                b"tc" => cells.push(text.trim().replace('|', "\\|")),
+                b"tr" if table_depth == 1 => rows.push(std::mem::take(&mut cells)),
+                b"tbl" => {
+                    table_depth -= 1;
+                    if table_depth == 0 && !rows.is_empty() {
+                        blocks.push(markdown_table(&rows));
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    // 列表项之间不留空行
+    let mut markdown = String::new();
+    for (idx, block) in blocks.iter().enumerate() {
+        if idx > 0 {
+            let tight = block.starts_with("- ") && blocks[idx - 1].starts_with("- ");
+            markdown.push_str(if tight { "\n" } else { "\n\n" });
+        }
+        markdown.push_str(block);
+    }
+    Ok(markdown)
+}
+
+fn markdown_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let row = |cells: &[String]| {
+        let mut cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+        cells.resize(columns, "");
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![row(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|cells| row(cells)));
+    lines.join("\n")
+}
+
+/// PDF 按页提取文本，页码记录在文本块上
+pub struct PdfExtractor;
+
+impl DocumentExtractor for PdfExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedText, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        // pdf-extract 遇到不支持的结构时可能 panic
+        let pages =
+            std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(&bytes))
+                .map_err(|_| "PDF 解析失败".to_string())?
+                .map_err(|e| format!("PDF 解析失败: {}", e))?;
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut page_starts = Vec::with_capacity(pages.len());
+        for page in &pages {
+            page_starts.push(lines.len());
+            lines.extend(
+                page.trim_end()
+                    .lines()
+                    .map(|line| line.trim_end().to_string()),
+            );
+            lines.push(String::new());
+        }
+        if lines.iter().all(|line| line.trim().is_empty()) {
+            return Err("PDF 中没有可提取的文本".to_string());
+        }
+
+        Ok(ExtractedText {
+            content: lines.join("\n"),
+            format: TextFormat::Plain,
+            page_starts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use uuid::Uuid;
+
+    fn write_docx(path: &Path, document: &str, styles: &str) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("word/document.xml", options).unwrap();
+        zip.write_all(document.as_bytes()).unwrap();
+        zip.start_file("word/styles.xml", options).unwrap();
+        zip.write_all(styles.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    /// 生成只含一行文本的多页 PDF
+    fn write_pdf(path: &Path, pages: &[&str]) {
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            String::new(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        let mut kids = Vec::new();
+        for text in pages {
+            let stream = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ));
+            kids.push(format!("{} 0 R", objects.len() + 1));
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents {} 0 R /Resources << /Font << /F1 3 0 R >> >> >>",
+                objects.len()
+            ));
+        }
+        objects[1] = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        );
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (idx, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", idx + 1, object));
+        }
+        let xref = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            pdf.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        fs::write(path, pdf).unwrap();
+    }
+
+    #[test]
+    fn test_extract_by_format() {
+        let dir = std::env::temp_dir().join(format!("extract-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let html = dir.join("page.HTML");
+        fs::write(
+            &html,
+            "<html><body><h1>标题</h1><p>第一段 &amp; 说明</p><ul><li>甲</li></ul></body></html>",
+        )
+        .unwrap();
+        let extracted = extract(&html).unwrap();
+        assert_eq!(extracted.format, TextFormat::Markdown);
+        assert_eq!(
+            extracted.content,
+            "标题\n==========\n\n第一段 & 说明\n\n* 甲"
+        );
+
+        let markdown = dir.join("readme.md");
+        fs::write(&markdown, "\u{feff}# 标题\n\n正文").unwrap();
+        let extracted = extract(&markdown).unwrap();
+        assert_eq!(extracted.format, TextFormat::Markdown);
+        assert_eq!(extracted.content, "# 标题\n\n正文");
+
+        let text = dir.join("notes.txt");
+        fs::write(&text, "\u{feff}第一段\n\n第二段").unwrap();
+        let extracted = extract(&text).unwrap();
+        assert_eq!(extracted.format, TextFormat::Plain);
+        assert_eq!(extracted.content, "第一段\n\n第二段");
+
+        let pdf = dir.join("paper.pdf");
+        write_pdf(&pdf, &["Hello page one", "Second page text"]);
+        let extracted = extract(&pdf).unwrap();
+        assert_eq!(extracted.format, TextFormat::Plain);
+        assert_eq!(extracted.page_starts.len(), 2);
+        let lines: Vec<&str> = extracted.content.lines().collect();
+        let second = extracted.page_starts[1];
+        assert!(lines[..second].join(" ").contains("Hello page one"));
+        assert!(lines[second..].join(" ").contains("Second page text"));
+
+        fs::write(dir.join("broken.pdf"), "not a pdf").unwrap();
+        assert!(extract(&dir.join("broken.pdf")).is_err());
+        assert!(extract(&dir.join("image.png")).is_err());
+        assert!(supported_extensions().contains(&"docx"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_docx_to_markdown() {
+        let dir = std::env::temp_dir().join(format!("extract-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let styles = r#"<w:styles xmlns:w="w">
+            <w:style w:styleId="1"><w:name w:val="heading 1"/></w:style>
+            <w:style w:styleId="a3"><w:name w:val="Normal"/></w:style>
+        </w:styles>"#;
+        let document = r#"<w:document xmlns:w="w"><w:body>
+            <w:p><w:pPr><w:pStyle w:val="1"/></w:pPr><w:r><w:t>概述</w:t></w:r></w:p>
+            <w:p><w:r><w:t xml:space="preserve">所有权 </w:t></w:r><w:r><w:t>&amp; 借用</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Heading2"/><w:tabs><w:tab w:val="left" w:pos="420"/></w:tabs></w:pPr><w:r><w:t>要点</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/></w:numPr></w:pPr><w:r><w:t>甲</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/></w:numPr></w:pPr><w:r><w:t>乙</w:t></w:r></w:p>
+            <w:p/>
+            <w:tbl>
+              <w:tr><w:tc><w:p><w:r><w:t>名称</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>说明</w:t></w:r></w:p></w:tc></w:tr>
+              <w:tr><w:tc><w:p><w:r><w:t>a|b</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>c</w:t></w:r></w:p></w:tc></w:tr>
+            </w:tbl>
+        </w:body></w:document>"#;
+        let path = dir.join("report.docx");
+        write_docx(&path, document, styles);
+
+        let extracted = extract(&path).unwrap();
+        assert_eq!(extracted.format, TextFormat::Markdown);
+        assert_eq!(
+            extracted.content,
+            "# 概述\n\n所有权 & 借用\n\n## 要点\n\n- 甲\n- 乙\n\n| 名称 | 说明 |\n| --- | --- |\n| a\\|b | c |"
+        );
+
+        fs::write(dir.join("fake.docx"), "not a zip").unwrap();
+        assert!(extract(&dir.join("fake.docx")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,594 @@
+//! 知识库内容生成服务
+//! 检索相关文本块，按生成类型组装提示词，调用大模型并将结果保存为 Markdown
+
+use crate::models::{
+    AiConfig, ChunkSearchResult, Document, GenerateRequest, GenerateResult, GenerateType,
+    KnowledgeBase, PromptTemplate,
+};
+use crate::services::knowledge_base_service::KnowledgeBaseService;
+use crate::services::llm_client::{ChatMessage, LlmClient, StreamEnd};
+use crate::services::prompt_template_service;
+use crate::services::search_service::SearchService;
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 提示词中资料内容的字符上限
+pub const MAX_CONTEXT_CHARS: usize = 12_000;
+
+/// 生成任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// 流式生成的文本片段事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationToken {
+    pub job_id: String,
+    pub token: String,
+}
+
+/// 流式生成结束事件；取消或失败时 `output_path` 指向已保存的部分结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationFinished {
+    pub job_id: String,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 进行中的生成任务，作为 Tauri 托管状态保存取消标记
+#[derive(Default)]
+pub struct GenerationJobs {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl GenerationJobs {
+    /// 登记任务并返回其取消标记
+    pub fn register(&self, job_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(job_id.to_string(), flag.clone());
+        }
+        flag
+    }
+
+    /// 请求取消任务，任务不存在时返回 false
+    pub fn cancel(&self, job_id: &str) -> bool {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(job_id).cloned())
+            .map(|flag| flag.store(true, Ordering::Relaxed))
+            .is_some()
+    }
+
+    /// 任务未在进行时登记并返回取消标记，检查与登记在同一次加锁中完成；
+    /// 任务已在进行时返回 `None`
+    pub fn try_register(&self, job_id: &str) -> Option<Arc<AtomicBool>> {
+        let mut jobs = self.jobs.lock().ok()?;
+        if jobs.contains_key(job_id) {
+            return None;
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        jobs.insert(job_id.to_string(), flag.clone());
+        Some(flag)
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(job_id);
+        }
+    }
+}
+
+pub struct GenerateService;
+
+impl GenerateService {
+    /// 选取生成所需的文本块：有主题时按相关度排序，否则按文档顺序，直到达到字符上限
+    pub fn select_chunks(
+        documents: &[Document],
+        topic: Option<&str>,
+        budget: usize,
+    ) -> Vec<ChunkSearchResult> {
+        let topic = topic.map(str::trim).filter(|t| !t.is_empty());
+        let mut candidates: Vec<ChunkSearchResult> = topic
+            .map(|topic| {
+                SearchService::rank(documents, topic)
+                    .into_iter()
+                    .map(|(doc_idx, chunk_idx, score)| {
+                        SearchService::to_result(&documents[doc_idx], chunk_idx, topic, score)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // 主题没有命中时退回到全部资料
+        if candidates.is_empty() {
+            candidates = documents
+                .iter()
+                .flat_map(|doc| {
+                    (0..doc.chunks.len())
+                        .map(move |idx| SearchService::to_result(doc, idx, "", 0.0))
+                })
+                .collect();
+        }
+
+        let mut used = 0;
+        let mut selected = Vec::new();
+        for chunk in candidates {
+            let len = chunk.content.chars().count();
+            if !selected.is_empty() && used + len > budget {
+                break;
+            }
+            used += len;
+            selected.push(chunk);
+        }
+        selected
+    }
+
+    /// 将文本块格式化为带编号的资料
+    pub fn format_context(chunks: &[ChunkSearchResult]) -> String {
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(idx, chunk)| {
+                let mut source = match chunk.page {
+                    Some(page) => format!("[{}] {} 第 {} 页", idx + 1, chunk.path, page),
+                    None => format!(
+                        "[{}] {} 第 {}-{} 行",
+                        idx + 1,
+                        chunk.path,
+                        chunk.start_line,
+                        chunk.end_line
+                    ),
+                };
+                if !chunk.headings.is_empty() {
+                    source.push_str(&format!(" › {}", chunk.headings.join(" > ")));
+                }
+                format!("{}\n{}", source, chunk.content.trim())
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// 模板变量：`topic`、`context`、`language`、`length`、`kb_name`，未指定的取默认值
+    pub fn template_variables(
+        kb_name: &str,
+        request: &GenerateRequest,
+        chunks: &[ChunkSearchResult],
+    ) -> HashMap<&'static str, String> {
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        [
+            (
+                "topic",
+                non_empty(&request.topic).unwrap_or_else(|| kb_name.to_string()),
+            ),
+            ("context", Self::format_context(chunks)),
+            (
+                "language",
+                non_empty(&request.language).unwrap_or_else(|| "中文".to_string()),
+            ),
+            (
+                "length",
+                non_empty(&request.length).unwrap_or_else(|| "适中".to_string()),
+            ),
+            ("kb_name", kb_name.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// 用模板变量渲染提示词
+    pub fn build_messages(
+        template: &PromptTemplate,
+        kb_name: &str,
+        request: &GenerateRequest,
+        chunks: &[ChunkSearchResult],
+    ) -> Vec<ChatMessage> {
+        let variables = Self::template_variables(kb_name, request, chunks);
+
+        let mut messages = Vec::new();
+        let system = prompt_template_service::render(&template.system_prompt, &variables);
+        if !system.trim().is_empty() {
+            messages.push(ChatMessage::system(system));
+        }
+        messages.push(ChatMessage::user(prompt_template_service::render(
+            &template.user_prompt,
+            &variables,
+        )));
+        messages
+    }
+
+    /// 校验请求并组装提示词
+    fn prepare(
+        kb_service: &KnowledgeBaseService,
+        template: &PromptTemplate,
+        request: &GenerateRequest,
+    ) -> Result<(KnowledgeBase, Vec<ChatMessage>), String> {
+        let kb = kb_service
+            .get_knowledge_base(&request.kb_id)?
+            .ok_or("知识库不存在")?;
+        let topic = request.topic.as_deref();
+        if request.generate_type == GenerateType::TopicReport
+            && topic.map_or(true, |t| t.trim().is_empty())
+        {
+            return Err("专题报告需要指定主题".to_string());
+        }
+
+        let documents = kb_service.get_documents(&kb.id)?;
+        let chunks = Self::select_chunks(&documents, topic, MAX_CONTEXT_CHARS);
+        if chunks.is_empty() {
+            return Err("知识库中没有可用的文档内容".to_string());
+        }
+
+        let messages = Self::build_messages(template, &kb.name, request, &chunks);
+        Ok((kb, messages))
+    }
+
+    /// 执行生成并将结果写入 `output_dir`
+    pub fn generate(
+        kb_service: &KnowledgeBaseService,
+        template: &PromptTemplate,
+        request: &GenerateRequest,
+        config: &AiConfig,
+        output_dir: &Path,
+    ) -> Result<GenerateResult, String> {
+        let (kb, messages) = Self::prepare(kb_service, template, request)?;
+        let content = LlmClient::new(config).chat(&messages)?;
+        let output_path = Self::save_output(
+            output_dir,
+            &kb.name,
+            &request.generate_type,
+            &content,
+            false,
+        )?;
+
+        Ok(GenerateResult {
+            success: true,
+            output_path: output_path.to_string_lossy().to_string(),
+            content,
+        })
+    }
+
+    /// 流式生成：逐段回调文本，结束后保存结果；取消或失败时保存已生成的部分
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_stream(
+        kb_service: &KnowledgeBaseService,
+        template: &PromptTemplate,
+        request: &GenerateRequest,
+        config: &AiConfig,
+        output_dir: &Path,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        mut on_token: impl FnMut(&str),
+    ) -> GenerationFinished {
+        let mut content = String::new();
+        let outcome = Self::prepare(kb_service, template, request).map(|(kb, messages)| {
+            let end = LlmClient::new(config).chat_stream(&messages, cancelled, |token| {
+                content.push_str(token);
+                on_token(token);
+            });
+            (kb, end)
+        });
+
+        let (kb, end) = match outcome {
+            Ok(result) => result,
+            Err(error) => {
+                return GenerationFinished {
+                    job_id: job_id.to_string(),
+                    status: JobStatus::Failed,
+                    output_path: None,
+                    content,
+                    error: Some(error),
+                }
+            }
+        };
+        let (status, mut error) = match end {
+            Ok(StreamEnd::Completed) => (JobStatus::Completed, None),
+            Ok(StreamEnd::Cancelled) => (JobStatus::Cancelled, None),
+            Err(error) => (JobStatus::Failed, Some(error)),
+        };
+
+        // 完整结果总是保存；部分结果仅在有内容时保存
+        let mut output_path = None;
+        if status == JobStatus::Completed || !content.is_empty() {
+            match Self::save_output(
+                output_dir,
+                &kb.name,
+                &request.generate_type,
+                &content,
+                status != JobStatus::Completed,
+            ) {
+                Ok(path) => output_path = Some(path.to_string_lossy().to_string()),
+                Err(e) => error = Some(error.map_or(e.clone(), |prev| format!("{}；{}", prev, e))),
+            }
+        }
+
+        GenerationFinished {
+            job_id: job_id.to_string(),
+            status: if status == JobStatus::Completed && error.is_some() {
+                JobStatus::Failed
+            } else {
+                status
+            },
+            output_path,
+            content,
+            error,
+        }
+    }
+
+    /// 保存生成结果，文件名包含知识库名称、生成类型和时间，部分结果追加 `-partial`
+    pub fn save_output(
+        output_dir: &Path,
+        kb_name: &str,
+        generate_type: &GenerateType,
+        content: &str,
+        partial: bool,
+    ) -> Result<PathBuf, String> {
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        let kind = match generate_type {
+            GenerateType::Summary => "summary".to_string(),
+            GenerateType::PptOutline => "ppt-outline".to_string(),
+            GenerateType::TopicReport => "topic-report".to_string(),
+            GenerateType::Custom(id) => file_name_part(id),
+        };
+        let name = file_name_part(kb_name);
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        let suffix = if partial { "-partial" } else { "" };
+
+        let mut path = output_dir.join(format!("{}-{}-{}{}.md", name, kind, stamp, suffix));
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = output_dir.join(format!("{}-{}-{}-{}{}.md", name, kind, stamp, n, suffix));
+        }
+
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
+
+/// 将名称中不能用于文件名的字符替换为 `-`
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_whitespace() || "/\\:*?\"<>|".contains(c) {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::prompt_template_service::builtin_templates;
+    use crate::services::test_support::{StubResponse, StubServer};
+    use uuid::Uuid;
+
+    fn builtin(id: &str) -> PromptTemplate {
+        builtin_templates()
+            .into_iter()
+            .find(|t| t.id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_generate_writes_markdown() {
+        let dir = std::env::temp_dir().join(format!("generate-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("rust.md");
+        fs::write(
+            &note,
+            "# Rust\n\n所有权系统保证内存安全。\n---\n## 并发\n\n借用检查器阻止数据竞争。",
+        )
+        .unwrap();
+
+        let kb_service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = kb_service
+            .create_knowledge_base("Rust 笔记".to_string(), None)
+            .unwrap();
+        kb_service
+            .import_document(&kb.id, &note.to_string_lossy())
+            .unwrap();
+
+        let server = StubServer::start(vec![StubResponse::json(
+            r##"{"choices":[{"message":{"role":"assistant","content":"# 并发报告\n\n借用检查器 [1]"}}]}"##,
+        )]);
+        let config = AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        };
+        let mut request = GenerateRequest {
+            kb_id: kb.id.clone(),
+            generate_type: GenerateType::TopicReport,
+            topic: None,
+            language: None,
+            length: None,
+            output_dir: None,
+        };
+        let out_dir = dir.join("out");
+        let template = builtin("topicReport");
+        assert!(
+            GenerateService::generate(&kb_service, &template, &request, &config, &out_dir).is_err()
+        );
+
+        request.topic = Some("数据竞争".to_string());
+        let result =
+            GenerateService::generate(&kb_service, &template, &request, &config, &out_dir).unwrap();
+        assert!(result.success);
+        assert!(result.output_path.ends_with(".md"));
+        assert!(result.output_path.contains("Rust-笔记-topic-report-"));
+        assert_eq!(
+            fs::read_to_string(&result.output_path).unwrap(),
+            "# 并发报告\n\n借用检查器 [1]"
+        );
+
+        // 相关度最高的文本块排在第一位
+        let body = server.requests()[0].json();
+        let prompt = body["messages"][1]["content"].as_str().unwrap().to_string();
+        assert!(prompt.contains("「数据竞争」"));
+        let first = prompt.find("[1]").unwrap();
+        assert!(prompt[first..].starts_with(&format!("[1] {} 第 4-6 行", note.to_string_lossy())));
+        assert!(prompt.contains("借用检查器阻止数据竞争"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_stream_saves_partial_result() {
+        let dir = std::env::temp_dir().join(format!("generate-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        fs::write(&note, "# 笔记\n\n流式输出").unwrap();
+
+        let kb_service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = kb_service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        kb_service
+            .import_document(&kb.id, &note.to_string_lossy())
+            .unwrap();
+
+        let events = [
+            r##"{"choices":[{"delta":{"content":"# 总结\n"}}]}"##,
+            r#"{"choices":[{"delta":{"content":"第一段"}}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+        let server = StubServer::start(vec![
+            StubResponse::sse(&events),
+            StubResponse::sse(&events[..2]),
+        ]);
+        let config = AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        };
+        let request = GenerateRequest {
+            kb_id: kb.id.clone(),
+            generate_type: GenerateType::Summary,
+            topic: None,
+            language: None,
+            length: None,
+            output_dir: None,
+        };
+        let out_dir = dir.join("out");
+        let template = builtin("summary");
+        let jobs = GenerationJobs::default();
+
+        let cancelled = jobs.register("job-1");
+        let mut tokens = Vec::new();
+        let finished = GenerateService::generate_stream(
+            &kb_service,
+            &template,
+            &request,
+            &config,
+            &out_dir,
+            "job-1",
+            &cancelled,
+            |t| {
+                tokens.push(t.to_string());
+                jobs.cancel("job-1");
+            },
+        );
+        assert_eq!(finished.status, JobStatus::Cancelled);
+        assert_eq!(tokens, vec!["# 总结\n"]);
+        let partial = finished.output_path.unwrap();
+        assert!(partial.ends_with("-partial.md"));
+        assert_eq!(fs::read_to_string(&partial).unwrap(), "# 总结\n");
+        // 进行中的任务不能重复登记
+        assert!(jobs.try_register("job-1").is_none());
+        jobs.finish("job-1");
+        assert!(!jobs.cancel("job-1"));
+        assert!(jobs.try_register("job-1").is_some());
+
+        // 流中断时同样保存已生成的内容
+        let finished = GenerateService::generate_stream(
+            &kb_service,
+            &template,
+            &request,
+            &config,
+            &out_dir,
+            "job-2",
+            &AtomicBool::new(false),
+            |_| {},
+        );
+        assert_eq!(finished.status, JobStatus::Failed);
+        assert!(finished.error.is_some());
+        assert_eq!(finished.content, "# 总结\n第一段");
+        assert!(finished.output_path.unwrap().ends_with("-partial.md"));
+
+        server.requests();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_custom_template_variables() {
+        let request: GenerateRequest = serde_json::from_str(
+            r#"{"kbId":"kb","generateType":"custom-1234","language":"English","length":"简短"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request.generate_type,
+            GenerateType::Custom("custom-1234".to_string())
+        );
+        assert_eq!(
+            serde_json::to_value(GenerateType::PptOutline).unwrap(),
+            "pptOutline"
+        );
+
+        let template = PromptTemplate {
+            id: "custom-1234".to_string(),
+            name: "术语表".to_string(),
+            description: None,
+            system_prompt: " ".to_string(),
+            user_prompt: "{{kb_name}}/{{topic}}/{{language}}/{{length}}\n{{context}}".to_string(),
+            built_in: false,
+            updated_at: None,
+        };
+        let chunks = vec![ChunkSearchResult {
+            document_id: "d".to_string(),
+            document_title: "D".to_string(),
+            path: "/notes/a.md".to_string(),
+            chunk_id: "c".to_string(),
+            content: "内容".to_string(),
+            snippets: Vec::new(),
+            headings: vec!["A".to_string(), "B".to_string()],
+            start_line: 1,
+            end_line: 2,
+            page: None,
+            score: 0.0,
+        }];
+
+        let messages = GenerateService::build_messages(&template, "KB", &request, &chunks);
+        assert_eq!(
+            messages,
+            vec![ChatMessage::user(
+                "KB/KB/English/简短\n[1] /notes/a.md 第 1-2 行 › A > B\n内容"
+            )]
+        );
+    }
+}
@@ -0,0 +1,606 @@
+//! 知识库数据库的表结构与版本迁移
+//! 版本号保存在 `PRAGMA user_version`，打开时按顺序执行未应用的迁移，迁移前自动备份；
+//! 数据库损坏时隔离原文件并新建数据库，隔离的文件可通过备份恢复
+
+use crate::models::{StorageBackup, StorageBackupKind};
+use crate::services::knowledge_base_service::content_hash;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, ErrorCode, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 当前数据库版本，新增迁移时递增
+pub const SCHEMA_VERSION: i32 = 4;
+/// 保留的迁移前备份数量（不含隔离的文件）
+const MAX_BACKUPS: usize = 5;
+/// 迁移前备份的文件名前缀
+const BACKUP_PREFIX: &str = "kb-backup-";
+/// 隔离的损坏数据库的文件名前缀
+const QUARANTINE_PREFIX: &str = "kb-corrupt-";
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS knowledge_bases (
+    id          TEXT PRIMARY KEY,
+    name        TEXT NOT NULL,
+    description TEXT,
+    created_at  TEXT NOT NULL,
+    updated_at  TEXT NOT NULL,
+    chunking    TEXT,
+    icon        TEXT,
+    color       TEXT,
+    tags        TEXT
+);
+
+CREATE TABLE IF NOT EXISTS documents (
+    id         TEXT PRIMARY KEY,
+    kb_id      TEXT NOT NULL REFERENCES knowledge_bases(id) ON DELETE CASCADE,
+    path       TEXT NOT NULL,
+    title      TEXT NOT NULL,
+    content    TEXT NOT NULL,
+    metadata   TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    content_hash   TEXT NOT NULL DEFAULT '',
+    modified_at    TEXT,
+    source_missing INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_documents_kb ON documents(kb_id);
+CREATE INDEX IF NOT EXISTS idx_documents_path ON documents(kb_id, path);
+
+CREATE TABLE IF NOT EXISTS chunks (
+    id          TEXT PRIMARY KEY,
+    document_id TEXT NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    position    INTEGER NOT NULL,
+    content     TEXT NOT NULL,
+    start_line  INTEGER NOT NULL,
+    end_line    INTEGER NOT NULL,
+    headings    TEXT NOT NULL,
+    page        INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_chunks_document ON chunks(document_id, position);
+
+CREATE TABLE IF NOT EXISTS chunk_embeddings (
+    chunk_id TEXT NOT NULL REFERENCES chunks(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    vector   BLOB NOT NULL,
+    PRIMARY KEY (chunk_id, provider)
+);
+
+CREATE TABLE IF NOT EXISTS document_terms (
+    document_id TEXT NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    term        TEXT NOT NULL,
+    count       INTEGER NOT NULL,
+    PRIMARY KEY (document_id, term)
+);
+
+CREATE TABLE IF NOT EXISTS conversations (
+    id         TEXT PRIMARY KEY,
+    kb_id      TEXT NOT NULL REFERENCES knowledge_bases(id) ON DELETE CASCADE,
+    title      TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_conversations_kb ON conversations(kb_id);
+
+CREATE TABLE IF NOT EXISTS messages (
+    id              TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    position        INTEGER NOT NULL,
+    role            TEXT NOT NULL,
+    content         TEXT NOT NULL,
+    citations       TEXT NOT NULL,
+    created_at      TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_messages_conversation ON messages(conversation_id, position);
+"#;
+
+/// 版本 3 新增的专题报告任务表，`state` 保存整个任务的 JSON
+const REPORT_JOBS_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS report_jobs (
+    id         TEXT PRIMARY KEY,
+    kb_id      TEXT NOT NULL REFERENCES knowledge_bases(id) ON DELETE CASCADE,
+    state      TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_report_jobs_kb ON report_jobs(kb_id);
+"#;
+
+/// 在已有数据库上补充的列：(表, 列, 定义)
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    (
+        "documents",
+        "content_hash",
+        "content_hash TEXT NOT NULL DEFAULT ''",
+    ),
+    ("documents", "modified_at", "modified_at TEXT"),
+    (
+        "documents",
+        "source_missing",
+        "source_missing INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("chunks", "page", "page INTEGER"),
+    ("knowledge_bases", "chunking", "chunking TEXT"),
+    ("knowledge_bases", "icon", "icon TEXT"),
+    ("knowledge_bases", "color", "color TEXT"),
+    ("knowledge_bases", "tags", "tags TEXT"),
+];
+
+/// 数据库迁移，在事务中执行，成功后将 `user_version` 更新为 `version`
+struct Migration {
+    version: i32,
+    description: &'static str,
+    apply: fn(&Connection) -> Result<(), String>,
+}
+
+/// 按版本顺序排列；已发布的迁移不能修改，只能追加
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "创建表结构并为无版本号的旧数据库补充列",
+        apply: create_tables,
+    },
+    Migration {
+        version: 2,
+        description: "补算旧版文档的内容哈希",
+        apply: backfill_content_hashes,
+    },
+    Migration {
+        version: 3,
+        description: "创建专题报告任务表",
+        apply: create_report_jobs,
+    },
+    Migration {
+        version: 4,
+        description: "记录文档分词时的内容哈希",
+        apply: add_terms_hash,
+    },
+];
+
+/// 打开数据库失败的原因
+enum OpenError {
+    /// 文件损坏或不是数据库，只能隔离
+    Corrupt(String),
+    /// 版本过高、被其他进程锁定、磁盘已满等，数据本身完好
+    Other(String),
+}
+
+impl From<OpenError> for String {
+    fn from(error: OpenError) -> Self {
+        match error {
+            OpenError::Corrupt(e) | OpenError::Other(e) => e,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OpenError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error.sqlite_error_code() {
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+                OpenError::Corrupt(error.to_string())
+            }
+            _ => OpenError::Other(error.to_string()),
+        }
+    }
+}
+
+/// 打开数据库并迁移到当前版本；文件损坏时隔离原文件并新建数据库，
+/// 返回连接和被隔离文件的路径。其他错误原样返回，不改动原文件
+pub fn open_database(
+    db_path: &Path,
+    backup_dir: &Path,
+) -> Result<(Connection, Option<PathBuf>), String> {
+    match try_open(db_path, backup_dir) {
+        Ok(conn) => Ok((conn, None)),
+        Err(OpenError::Corrupt(e)) if db_path.exists() => {
+            log::error!("知识库数据库已损坏，已隔离并新建: {}", e);
+            let quarantined = quarantine(db_path, backup_dir)?;
+            let conn = open_and_migrate(db_path, backup_dir)?;
+            Ok((conn, Some(quarantined)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 打开数据库、检查完整性并执行迁移
+pub fn open_and_migrate(db_path: &Path, backup_dir: &Path) -> Result<Connection, String> {
+    try_open(db_path, backup_dir).map_err(String::from)
+}
+
+fn try_open(db_path: &Path, backup_dir: &Path) -> Result<Connection, OpenError> {
+    let mut conn = Connection::open(db_path)?;
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;",
+    )?;
+    quick_check(&conn)?;
+    migrate(&mut conn, backup_dir).map_err(OpenError::Other)?;
+    Ok(conn)
+}
+
+/// 以只读方式打开数据库，不迁移、不隔离，供不打开窗口的外部进程查询；
+/// 数据库还不存在时返回空的内存数据库，版本与当前版本不一致时报错
+pub fn open_read_only(db_path: &Path) -> Result<Connection, String> {
+    if !db_path.exists() {
+        let mut conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+        migrate(&mut conn, Path::new(""))?;
+        return Ok(conn);
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    conn.execute_batch("PRAGMA busy_timeout = 5000;")
+        .map_err(|e| e.to_string())?;
+    let version = user_version(&conn)?;
+    if version != SCHEMA_VERSION {
+        return Err(format!(
+            "数据库版本 {} 与当前版本 {} 不一致，请先打开应用完成升级",
+            version, SCHEMA_VERSION
+        ));
+    }
+    Ok(conn)
+}
+
+/// 执行未应用的迁移；已有数据的数据库先备份
+fn migrate(conn: &mut Connection, backup_dir: &Path) -> Result<(), String> {
+    let version = user_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "数据库版本 {} 高于当前支持的版本 {}，请升级应用",
+            version, SCHEMA_VERSION
+        ));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let has_tables: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if has_tables {
+        let backup = backup_database(conn, backup_dir, &format!("v{}", version))?;
+        log::info!("迁移前已备份知识库数据库到 {}", backup.display());
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        (migration.apply)(&tx)
+            .map_err(|e| format!("数据库迁移到版本 {} 失败: {}", migration.version, e))?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        log::info!(
+            "知识库数据库已迁移到版本 {}: {}",
+            migration.version,
+            migration.description
+        );
+    }
+    Ok(())
+}
+
+fn user_version(conn: &Connection) -> Result<i32, String> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+fn quick_check(conn: &Connection) -> Result<(), OpenError> {
+    let result: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(OpenError::Corrupt(format!("数据库已损坏: {}", result)))
+    }
+}
+
+/// 检查备份文件是否可以恢复：完整且版本不高于当前版本
+pub fn validate_backup(path: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    quick_check(&conn)?;
+    let version = user_version(&conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "备份的数据库版本 {} 高于当前支持的版本 {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// 生成数据库快照，并清理过多的旧备份
+pub fn backup_database(
+    conn: &Connection,
+    backup_dir: &Path,
+    label: &str,
+) -> Result<PathBuf, String> {
+    let path = snapshot_database(conn, backup_dir, label)?;
+    prune_backups(backup_dir)?;
+    Ok(path)
+}
+
+/// 用 `VACUUM INTO` 生成数据库的一致快照，不清理旧备份
+pub fn snapshot_database(
+    conn: &Connection,
+    backup_dir: &Path,
+    label: &str,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_dir).map_err(|e| e.to_string())?;
+    let path = backup_dir.join(format!(
+        "{}{}-{}.db",
+        BACKUP_PREFIX,
+        label,
+        Utc::now().format("%Y%m%d-%H%M%S%3f")
+    ));
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+        .map_err(|e| format!("备份数据库失败: {}", e))?;
+    Ok(path)
+}
+
+/// 只保留最新的 `MAX_BACKUPS` 个备份，隔离的文件不受影响
+pub fn prune_backups(backup_dir: &Path) -> Result<(), String> {
+    let mut backups: Vec<StorageBackup> = list_backups(backup_dir)?
+        .into_iter()
+        .filter(|b| b.kind == StorageBackupKind::Backup)
+        .collect();
+    if backups.len() > MAX_BACKUPS {
+        for old in backups.split_off(MAX_BACKUPS) {
+            if let Err(e) = fs::remove_file(backup_dir.join(&old.name)) {
+                log::warn!("删除旧备份失败 {}: {}", old.name, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 将数据库文件（及 WAL 文件）移入备份目录
+fn quarantine(db_path: &Path, backup_dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_dir).map_err(|e| e.to_string())?;
+    let target = backup_dir.join(format!(
+        "{}{}.db",
+        QUARANTINE_PREFIX,
+        Utc::now().format("%Y%m%d-%H%M%S%3f")
+    ));
+    fs::rename(db_path, &target).map_err(|e| format!("隔离数据库失败: {}", e))?;
+    for suffix in ["-wal", "-shm"] {
+        let side = with_suffix(db_path, suffix);
+        if side.exists() {
+            let _ = fs::rename(&side, with_suffix(&target, suffix));
+        }
+    }
+    Ok(target)
+}
+
+/// 用备份文件替换数据库文件，调用前必须关闭原数据库连接
+pub fn replace_database(db_path: &Path, backup: &Path) -> Result<(), String> {
+    for suffix in ["-wal", "-shm"] {
+        let side = with_suffix(db_path, suffix);
+        if side.exists() {
+            fs::remove_file(&side).map_err(|e| e.to_string())?;
+        }
+    }
+    fs::copy(backup, db_path).map_err(|e| e.to_string())?;
+    let wal = with_suffix(backup, "-wal");
+    if wal.exists() {
+        fs::copy(&wal, with_suffix(db_path, "-wal")).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 列出备份目录中的备份和隔离文件，最新的在前
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<StorageBackup>, String> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backup_dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let kind = if name.starts_with(QUARANTINE_PREFIX) {
+            StorageBackupKind::Quarantined
+        } else if name.starts_with(BACKUP_PREFIX) {
+            StorageBackupKind::Backup
+        } else {
+            continue;
+        };
+        if !name.ends_with(".db") {
+            continue;
+        }
+        let meta = entry.metadata().map_err(|e| e.to_string())?;
+        backups.push(StorageBackup {
+            name,
+            kind,
+            size: meta.len(),
+            created_at: meta
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now()),
+        });
+    }
+    backups.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| b.name.cmp(&a.name))
+    });
+    Ok(backups)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 版本 1：创建所有表；无版本号的旧数据库先补充后来新增的列
+fn create_tables(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(conn)?;
+    conn.execute_batch(SCHEMA).map_err(|e| e.to_string())
+}
+
+/// 为旧版数据库的表补充新增的列
+fn add_missing_columns(conn: &Connection) -> Result<(), String> {
+    for (table, name, definition) in ADDED_COLUMNS {
+        let mut stmt = conn
+            .prepare("SELECT name FROM pragma_table_info(?1)")
+            .map_err(|e| e.to_string())?;
+        let columns = stmt
+            .query_map(params![table], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        // 新数据库由 SCHEMA 直接创建完整的表
+        if columns.is_empty() || columns.iter().any(|c| c == name) {
+            continue;
+        }
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {}", table, definition))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 为缺少内容哈希的文档（旧版数据）按已保存的内容补算哈希
+fn backfill_content_hashes(conn: &Connection) -> Result<(), String> {
+    let pending: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM documents WHERE content_hash = ''")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    for (id, content) in &pending {
+        conn.execute(
+            "UPDATE documents SET content_hash = ?2 WHERE id = ?1",
+            params![id, content_hash(content)],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 版本 3：保存专题报告的中间步骤，失败后可以继续生成
+fn create_report_jobs(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(REPORT_JOBS_SCHEMA)
+        .map_err(|e| e.to_string())
+}
+
+/// 版本 4：`terms_hash` 记录文档分词时的内容哈希，没有词语的文档也不会被重复分词
+fn add_terms_hash(conn: &Connection) -> Result<(), String> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('documents') WHERE name = 'terms_hash')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if exists {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "ALTER TABLE documents ADD COLUMN terms_hash TEXT;
+         UPDATE documents SET terms_hash = content_hash
+         WHERE EXISTS (SELECT 1 FROM document_terms t WHERE t.document_id = documents.id);",
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kb-schema-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_migrates_unversioned_database() {
+        let dir = temp_dir();
+        let db_path = dir.join("kb.db");
+        let backup_dir = dir.join("backups");
+        {
+            // 最早发布的表结构，没有版本号
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE knowledge_bases (id TEXT PRIMARY KEY, name TEXT NOT NULL,
+                     description TEXT, created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+                 CREATE TABLE documents (id TEXT PRIMARY KEY, kb_id TEXT NOT NULL, path TEXT NOT NULL,
+                     title TEXT NOT NULL, content TEXT NOT NULL, metadata TEXT NOT NULL,
+                     created_at TEXT NOT NULL);
+                 CREATE TABLE chunks (id TEXT PRIMARY KEY, document_id TEXT NOT NULL,
+                     position INTEGER NOT NULL, content TEXT NOT NULL, start_line INTEGER NOT NULL,
+                     end_line INTEGER NOT NULL, headings TEXT NOT NULL);
+                 INSERT INTO knowledge_bases VALUES ('kb', 'KB', NULL, '2024-01-01', '2024-01-01');
+                 INSERT INTO documents VALUES ('doc', 'kb', '/a.md', 'A', 'hello', '{}', '2024-01-01');",
+            )
+            .unwrap();
+        }
+
+        let (conn, quarantined) = open_database(&db_path, &backup_dir).unwrap();
+        assert!(quarantined.is_none());
+        assert_eq!(user_version(&conn).unwrap(), SCHEMA_VERSION);
+        let (hash, page): (String, Option<i64>) = conn
+            .query_row(
+                "SELECT d.content_hash, (SELECT page FROM chunks LIMIT 1) FROM documents d",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(hash, content_hash("hello"));
+        assert_eq!(page, None);
+
+        // 迁移前的备份保留原始版本号
+        let backups = list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].kind, StorageBackupKind::Backup);
+        assert!(backups[0].name.starts_with("kb-backup-v0-"));
+        validate_backup(&backup_dir.join(&backups[0].name)).unwrap();
+        drop(conn);
+
+        // 已是最新版本时不再备份
+        open_database(&db_path, &backup_dir).unwrap();
+        assert_eq!(list_backups(&backup_dir).unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_quarantines_unreadable_database() {
+        let dir = temp_dir();
+        let db_path = dir.join("kb.db");
+        let backup_dir = dir.join("backups");
+        fs::write(&db_path, vec![0x42; 8192]).unwrap();
+
+        let (conn, quarantined) = open_database(&db_path, &backup_dir).unwrap();
+        let quarantined = quarantined.unwrap();
+        assert!(quarantined.exists());
+        assert!(validate_backup(&quarantined).is_err());
+        assert_eq!(user_version(&conn).unwrap(), SCHEMA_VERSION);
+        drop(conn);
+
+        // 更高版本的数据库完好，只报错而不隔离，升级应用后仍可打开
+        Connection::open(&db_path)
+            .unwrap()
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        assert!(open_database(&db_path, &backup_dir)
+            .unwrap_err()
+            .contains("请升级应用"));
+        assert_eq!(
+            user_version(&Connection::open(&db_path).unwrap()).unwrap(),
+            SCHEMA_VERSION + 1
+        );
+        let kinds: Vec<StorageBackupKind> = list_backups(&backup_dir)
+            .unwrap()
+            .iter()
+            .map(|b| b.kind)
+            .collect();
+        assert_eq!(kinds, vec![StorageBackupKind::Quarantined]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
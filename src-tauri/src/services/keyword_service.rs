@@ -0,0 +1,228 @@
+//! 关键词与标签提取
+//! 使用结巴分词切分中英文混排文本，按知识库内的 TF-IDF 选出每篇文档的关键词；
+//! 标签来自 Front Matter 的 `tags` 字段
+
+use crate::services::chunking::{closes_fence, fence_marker, front_matter_end};
+use jieba_rs::Jieba;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// 每篇文档保留的关键词数量
+pub const MAX_KEYWORDS: usize = 10;
+
+/// 中文停用词
+const CHINESE_STOP_WORDS: &str = "\
+一个 一些 一种 一样 一般 不是 不会 不能 之后 之前 也是 了解 \
+什么 他们 以上 以下 以及 但是 使用 例如 其中 其他 具有 出来 \
+包括 即可 只是 只有 可以 可能 同时 因为 因此 如何 如果 如下 \
+它们 对于 就是 已经 并且 当前 我们 所以 所有 才能 比如 \
+没有 然后 现在 由于 目前 相关 看到 知道 而且 而是 自己 进行 \
+还是 这个 这些 这是 这样 这种 通过 那么 那些 需要 非常 首先 \
+还有 或者 以后 为了 什么样 怎么 怎样 然而 虽然 就会 能够 应该";
+
+/// 英文停用词及链接残留
+const ENGLISH_STOP_WORDS: &str = "\
+about after all also an and any are as at be because been before \
+but by can could did do does each for from had has have he \
+her his how if in into is it its just may more most no \
+not of on one only or other our she should so some such than \
+that the their them then there these they this those to too up use \
+used using very was we were what when where which while who will with \
+would you your \
+http https www com";
+
+fn jieba() -> &'static Jieba {
+    static JIEBA: OnceLock<Jieba> = OnceLock::new();
+    JIEBA.get_or_init(Jieba::new)
+}
+
+fn stop_words() -> &'static HashSet<&'static str> {
+    static STOP_WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    STOP_WORDS.get_or_init(|| {
+        CHINESE_STOP_WORDS
+            .split_whitespace()
+            .chain(ENGLISH_STOP_WORDS.split_whitespace())
+            .collect()
+    })
+}
+
+/// 分词并统计词频；跳过 Front Matter、代码块、停用词、单字和纯数字
+pub fn term_counts(content: &str) -> HashMap<String, usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut counts = HashMap::new();
+
+    let mut fence = None;
+    for line in lines
+        .iter()
+        .skip(front_matter_end(&lines).map_or(0, |end| end + 1))
+    {
+        match fence {
+            Some(marker) => {
+                if closes_fence(line, marker) {
+                    fence = None;
+                }
+                continue;
+            }
+            None if fence_marker(line).is_some() => {
+                fence = fence_marker(line);
+                continue;
+            }
+            None => {}
+        }
+
+        for word in jieba().cut(line, true) {
+            let word = word.trim().to_lowercase();
+            if word.chars().count() < 2
+                || !word.chars().any(char::is_alphabetic)
+                || !word
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                || stop_words().contains(word.as_str())
+            {
+                continue;
+            }
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// 按 TF-IDF 为每篇文档选出关键词，IDF 在传入的文档集合内计算
+pub fn rank_keywords(documents: &[HashMap<String, usize>]) -> Vec<Vec<String>> {
+    let mut doc_freq: HashMap<String, usize> = HashMap::new();
+    for terms in documents {
+        for term in terms.keys() {
+            *doc_freq.entry(term.clone()).or_insert(0) += 1;
+        }
+    }
+
+    documents
+        .iter()
+        .map(|terms| top_keywords(terms, &doc_freq, documents.len()))
+        .collect()
+}
+
+/// 按 TF-IDF 选出一篇文档的关键词，`doc_freq` 为各词出现的文档数，`total_docs` 为文档总数
+fn top_keywords(
+    terms: &HashMap<String, usize>,
+    doc_freq: &HashMap<String, usize>,
+    total_docs: usize,
+) -> Vec<String> {
+    let total: usize = terms.values().sum();
+    let mut scored: Vec<(&str, f64)> = terms
+        .iter()
+        .map(|(term, count)| {
+            let freq = doc_freq.get(term).copied().unwrap_or(1);
+            let idf = ((total_docs as f64 + 1.0) / (freq as f64 + 1.0)).ln() + 1.0;
+            (term.as_str(), *count as f64 / total as f64 * idf)
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.0.cmp(b.0))
+    });
+    scored
+        .into_iter()
+        .take(MAX_KEYWORDS)
+        .map(|(term, _)| term.to_string())
+        .collect()
+}
+
+/// 读取 Front Matter 中的 `tags`，支持 `[a, b]`、`a, b` 和 YAML 列表写法
+pub fn front_matter_tags(content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(end) = front_matter_end(&lines) else {
+        return Vec::new();
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    let mut push = |item: &str| {
+        let tag = item
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .trim_start_matches('#')
+            .trim();
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    };
+
+    let mut in_list = false;
+    for line in &lines[1..end] {
+        if in_list {
+            if let Some(item) = line.trim_start().strip_prefix('-') {
+                push(item);
+                continue;
+            }
+            in_list = false;
+        }
+        // 只读取顶层字段
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !matches!(key.trim().to_lowercase().as_str(), "tags" | "tag") {
+            continue;
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            in_list = true;
+            continue;
+        }
+        let value = value.trim_start_matches('[').trim_end_matches(']');
+        value.split([',', '，']).for_each(&mut push);
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_tags() {
+        let inline = "---\ntitle: 笔记\ntags: [Rust, \"并发\", rust]\n---\n# 正文";
+        assert_eq!(front_matter_tags(inline), vec!["Rust", "并发"]);
+
+        let list = "---\ntags:\n  - 数据库\n  - '#sqlite'\nauthor: me\n---\n正文";
+        assert_eq!(front_matter_tags(list), vec!["数据库", "sqlite"]);
+
+        assert!(front_matter_tags("# 没有 Front Matter\ntags: [x]").is_empty());
+    }
+
+    #[test]
+    fn test_keywords_by_tf_idf() {
+        let documents: Vec<HashMap<String, usize>> = [
+            "---\ntags: [忽略]\n---\n# 所有权\n\n所有权系统管理内存。借用检查器检查所有权和借用规则。\n\n```rust\nlet borrowed = &value;\n```",
+            "# 并发\n\n线程之间通过通道传递消息，借用规则同样适用于并发代码。",
+            "# 数据库\n\nSQLite 数据库使用 WAL 模式，数据库文件与 WAL 文件放在一起。",
+        ]
+        .iter()
+        .map(|content| term_counts(content))
+        .collect();
+
+        // 停用词、单字、Front Matter 和代码块内容不计入
+        assert!(documents[0].contains_key("所有权"));
+        assert!(!documents[0].contains_key("忽略"));
+        assert!(!documents[0].contains_key("borrowed"));
+        assert!(!documents[2].contains_key("使用"));
+        assert_eq!(documents[2]["sqlite"], 1);
+
+        let keywords = rank_keywords(&documents);
+        assert_eq!(keywords[0][0], "所有权");
+        assert_eq!(keywords[2][0], "数据库");
+        // 多篇文档共有的词排在独有的词之后
+        let position = |doc: usize, term: &str| {
+            keywords[doc]
+                .iter()
+                .position(|k| k == term)
+                .unwrap_or_else(|| panic!("缺少关键词 {}", term))
+        };
+        assert!(position(0, "内存") < position(0, "规则"));
+        assert!(keywords.iter().all(|k| k.len() <= MAX_KEYWORDS));
+    }
+}
@@ -1,53 +1,218 @@
 //! 知识库存储服务
-//! 使用 JSON 文件存储知识库数据
+//! 使用 SQLite 存储知识库、文档和文本块
 
-use crate::models::{Chunk, Document, DocumentMetadata, HeadingInfo, KnowledgeBase};
-use chrono::Utc;
+use crate::models::{
+    Chunk, ChunkingConfig, Conversation, ConversationMessage, Document, DocumentListQuery,
+    DocumentMetadata, DocumentPage, DocumentSortField, DocumentSummary, FailedFile,
+    FolderImportOptions, ImportFileResult, ImportProgress, ImportReport, ImportStatus,
+    KnowledgeBase, KnowledgeBaseInput, ReportJob, StorageStatus, SyncReport, TagCount,
+};
+use crate::services::chunking::{self, ChunkingStrategy, ParagraphChunker};
+use crate::services::embedding_service::{self, EmbeddingProvider};
+use crate::services::extractors::{self, TextFormat};
+use crate::services::kb_schema;
+use crate::services::keyword_service;
+use chrono::{DateTime, Utc};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockWriteGuard};
 use uuid::Uuid;
 
-/// 存储数据结构
+/// 数据库文件名
+const DB_FILE: &str = "kb.db";
+/// 旧版 JSON 存储文件名
+const LEGACY_FILE: &str = "kb_data.json";
+/// 数据库备份目录
+const BACKUP_DIR: &str = "backups";
+
+/// 文件夹导入每批写入的文档数
+const IMPORT_BATCH_SIZE: usize = 64;
+
+/// 写入文档时的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreOutcome {
+    /// 新文档
+    Inserted,
+    /// 同路径文档内容有变化，已重新分块
+    Updated,
+    /// 同路径文档内容未变
+    Unchanged,
+}
+
+/// 同步时需要写入的变更
+enum SyncChange {
+    Content(Box<Document>),
+    Touch(String, Option<DateTime<Utc>>),
+    Missing(String),
+}
+
+/// 旧版 JSON 存储数据结构，仅用于迁移；逐条解析，无法识别的条目单独跳过
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 struct StorageData {
-    knowledge_bases: Vec<KnowledgeBase>,
-    documents: Vec<Document>,
+    knowledge_bases: Vec<serde_json::Value>,
+    documents: Vec<serde_json::Value>,
+}
+
+/// 文本块在某个嵌入提供方下的向量
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEmbedding {
+    pub chunk_id: String,
+    pub provider: String,
+    pub vector: Vec<f32>,
+}
+
+/// 知识库读取缓存，任何写入都会使对应条目失效
+#[derive(Default)]
+struct Cache {
+    knowledge_bases: Option<Vec<KnowledgeBase>>,
+    documents: HashMap<String, Vec<Document>>,
 }
 
 /// 知识库存储服务
+///
+/// 作为 Tauri 托管状态在整个应用生命周期内共享：写入通过连接锁串行化，
+/// 读取优先命中内存缓存。
 pub struct KnowledgeBaseService {
-    storage_path: PathBuf,
+    storage_dir: PathBuf,
+    conn: Mutex<Connection>,
+    cache: RwLock<Cache>,
+    /// 打开时因损坏或无法迁移而被隔离的数据库
+    quarantined: Option<PathBuf>,
 }
 
 impl KnowledgeBaseService {
     /// 创建服务实例
     pub fn new() -> Result<Self, String> {
-        let storage_dir = dirs::data_dir()
-            .ok_or("无法获取数据目录")?
-            .join("bun-codeview")
-            .join("knowledge_bases");
+        Self::open(&default_storage_dir()?)
+    }
 
-        fs::create_dir_all(&storage_dir).map_err(|e| e.to_string())?;
+    /// 以只读方式打开默认目录下的知识库，不迁移、不隔离数据库也不补充关键词，
+    /// 供 MCP 标准输入输出模式在应用运行时并行查询
+    pub fn new_read_only() -> Result<Self, String> {
+        Self::open_read_only(&default_storage_dir()?)
+    }
 
+    /// 以只读方式打开指定目录下的知识库数据库
+    pub fn open_read_only(storage_dir: &Path) -> Result<Self, String> {
+        let conn = kb_schema::open_read_only(&storage_dir.join(DB_FILE))?;
         Ok(Self {
-            storage_path: storage_dir.join("kb_data.json"),
+            storage_dir: storage_dir.to_path_buf(),
+            conn: Mutex::new(conn),
+            cache: RwLock::new(Cache::default()),
+            quarantined: None,
+        })
+    }
+
+    /// 打开指定目录下的知识库数据库
+    pub fn open(storage_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(storage_dir).map_err(|e| e.to_string())?;
+
+        let (mut conn, quarantined) =
+            kb_schema::open_database(&storage_dir.join(DB_FILE), &storage_dir.join(BACKUP_DIR))?;
+        migrate_legacy_json(&mut conn, &storage_dir.join(LEGACY_FILE))?;
+
+        let service = Self {
+            storage_dir: storage_dir.to_path_buf(),
+            conn: Mutex::new(conn),
+            cache: RwLock::new(Cache::default()),
+            quarantined,
+        };
+        // 关键词只影响标签筛选，补算失败不影响打开知识库
+        if let Err(e) = service.backfill_keywords() {
+            log::warn!("补充提取关键词失败: {}", e);
+        }
+        Ok(service)
+    }
+
+    /// 为尚未分词（旧版数据或上次分词中断）的文档补充提取关键词
+    fn backfill_keywords(&self) -> Result<(), String> {
+        let pending = kbs_with_pending_terms(&*self.conn()?)?;
+        for kb_id in pending {
+            self.refresh_keywords(&kb_id)?;
+        }
+        Ok(())
+    }
+
+    /// 数据库版本、本次启动时隔离的文件和可恢复的备份
+    pub fn storage_status(&self) -> Result<StorageStatus, String> {
+        Ok(StorageStatus {
+            schema_version: kb_schema::SCHEMA_VERSION,
+            quarantined: self
+                .quarantined
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().to_string()),
+            backups: kb_schema::list_backups(&self.storage_dir.join(BACKUP_DIR))?,
         })
     }
 
-    /// 加载存储数据
-    fn load_data(&self) -> Result<StorageData, String> {
-        if !self.storage_path.exists() {
-            return Ok(StorageData::default());
+    /// 用备份（或隔离的数据库）替换当前数据库，替换前先备份当前数据
+    pub fn restore_backup(&self, name: &str) -> Result<StorageStatus, String> {
+        let backup_dir = self.storage_dir.join(BACKUP_DIR);
+        let backup = kb_schema::list_backups(&backup_dir)?
+            .into_iter()
+            .find(|b| b.name == name)
+            .ok_or("备份不存在")?;
+        let source = backup_dir.join(&backup.name);
+        kb_schema::validate_backup(&source)?;
+
+        let db_path = self.storage_dir.join(DB_FILE);
+        {
+            let mut conn = self.conn()?;
+            // 恢复成功后再清理旧备份，以免要恢复的备份先被删除
+            kb_schema::snapshot_database(&conn, &backup_dir, "before-restore")?;
+            // 先关闭当前数据库再替换文件
+            *conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+            let restored = kb_schema::replace_database(&db_path, &source)
+                .and_then(|_| kb_schema::open_and_migrate(&db_path, &backup_dir));
+            match restored {
+                Ok(restored) => *conn = restored,
+                Err(e) => {
+                    // 恢复失败时仍保证服务可用
+                    *conn = kb_schema::open_database(&db_path, &backup_dir)?.0;
+                    return Err(format!("恢复备份失败: {}", e));
+                }
+            }
+            *self.cache()? = Cache::default();
         }
-        let content = fs::read_to_string(&self.storage_path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&content).map_err(|e| e.to_string())
+        log::info!("已从备份 {} 恢复知识库数据库", name);
+        kb_schema::prune_backups(&backup_dir)?;
+
+        if let Err(e) = self.backfill_keywords() {
+            log::warn!("补充提取关键词失败: {}", e);
+        }
+        self.storage_status()
+    }
+
+    /// 生成内容的默认保存目录
+    pub fn generated_dir(&self) -> PathBuf {
+        self.storage_dir.join("generated")
     }
 
-    /// 保存存储数据
-    fn save_data(&self, data: &StorageData) -> Result<(), String> {
-        let content = serde_json::to_string_pretty(data).map_err(|e| e.to_string())?;
-        fs::write(&self.storage_path, content).map_err(|e| e.to_string())
+    /// 分享包中源文件的解压目录
+    pub fn shared_sources_dir(&self, kb_id: &str) -> PathBuf {
+        self.storage_dir.join("shared").join(kb_id)
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        self.conn
+            .lock()
+            .map_err(|_| "知识库数据库锁已损坏".to_string())
+    }
+
+    fn cache(&self) -> Result<RwLockWriteGuard<'_, Cache>, String> {
+        self.cache
+            .write()
+            .map_err(|_| "知识库缓存锁已损坏".to_string())
     }
 
     /// 创建知识库
@@ -56,7 +221,6 @@ impl KnowledgeBaseService {
         name: String,
         description: Option<String>,
     ) -> Result<KnowledgeBase, String> {
-        let mut data = self.load_data()?;
         let now = Utc::now();
 
         let kb = KnowledgeBase {
@@ -66,47 +230,395 @@ impl KnowledgeBaseService {
             created_at: now,
             updated_at: now,
             document_count: 0,
+            chunking: ChunkingConfig::default(),
+            icon: None,
+            color: None,
+            tags: Vec::new(),
         };
 
-        data.knowledge_bases.push(kb.clone());
-        self.save_data(&data)?;
+        let conn = self.conn()?;
+        insert_knowledge_base(&conn, &kb)?;
+        self.cache()?.knowledge_bases = None;
         Ok(kb)
     }
 
+    /// 修改知识库的名称、描述、图标、颜色和标签
+    pub fn update_knowledge_base(
+        &self,
+        id: &str,
+        input: KnowledgeBaseInput,
+    ) -> Result<KnowledgeBase, String> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err("知识库名称不能为空".to_string());
+        }
+        let color = non_empty(input.color);
+        if let Some(color) = &color {
+            validate_color(color)?;
+        }
+        let tags =
+            serde_json::to_string(&normalize_tags(&input.tags)).map_err(|e| e.to_string())?;
+
+        let conn = self.conn()?;
+        let updated = conn
+            .execute(
+                "UPDATE knowledge_bases
+                 SET name = ?2, description = ?3, icon = ?4, color = ?5, tags = ?6, updated_at = ?7
+                 WHERE id = ?1",
+                params![
+                    id,
+                    name,
+                    non_empty(input.description),
+                    non_empty(input.icon),
+                    color,
+                    tags,
+                    Utc::now()
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("知识库不存在".to_string());
+        }
+        self.cache()?.knowledge_bases = None;
+        drop(conn);
+
+        self.get_knowledge_base(id)?
+            .ok_or_else(|| "知识库不存在".to_string())
+    }
+
     /// 列出所有知识库
     pub fn list_knowledge_bases(&self) -> Result<Vec<KnowledgeBase>, String> {
-        let data = self.load_data()?;
-        Ok(data.knowledge_bases)
+        if let Some(kbs) = self
+            .cache
+            .read()
+            .ok()
+            .and_then(|cache| cache.knowledge_bases.clone())
+        {
+            return Ok(kbs);
+        }
+
+        // 持有连接锁填充缓存，避免与并发写入交错导致缓存过期
+        let conn = self.conn()?;
+        let kbs = query_knowledge_bases(&conn)?;
+        self.cache()?.knowledge_bases = Some(kbs.clone());
+        Ok(kbs)
     }
 
     /// 获取知识库详情
     pub fn get_knowledge_base(&self, id: &str) -> Result<Option<KnowledgeBase>, String> {
-        let data = self.load_data()?;
-        Ok(data.knowledge_bases.into_iter().find(|kb| kb.id == id))
+        Ok(self
+            .list_knowledge_bases()?
+            .into_iter()
+            .find(|kb| kb.id == id))
+    }
+
+    /// 修改知识库的分块配置，已导入的文档需调用 [`Self::rechunk_knowledge_base`] 重新分块
+    pub fn set_chunking_config(
+        &self,
+        kb_id: &str,
+        config: ChunkingConfig,
+    ) -> Result<KnowledgeBase, String> {
+        chunking::validate_config(&config)?;
+        let chunking = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+
+        let conn = self.conn()?;
+        let updated = conn
+            .execute(
+                "UPDATE knowledge_bases SET chunking = ?1, updated_at = ?2 WHERE id = ?3",
+                params![chunking, Utc::now(), kb_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("知识库不存在".to_string());
+        }
+        self.cache()?.knowledge_bases = None;
+        drop(conn);
+
+        self.get_knowledge_base(kb_id)?
+            .ok_or_else(|| "知识库不存在".to_string())
     }
 
     /// 删除知识库
     pub fn delete_knowledge_base(&self, id: &str) -> Result<(), String> {
-        let mut data = self.load_data()?;
-        data.knowledge_bases.retain(|kb| kb.id != id);
-        data.documents.retain(|doc| doc.kb_id != id);
-        self.save_data(&data)
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM knowledge_bases WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+
+        let mut cache = self.cache()?;
+        cache.knowledge_bases = None;
+        cache.documents.remove(id);
+
+        // 从分享包导入的源文件随知识库一起删除
+        let shared = self.shared_sources_dir(id);
+        if shared.exists() {
+            if let Err(e) = fs::remove_dir_all(&shared) {
+                log::warn!("删除源文件目录失败 {}: {}", shared.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入从分享包还原的知识库、文档和向量；名称与已有知识库重复时自动加序号
+    pub fn restore_knowledge_base(
+        &self,
+        mut kb: KnowledgeBase,
+        documents: &[Document],
+        embeddings: &[StoredEmbedding],
+    ) -> Result<KnowledgeBase, String> {
+        chunking::validate_config(&kb.chunking)?;
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let names: HashSet<String> = {
+            let mut stmt = tx
+                .prepare("SELECT name FROM knowledge_bases")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        kb.name = unique_name(&kb.name, &names);
+
+        insert_knowledge_base(&tx, &kb)?;
+        for doc in documents {
+            insert_document(&tx, doc)?;
+        }
+        for embedding in embeddings {
+            tx.execute(
+                "INSERT OR REPLACE INTO chunk_embeddings (chunk_id, provider, vector)
+                 VALUES (?1, ?2, ?3)",
+                params![
+                    embedding.chunk_id,
+                    embedding.provider,
+                    embedding_service::vector_to_bytes(&embedding.vector)
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+
+        self.cache()?.knowledge_bases = None;
+        self.refresh_keywords(&kb.id)?;
+        self.get_knowledge_base(&kb.id)?
+            .ok_or_else(|| "知识库不存在".to_string())
     }
 
-    /// 导入文档到知识库
+    /// 导入文档到知识库；同一路径已导入时更新原文档
     pub fn import_document(&self, kb_id: &str, path: &str) -> Result<Document, String> {
-        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let kb = self.get_knowledge_base(kb_id)?.ok_or("知识库不存在")?;
+
+        let doc = self.build_document(&kb, path)?;
+        let path = doc.path.clone();
+        self.store_documents(kb_id, vec![doc])?;
+        self.refresh_keywords(kb_id)?;
+        self.imported_documents(kb_id, &[path])?
+            .pop()
+            .ok_or_else(|| "导入文档失败".to_string())
+    }
+
+    /// 批量导入文档，所有成功解析的文档在同一事务中写入
+    pub fn import_documents(&self, kb_id: &str, paths: &[String]) -> Result<Vec<Document>, String> {
+        let kb = self.get_knowledge_base(kb_id)?.ok_or("知识库不存在")?;
+
+        // 在获取连接锁之前完成文件读取和解析
+        let documents: Vec<Document> = paths
+            .iter()
+            .filter_map(|path| match self.build_document(&kb, path) {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    log::warn!("导入文档失败 {}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+
+        let paths: Vec<String> = documents.iter().map(|doc| doc.path.clone()).collect();
+        self.store_documents(kb_id, documents)?;
+        self.refresh_keywords(kb_id)?;
+        self.imported_documents(kb_id, &paths)
+    }
+
+    /// 按路径顺序取出刚写入的文档（重复路径只返回一次）
+    fn imported_documents(&self, kb_id: &str, paths: &[String]) -> Result<Vec<Document>, String> {
+        let mut documents: HashMap<String, Document> = self
+            .get_documents(kb_id)?
+            .into_iter()
+            .map(|doc| (doc.path.clone(), doc))
+            .collect();
+        Ok(paths
+            .iter()
+            .filter_map(|path| documents.remove(path))
+            .collect())
+    }
+
+    /// 在同一事务中写入文档并刷新缓存；已存在同路径文档时按内容哈希决定是否更新
+    fn store_documents(
+        &self,
+        kb_id: &str,
+        documents: Vec<Document>,
+    ) -> Result<Vec<(String, StoreOutcome)>, String> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut outcomes = Vec::with_capacity(documents.len());
+        for mut doc in documents {
+            let existing: Option<(String, String)> = tx
+                .query_row(
+                    "SELECT id, content_hash FROM documents WHERE kb_id = ?1 AND path = ?2",
+                    params![kb_id, doc.path],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+
+            let outcome = match existing {
+                None => {
+                    insert_document(&tx, &doc)?;
+                    (doc.id, StoreOutcome::Inserted)
+                }
+                Some((id, hash)) if hash == doc.content_hash => {
+                    touch_document(&tx, &id, doc.modified_at)?;
+                    (id, StoreOutcome::Unchanged)
+                }
+                Some((id, _)) => {
+                    doc.id = id;
+                    update_document(&tx, &doc)?;
+                    (doc.id, StoreOutcome::Updated)
+                }
+            };
+            outcomes.push(outcome);
+        }
+        // 更新知识库修改时间
+        touch_knowledge_base(&tx, kb_id)?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        let mut cache = self.cache()?;
+        cache.knowledge_bases = None;
+        cache.documents.remove(kb_id);
+        Ok(outcomes)
+    }
+
+    /// 导入文件夹：按过滤规则收集文件，并行解析，分批写入，每处理一个文件回调一次进度
+    pub fn import_folder(
+        &self,
+        kb_id: &str,
+        folder: &Path,
+        options: &FolderImportOptions,
+        on_progress: &(dyn Fn(&ImportProgress) + Sync),
+    ) -> Result<ImportReport, String> {
+        let kb = self.get_knowledge_base(kb_id)?.ok_or("知识库不存在")?;
+
+        let (files, unreadable) = collect_folder_files(folder, options)?;
+        let total = files.len();
+        let processed = AtomicUsize::new(0);
+        let mut report = ImportReport {
+            kb_id: kb_id.to_string(),
+            files: Vec::with_capacity(total + unreadable.len()),
+        };
+        report.files.extend(unreadable);
+
+        for batch in files.chunks(IMPORT_BATCH_SIZE) {
+            let parsed: Vec<(String, Result<Document, String>)> = batch
+                .par_iter()
+                .map(|path| {
+                    let path = path.to_string_lossy().to_string();
+                    let result = self.build_document(&kb, &path);
+                    on_progress(&ImportProgress {
+                        kb_id: kb_id.to_string(),
+                        processed: processed.fetch_add(1, Ordering::Relaxed) + 1,
+                        total,
+                        path: path.clone(),
+                    });
+                    (path, result)
+                })
+                .collect();
+
+            let mut documents = Vec::new();
+            let mut stored_paths = Vec::new();
+            for (path, result) in parsed {
+                match result {
+                    Ok(doc) => {
+                        stored_paths.push(path);
+                        documents.push(doc);
+                    }
+                    Err(error) => report.files.push(ImportFileResult {
+                        path,
+                        status: ImportStatus::Failed,
+                        document_id: None,
+                        error: Some(error),
+                    }),
+                }
+            }
+
+            // 每批单独提交，写入失败时保留之前批次的结果并继续处理后续文件
+            let outcomes = match self.store_documents(kb_id, documents) {
+                Ok(outcomes) => outcomes,
+                Err(error) => {
+                    log::warn!("导入文件夹时写入失败: {}", error);
+                    report
+                        .files
+                        .extend(stored_paths.into_iter().map(|path| ImportFileResult {
+                            path,
+                            status: ImportStatus::Failed,
+                            document_id: None,
+                            error: Some(error.clone()),
+                        }));
+                    continue;
+                }
+            };
+            for (path, (id, outcome)) in stored_paths.into_iter().zip(outcomes) {
+                report.files.push(ImportFileResult {
+                    path,
+                    status: match outcome {
+                        StoreOutcome::Inserted => ImportStatus::Imported,
+                        StoreOutcome::Updated => ImportStatus::Updated,
+                        StoreOutcome::Unchanged => ImportStatus::Duplicate,
+                    },
+                    document_id: Some(id),
+                    error: None,
+                });
+            }
+        }
+
+        if let Err(e) = self.refresh_keywords(kb_id) {
+            log::warn!("更新关键词失败: {}", e);
+        }
+        report.files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+
+    /// 读取并解析单个文档，文档路径保存为规范化后的绝对路径
+    fn build_document(&self, kb: &KnowledgeBase, path: &str) -> Result<Document, String> {
+        let path = &canonical_path(path)?;
+        let extracted = extractors::extract(Path::new(path))?;
+        let content = extracted.content;
+        let modified_at = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
         let file_name = std::path::Path::new(path)
             .file_stem()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| "Untitled".to_string());
 
-        let (chunks, headings) = self.parse_markdown(&content);
+        // 纯文本没有标题结构，总是按段落分块
+        let strategy: Box<dyn ChunkingStrategy> = match extracted.format {
+            TextFormat::Markdown => chunking::strategy_for(&kb.chunking),
+            TextFormat::Plain => Box::new(ParagraphChunker::new(&kb.chunking)),
+        };
+        let (chunks, headings) = strategy.chunk(&content, &extracted.page_starts);
         let metadata = DocumentMetadata {
             word_count: content.chars().filter(|c| !c.is_whitespace()).count(),
             line_count: content.lines().count(),
             headings,
             keywords: None,
+            page_count: (!extracted.page_starts.is_empty()).then_some(extracted.page_starts.len()),
+            tags: keyword_service::front_matter_tags(&content),
         };
 
         let title = metadata
@@ -115,174 +627,2384 @@ impl KnowledgeBaseService {
             .map(|h| h.text.clone())
             .unwrap_or(file_name);
 
-        let doc = Document {
+        Ok(Document {
             id: Uuid::new_v4().to_string(),
-            kb_id: kb_id.to_string(),
+            kb_id: kb.id.clone(),
             path: path.to_string(),
             title,
+            content_hash: content_hash(&content),
             content,
             chunks,
             metadata,
             created_at: Utc::now(),
+            modified_at,
+            source_missing: false,
+        })
+    }
+
+    /// 将知识库与源文件同步：修改时间变化且内容哈希不同的文档重新分块，源文件缺失的文档打上标记
+    pub fn sync_knowledge_base(&self, kb_id: &str) -> Result<SyncReport, String> {
+        self.sync_documents(kb_id, false)
+    }
+
+    /// 按当前分块配置从源文件重新分块所有文档，源文件缺失的文档保留原有文本块
+    pub fn rechunk_knowledge_base(&self, kb_id: &str) -> Result<SyncReport, String> {
+        self.sync_documents(kb_id, true)
+    }
+
+    /// `rechunk` 为 true 时不跳过未修改的文档，内容相同也重新分块
+    fn sync_documents(&self, kb_id: &str, rechunk: bool) -> Result<SyncReport, String> {
+        let kb = self.get_knowledge_base(kb_id)?.ok_or("知识库不存在")?;
+
+        let mut report = SyncReport {
+            kb_id: kb_id.to_string(),
+            ..SyncReport::default()
         };
+        let mut changes = Vec::new();
+
+        // 文件读取和解析不持有连接锁
+        for doc in self.get_documents(kb_id)? {
+            let modified_at = match fs::metadata(&doc.path) {
+                Ok(meta) => meta.modified().ok().map(DateTime::<Utc>::from),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    if !doc.source_missing {
+                        report.missing.push(doc.path.clone());
+                        changes.push(SyncChange::Missing(doc.id));
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    report.failed.push(FailedFile {
+                        path: doc.path,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            if !rechunk
+                && !doc.source_missing
+                && !doc.content_hash.is_empty()
+                && modified_at.is_some()
+                && modified_at == doc.modified_at
+            {
+                report.unchanged += 1;
+                continue;
+            }
+
+            let mut rebuilt = match self.build_document(&kb, &doc.path) {
+                Ok(rebuilt) => rebuilt,
+                Err(error) => {
+                    report.failed.push(FailedFile {
+                        path: doc.path,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            let same = rebuilt.content_hash == doc.content_hash;
+            if same && doc.source_missing {
+                report.restored.push(doc.path.clone());
+            } else if same && !rechunk {
+                report.unchanged += 1;
+            } else {
+                report.updated.push(doc.path.clone());
+            }
+            if same && !rechunk {
+                changes.push(SyncChange::Touch(doc.id, rebuilt.modified_at));
+            } else {
+                rebuilt.id = doc.id;
+                rebuilt.created_at = doc.created_at;
+                changes.push(SyncChange::Content(Box::new(rebuilt)));
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(report);
+        }
 
-        let mut data = self.load_data()?;
-        data.documents.push(doc.clone());
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for change in &changes {
+            match change {
+                SyncChange::Content(doc) => update_document(&tx, doc)?,
+                SyncChange::Touch(id, modified_at) => touch_document(&tx, id, *modified_at)?,
+                SyncChange::Missing(id) => {
+                    tx.execute(
+                        "UPDATE documents SET source_missing = 1 WHERE id = ?1",
+                        params![id],
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        if !report.updated.is_empty() {
+            touch_knowledge_base(&tx, kb_id)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
 
-        // 更新知识库文档计数
-        if let Some(kb) = data.knowledge_bases.iter_mut().find(|kb| kb.id == kb_id) {
-            kb.document_count = data.documents.iter().filter(|d| d.kb_id == kb_id).count();
-            kb.updated_at = Utc::now();
+        {
+            let mut cache = self.cache()?;
+            cache.knowledge_bases = None;
+            cache.documents.remove(kb_id);
+        }
+        if !report.updated.is_empty() {
+            self.refresh_keywords(kb_id)?;
         }
+        Ok(report)
+    }
 
-        self.save_data(&data)?;
-        Ok(doc)
+    /// 同步所有知识库，单个知识库失败时记录日志并继续
+    pub fn sync_all(&self) -> Result<Vec<SyncReport>, String> {
+        let mut reports = Vec::new();
+        for kb in self.list_knowledge_bases()? {
+            match self.sync_knowledge_base(&kb.id) {
+                Ok(report) => reports.push(report),
+                Err(e) => log::warn!("同步知识库失败 {}: {}", kb.name, e),
+            }
+        }
+        Ok(reports)
     }
 
     /// 获取知识库的所有文档
     pub fn get_documents(&self, kb_id: &str) -> Result<Vec<Document>, String> {
-        let data = self.load_data()?;
-        Ok(data
+        if let Some(documents) = self
+            .cache
+            .read()
+            .ok()
+            .and_then(|cache| cache.documents.get(kb_id).cloned())
+        {
+            return Ok(documents);
+        }
+
+        let conn = self.conn()?;
+        let documents = query_documents(&conn, kb_id)?;
+        self.cache()?
             .documents
-            .into_iter()
-            .filter(|doc| doc.kb_id == kb_id)
-            .collect())
+            .insert(kb_id.to_string(), documents.clone());
+        Ok(documents)
+    }
+
+    /// 分页列出文档摘要，不读取正文和文本块
+    pub fn list_document_summaries(
+        &self,
+        kb_id: &str,
+        query: &DocumentListQuery,
+    ) -> Result<DocumentPage, String> {
+        let conn = self.conn()?;
+        query_document_summaries(&conn, kb_id, query)
+    }
+
+    /// 获取文档的正文和文本块
+    pub fn get_document(&self, doc_id: &str) -> Result<Option<Document>, String> {
+        let conn = self.conn()?;
+        let Some(mut doc) = conn
+            .query_row(
+                &format!("{} WHERE id = ?1", DOCUMENT_SELECT),
+                params![doc_id],
+                document_from_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+        else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, content, start_line, end_line, headings, page FROM chunks
+                 WHERE document_id = ?1 ORDER BY position",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![doc_id], |row| chunk_from_row(row, 0))
+            .map_err(|e| e.to_string())?;
+        doc.chunks = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(Some(doc))
     }
 
     /// 删除文档
     pub fn delete_document(&self, doc_id: &str) -> Result<(), String> {
-        let mut data = self.load_data()?;
-        let kb_id = data
-            .documents
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let kb_id: Option<String> = tx
+            .query_row(
+                "SELECT kb_id FROM documents WHERE id = ?1",
+                params![doc_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        tx.execute("DELETE FROM documents WHERE id = ?1", params![doc_id])
+            .map_err(|e| e.to_string())?;
+
+        // 更新知识库修改时间；文档频率变化，其余文档的关键词随之重新排序
+        if let Some(kb_id) = &kb_id {
+            touch_knowledge_base(&tx, kb_id)?;
+            rerank_keywords(&tx, kb_id)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+
+        let mut cache = self.cache()?;
+        cache.knowledge_bases = None;
+        if let Some(kb_id) = &kb_id {
+            cache.documents.remove(kb_id);
+        }
+        Ok(())
+    }
+
+    /// 将文档移动到另一个知识库，文本块及其向量随文档移动
+    pub fn move_documents(
+        &self,
+        doc_ids: &[String],
+        target_kb_id: &str,
+    ) -> Result<Vec<Document>, String> {
+        self.transfer_documents(doc_ids, target_kb_id, false)
+    }
+
+    /// 将文档复制到另一个知识库，副本的文本块及向量使用新的 ID
+    pub fn copy_documents(
+        &self,
+        doc_ids: &[String],
+        target_kb_id: &str,
+    ) -> Result<Vec<Document>, String> {
+        self.transfer_documents(doc_ids, target_kb_id, true)
+    }
+
+    /// 在同一事务中移动或复制文档；目标知识库已有同路径文档时整体失败
+    fn transfer_documents(
+        &self,
+        doc_ids: &[String],
+        target_kb_id: &str,
+        copy: bool,
+    ) -> Result<Vec<Document>, String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let target_exists: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM knowledge_bases WHERE id = ?1)",
+                params![target_kb_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !target_exists {
+            return Err("目标知识库不存在".to_string());
+        }
+
+        let mut affected: HashSet<String> = HashSet::from([target_kb_id.to_string()]);
+        let mut transferred: Vec<String> = Vec::new();
+        for doc_id in doc_ids {
+            if transferred.contains(doc_id) {
+                continue;
+            }
+            let (kb_id, path): (String, String) = tx
+                .query_row(
+                    "SELECT kb_id, path FROM documents WHERE id = ?1",
+                    params![doc_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?
+                .ok_or("文档不存在")?;
+            // 已在目标知识库中的文档无需移动
+            if kb_id == target_kb_id && !copy {
+                transferred.push(doc_id.clone());
+                continue;
+            }
+            let conflict: bool = tx
+                .query_row(
+                    "SELECT EXISTS (SELECT 1 FROM documents WHERE kb_id = ?1 AND path = ?2)",
+                    params![target_kb_id, path],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if conflict {
+                return Err(format!("目标知识库已包含文档: {}", path));
+            }
+
+            if copy {
+                transferred.push(copy_document(&tx, doc_id, target_kb_id)?);
+            } else {
+                tx.execute(
+                    "UPDATE documents SET kb_id = ?2 WHERE id = ?1",
+                    params![doc_id, target_kb_id],
+                )
+                .map_err(|e| e.to_string())?;
+                transferred.push(doc_id.clone());
+                affected.insert(kb_id);
+            }
+        }
+        // 文档集合变化后两边的关键词都按各自的文档频率重新排序
+        for kb_id in &affected {
+            touch_knowledge_base(&tx, kb_id)?;
+            rerank_keywords(&tx, kb_id)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+
+        {
+            let mut cache = self.cache()?;
+            cache.knowledge_bases = None;
+            for kb_id in &affected {
+                cache.documents.remove(kb_id);
+            }
+        }
+        let mut documents: HashMap<String, Document> = self
+            .get_documents(target_kb_id)?
+            .into_iter()
+            .map(|doc| (doc.id.clone(), doc))
+            .collect();
+        Ok(transferred
             .iter()
-            .find(|d| d.id == doc_id)
-            .map(|d| d.kb_id.clone());
+            .filter_map(|id| documents.remove(id))
+            .collect())
+    }
+
+    /// 为新增或内容变化的文档分词，有文档重新分词时按知识库内的 TF-IDF 重新排序全部文档的关键词
+    fn refresh_keywords(&self, kb_id: &str) -> Result<(), String> {
+        let pending: Vec<(String, String, String)> = {
+            let conn = self.conn()?;
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, content, content_hash FROM documents
+                     WHERE kb_id = ?1 AND (terms_hash IS NULL OR terms_hash != content_hash)",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![kb_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
 
-        data.documents.retain(|doc| doc.id != doc_id);
+        // 分词较慢，期间不持有连接锁
+        let counted: Vec<(String, String, HashMap<String, usize>)> = pending
+            .into_par_iter()
+            .map(|(id, content, hash)| (id, hash, keyword_service::term_counts(&content)))
+            .collect();
 
-        // 更新知识库文档计数
-        if let Some(kb_id) = kb_id {
-            if let Some(kb) = data.knowledge_bases.iter_mut().find(|kb| kb.id == kb_id) {
-                kb.document_count = data.documents.iter().filter(|d| d.kb_id == kb_id).count();
-                kb.updated_at = Utc::now();
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut refreshed = false;
+        for (id, hash, terms) in &counted {
+            // 文档可能已在分词期间被修改或删除；没有词语的文档同样记录哈希，不再重复分词
+            let current = tx
+                .execute(
+                    "UPDATE documents SET terms_hash = ?2 WHERE id = ?1 AND content_hash = ?2",
+                    params![id, hash],
+                )
+                .map_err(|e| e.to_string())?;
+            if current == 0 {
+                continue;
             }
+            tx.execute(
+                "DELETE FROM document_terms WHERE document_id = ?1",
+                params![id],
+            )
+            .map_err(|e| e.to_string())?;
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT INTO document_terms (document_id, term, count) VALUES (?1, ?2, ?3)",
+                )
+                .map_err(|e| e.to_string())?;
+            for (term, count) in terms {
+                stmt.execute(params![id, term, *count as i64])
+                    .map_err(|e| e.to_string())?;
+            }
+            refreshed = true;
         }
 
-        self.save_data(&data)
-    }
-
-    /// 解析 Markdown 内容，提取标题和分块
-    fn parse_markdown(&self, content: &str) -> (Vec<Chunk>, Vec<HeadingInfo>) {
-        let mut chunks = Vec::new();
-        let mut headings = Vec::new();
-        let mut current_headings: Vec<String> = Vec::new();
-        let mut chunk_start = 0;
-        let mut chunk_content = String::new();
-        let lines: Vec<&str> = content.lines().collect();
-
-        for (idx, line) in lines.iter().enumerate() {
-            // 检测标题
-            if let Some(heading) = self.parse_heading(line) {
-                // 保存之前的块
-                if !chunk_content.trim().is_empty() {
-                    chunks.push(Chunk {
-                        id: Uuid::new_v4().to_string(),
-                        content: chunk_content.trim().to_string(),
-                        start_line: chunk_start,
-                        end_line: idx.saturating_sub(1),
-                        headings: current_headings.clone(),
-                    });
-                }
+        // 词语变化后 IDF 随之变化，知识库内所有文档的关键词都重新排序
+        let changed = refreshed && rerank_keywords(&tx, kb_id)?;
+        tx.commit().map_err(|e| e.to_string())?;
 
-                headings.push(HeadingInfo {
-                    level: heading.0,
-                    text: heading.1.clone(),
-                    line: idx,
-                });
+        if changed {
+            self.cache()?.documents.remove(kb_id);
+        }
+        Ok(())
+    }
 
-                // 更新当前标题层级
-                let level = heading.0 as usize;
-                if level <= current_headings.len() {
-                    current_headings.truncate(level - 1);
-                }
-                current_headings.push(heading.1);
+    /// 统计知识库中的标签（含自动提取的关键词）及其文档数量，按数量降序
+    pub fn list_tags(&self, kb_id: &str) -> Result<Vec<TagCount>, String> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for doc in self.get_documents(kb_id)? {
+            for tag in doc.metadata.all_tags() {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        Ok(tags)
+    }
+
+    /// 获取带有指定标签或关键词的文档（不区分大小写）
+    pub fn get_documents_by_tag(&self, kb_id: &str, tag: &str) -> Result<Vec<Document>, String> {
+        let tag = tag.trim().to_lowercase();
+        Ok(self
+            .get_documents(kb_id)?
+            .into_iter()
+            .filter(|doc| doc.metadata.all_tags().contains(&tag))
+            .collect())
+    }
 
-                chunk_start = idx;
-                chunk_content = String::new();
+    /// 为知识库中尚无向量的文本块计算并保存嵌入，返回新增数量
+    pub fn embed_knowledge_base(
+        &self,
+        kb_id: &str,
+        provider: &dyn EmbeddingProvider,
+    ) -> Result<usize, String> {
+        let provider_id = provider.id();
+        let pending: Vec<(String, String)> = {
+            let conn = self.conn()?;
+            let mut stmt = conn
+                .prepare(
+                    "SELECT c.id, c.content FROM chunks c
+                     JOIN documents d ON d.id = c.document_id
+                     LEFT JOIN chunk_embeddings e ON e.chunk_id = c.id AND e.provider = ?2
+                     WHERE d.kb_id = ?1 AND e.chunk_id IS NULL",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![kb_id, provider_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+
+        // 嵌入计算可能较慢，期间不持有连接锁
+        for batch in pending.chunks(embedding_service::BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|(_, content)| content.clone()).collect();
+            let vectors = provider.embed(&texts)?;
+
+            let mut conn = self.conn()?;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            for ((chunk_id, _), vector) in batch.iter().zip(&vectors) {
+                // 文本块可能已在计算期间被删除
+                tx.execute(
+                    "INSERT OR REPLACE INTO chunk_embeddings (chunk_id, provider, vector)
+                     SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM chunks WHERE id = ?1)",
+                    params![
+                        chunk_id,
+                        provider_id,
+                        embedding_service::vector_to_bytes(vector)
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
             }
+            tx.commit().map_err(|e| e.to_string())?;
+        }
 
-            chunk_content.push_str(line);
-            chunk_content.push('\n');
-
-            // 每 500 行或遇到分隔符时分块
-            if chunk_content.len() > 2000 || line.starts_with("---") {
-                if !chunk_content.trim().is_empty() {
-                    chunks.push(Chunk {
-                        id: Uuid::new_v4().to_string(),
-                        content: chunk_content.trim().to_string(),
-                        start_line: chunk_start,
-                        end_line: idx,
-                        headings: current_headings.clone(),
-                    });
-                }
-                chunk_start = idx + 1;
-                chunk_content = String::new();
+        Ok(pending.len())
+    }
+
+    /// 获取知识库在指定提供方下的文本块向量
+    pub fn get_embeddings(
+        &self,
+        kb_id: &str,
+        provider_id: &str,
+    ) -> Result<HashMap<String, Vec<f32>>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.chunk_id, e.vector FROM chunk_embeddings e
+                 JOIN chunks c ON c.id = e.chunk_id
+                 JOIN documents d ON d.id = c.document_id
+                 WHERE d.kb_id = ?1 AND e.provider = ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id, provider_id], |row| {
+                let bytes: Vec<u8> = row.get(1)?;
+                Ok((row.get(0)?, embedding_service::vector_from_bytes(&bytes)))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// 获取知识库所有提供方下的文本块向量
+    pub fn list_embeddings(&self, kb_id: &str) -> Result<Vec<StoredEmbedding>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.chunk_id, e.provider, e.vector FROM chunk_embeddings e
+                 JOIN chunks c ON c.id = e.chunk_id
+                 JOIN documents d ON d.id = c.document_id
+                 WHERE d.kb_id = ?1 ORDER BY e.provider, d.created_at, c.position",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], |row| {
+                let bytes: Vec<u8> = row.get(2)?;
+                Ok(StoredEmbedding {
+                    chunk_id: row.get(0)?,
+                    provider: row.get(1)?,
+                    vector: embedding_service::vector_from_bytes(&bytes),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// 列出知识库的对话，最近更新的在前
+    pub fn list_conversations(&self, kb_id: &str) -> Result<Vec<Conversation>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, kb_id, title, created_at, updated_at FROM conversations
+                 WHERE kb_id = ?1 ORDER BY updated_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], conversation_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// 获取对话
+    pub fn get_conversation(&self, id: &str) -> Result<Option<Conversation>, String> {
+        let conn = self.conn()?;
+        conn.query_row(
+            "SELECT id, kb_id, title, created_at, updated_at FROM conversations WHERE id = ?1",
+            params![id],
+            conversation_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    }
+
+    /// 按顺序获取对话消息
+    pub fn get_conversation_messages(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<ConversationMessage>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, role, content, citations, created_at FROM messages
+                 WHERE conversation_id = ?1 ORDER BY position",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![conversation_id], message_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// 追加一轮问答；`conversation_id` 为空时以 `title` 新建对话
+    pub fn append_messages(
+        &self,
+        kb_id: &str,
+        conversation_id: Option<&str>,
+        title: &str,
+        messages: &[ConversationMessage],
+    ) -> Result<Conversation, String> {
+        let now = Utc::now();
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let conversation = match conversation_id {
+            Some(id) => {
+                tx.execute(
+                    "UPDATE conversations SET updated_at = ?1 WHERE id = ?2 AND kb_id = ?3",
+                    params![now, id, kb_id],
+                )
+                .map_err(|e| e.to_string())?;
+                tx.query_row(
+                    "SELECT id, kb_id, title, created_at, updated_at FROM conversations
+                     WHERE id = ?1 AND kb_id = ?2",
+                    params![id, kb_id],
+                    conversation_from_row,
+                )
+                .optional()
+                .map_err(|e| e.to_string())?
+                .ok_or("对话不存在")?
+            }
+            None => {
+                let conversation = Conversation {
+                    id: Uuid::new_v4().to_string(),
+                    kb_id: kb_id.to_string(),
+                    title: title.to_string(),
+                    created_at: now,
+                    updated_at: now,
+                };
+                tx.execute(
+                    "INSERT INTO conversations (id, kb_id, title, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        conversation.id,
+                        conversation.kb_id,
+                        conversation.title,
+                        conversation.created_at,
+                        conversation.updated_at
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+                conversation
+            }
+        };
+
+        let start: i64 = tx
+            .query_row(
+                "SELECT COUNT(*) FROM messages WHERE conversation_id = ?1",
+                params![conversation.id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        for (offset, message) in messages.iter().enumerate() {
+            let citations = serde_json::to_string(&message.citations).map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO messages (id, conversation_id, position, role, content, citations, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    message.id,
+                    conversation.id,
+                    start + offset as i64,
+                    message.role,
+                    message.content,
+                    citations,
+                    message.created_at
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(conversation)
+    }
+
+    /// 删除对话及其消息
+    pub fn delete_conversation(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM conversations WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 保存专题报告任务（新建或覆盖）
+    pub fn save_report_job(&self, job: &ReportJob) -> Result<(), String> {
+        let state = serde_json::to_string(job).map_err(|e| e.to_string())?;
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO report_jobs (id, kb_id, state, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(id) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![job.id, job.request.kb_id, state, job.created_at, job.updated_at],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 获取专题报告任务
+    pub fn get_report_job(&self, id: &str) -> Result<Option<ReportJob>, String> {
+        let conn = self.conn()?;
+        let state: Option<String> = conn
+            .query_row(
+                "SELECT state FROM report_jobs WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        state
+            .map(|state| serde_json::from_str(&state).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// 列出知识库的专题报告任务，最近更新的在前
+    pub fn list_report_jobs(&self, kb_id: &str) -> Result<Vec<ReportJob>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT state FROM report_jobs WHERE kb_id = ?1 ORDER BY updated_at DESC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        let mut jobs = Vec::new();
+        for state in rows {
+            let state = state.map_err(|e| e.to_string())?;
+            match serde_json::from_str(&state) {
+                Ok(job) => jobs.push(job),
+                Err(e) => log::warn!("跳过无法解析的专题报告任务: {}", e),
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// 删除专题报告任务，已保存的报告文件不受影响
+    pub fn delete_report_job(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM report_jobs WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// 按包含 / 排除规则和忽略文件收集文件夹中待导入的文件，结果按路径排序；
+/// 无法读取的条目不中断收集，作为导入失败的结果一并返回
+pub fn collect_folder_files(
+    folder: &Path,
+    options: &FolderImportOptions,
+) -> Result<(Vec<PathBuf>, Vec<ImportFileResult>), String> {
+    if !folder.is_dir() {
+        return Err(format!("文件夹不存在: {}", folder.display()));
+    }
+
+    let include = if options.include.is_empty() {
+        // 默认导入所有支持的格式
+        let patterns: Vec<String> = extractors::supported_extensions()
+            .iter()
+            .map(|ext| format!("*.{}", ext))
+            .collect();
+        build_globset(&patterns)?
+    } else {
+        build_globset(&options.include)?
+    };
+    let exclude = build_globset(&options.exclude)?;
+
+    let root = folder.to_path_buf();
+    let dir_exclude = exclude.clone();
+    let walker = ignore::WalkBuilder::new(folder)
+        .standard_filters(options.respect_ignore)
+        .hidden(!options.include_hidden)
+        .require_git(false)
+        // 排除规则命中的目录整体跳过
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_some_and(|t| t.is_dir())
+                || !dir_exclude.is_match(relative_path(&root, entry.path()))
+        })
+        .build();
+
+    let mut files = Vec::new();
+    let mut unreadable = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                let path = walk_error_path(&error).unwrap_or(folder);
+                unreadable.push(ImportFileResult {
+                    path: path.to_string_lossy().to_string(),
+                    status: ImportStatus::Failed,
+                    document_id: None,
+                    error: Some(error.to_string()),
+                });
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let relative = relative_path(folder, entry.path());
+        if include.is_match(&relative) && !exclude.is_match(&relative) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok((files, unreadable))
+}
+
+/// 遍历错误所对应的路径
+fn walk_error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        _ => None,
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim())
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("无效的匹配规则 {}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// 相对于导入根目录、以 `/` 分隔的路径，用于 glob 匹配
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// 名称已被占用时依次尝试「名称 (2)」「名称 (3)」…
+fn unique_name(name: &str, existing: &HashSet<String>) -> String {
+    if !existing.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !existing.contains(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// 有文档尚未分词或内容已变化的知识库
+/// 用已保存的词频重新计算知识库内每篇文档的关键词，不重新分词；
+/// 文档增删或移动后 IDF 变化时调用，返回是否有文档的关键词改变
+fn rerank_keywords(conn: &Connection, kb_id: &str) -> Result<bool, String> {
+    let documents: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, metadata FROM documents WHERE kb_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    let index: HashMap<&str, usize> = documents
+        .iter()
+        .enumerate()
+        .map(|(i, (id, _))| (id.as_str(), i))
+        .collect();
+    let mut terms: Vec<HashMap<String, usize>> = vec![HashMap::new(); documents.len()];
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT t.document_id, t.term, t.count FROM document_terms t
+                 JOIN documents d ON d.id = t.document_id
+                 WHERE d.kb_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (doc_id, term, count) = row.map_err(|e| e.to_string())?;
+            if let Some(&i) = index.get(doc_id.as_str()) {
+                terms[i].insert(term, count as usize);
             }
         }
+    }
 
-        // 保存最后一个块
-        if !chunk_content.trim().is_empty() {
-            chunks.push(Chunk {
-                id: Uuid::new_v4().to_string(),
-                content: chunk_content.trim().to_string(),
-                start_line: chunk_start,
-                end_line: lines.len().saturating_sub(1),
-                headings: current_headings,
-            });
+    let mut changed = false;
+    for ((id, metadata), keywords) in documents.iter().zip(keyword_service::rank_keywords(&terms)) {
+        let mut parsed: DocumentMetadata =
+            serde_json::from_str(metadata).map_err(|e| e.to_string())?;
+        if parsed.keywords.as_ref() == Some(&keywords) {
+            continue;
         }
+        parsed.keywords = Some(keywords);
+        let metadata = serde_json::to_string(&parsed).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE documents SET metadata = ?2 WHERE id = ?1",
+            params![id, metadata],
+        )
+        .map_err(|e| e.to_string())?;
+        changed = true;
+    }
+    Ok(changed)
+}
+
+fn kbs_with_pending_terms(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT kb_id FROM documents
+             WHERE terms_hash IS NULL OR terms_hash != content_hash",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
 
-        (chunks, headings)
+/// 文本内容的 SHA-256（十六进制）
+pub(crate) fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// 一次性迁移旧版 kb_data.json，成功后将其重命名为 `.migrated`
+fn migrate_legacy_json(conn: &mut Connection, legacy_path: &Path) -> Result<(), String> {
+    if !legacy_path.exists() {
+        return Ok(());
     }
 
-    /// 解析标题行
-    fn parse_heading(&self, line: &str) -> Option<(u8, String)> {
-        let trimmed = line.trim_start();
-        if !trimmed.starts_with('#') {
-            return None;
+    let data: StorageData = match fs::read_to_string(legacy_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(data) => data,
+        Err(e) => {
+            // 保留原文件以便手动恢复，不再在每次启动时报错
+            log::error!("旧版知识库数据无法解析，已跳过迁移: {}", e);
+            let mut corrupt = legacy_path.as_os_str().to_owned();
+            corrupt.push(".corrupt");
+            fs::rename(legacy_path, PathBuf::from(corrupt)).map_err(|e| e.to_string())?;
+            return Ok(());
         }
+    };
+    let knowledge_bases: Vec<KnowledgeBase> = parse_legacy_items(data.knowledge_bases);
+    let documents: Vec<Document> = parse_legacy_items(data.documents);
 
-        let level = trimmed.chars().take_while(|c| *c == '#').count();
-        if level > 6 || level == 0 {
-            return None;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for kb in &knowledge_bases {
+        tx.execute(
+            "INSERT OR IGNORE INTO knowledge_bases (id, name, description, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![kb.id, kb.name, kb.description, kb.created_at, kb.updated_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    for mut doc in documents.iter().cloned() {
+        // 跳过已不属于任何知识库的文档
+        if !knowledge_bases.iter().any(|kb| kb.id == doc.kb_id) {
+            continue;
+        }
+        // 上次迁移已提交但旧文件未能改名时，已写入的文档不再重复写入
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM documents WHERE id = ?1)",
+                params![doc.id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if exists {
+            continue;
         }
+        if doc.content_hash.is_empty() {
+            doc.content_hash = content_hash(&doc.content);
+        }
+        insert_document(&tx, &doc)?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let mut migrated = legacy_path.as_os_str().to_owned();
+    migrated.push(".migrated");
+    fs::rename(legacy_path, PathBuf::from(migrated)).map_err(|e| e.to_string())?;
+
+    log::info!(
+        "已从 {} 迁移 {} 个知识库、{} 个文档",
+        LEGACY_FILE,
+        knowledge_bases.len(),
+        documents.len()
+    );
+    Ok(())
+}
+
+fn default_storage_dir() -> Result<PathBuf, String> {
+    Ok(dirs::data_dir()
+        .ok_or("无法获取数据目录")?
+        .join("bun-codeview")
+        .join("knowledge_bases"))
+}
+
+/// 逐条解析旧版数据，无法识别的条目记录日志后跳过
+fn parse_legacy_items<T: DeserializeOwned>(items: Vec<serde_json::Value>) -> Vec<T> {
+    items
+        .into_iter()
+        .filter_map(|item| match serde_json::from_value(item) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                log::warn!("跳过无法识别的旧版数据: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 查询所有知识库及其文档数量
+fn query_knowledge_bases(conn: &Connection) -> Result<Vec<KnowledgeBase>, String> {
+    let mut stmt = conn
+        .prepare(&format!("{} ORDER BY kb.created_at", KB_SELECT))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], kb_from_row).map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// 查询知识库的所有文档及文本块
+fn query_documents(conn: &Connection, kb_id: &str) -> Result<Vec<Document>, String> {
+    let mut chunks: HashMap<String, Vec<Chunk>> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT c.document_id, c.id, c.content, c.start_line, c.end_line, c.headings,
+                        c.page
+                 FROM chunks c JOIN documents d ON d.id = c.document_id
+                 WHERE d.kb_id = ?1 ORDER BY c.document_id, c.position",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kb_id], |row| {
+                Ok((row.get::<_, String>(0)?, chunk_from_row(row, 1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (doc_id, chunk) = row.map_err(|e| e.to_string())?;
+            chunks.entry(doc_id).or_default().push(chunk);
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE kb_id = ?1 ORDER BY created_at",
+            DOCUMENT_SELECT
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![kb_id], document_from_row)
+        .map_err(|e| e.to_string())?;
+
+    let mut documents = Vec::new();
+    for row in rows {
+        let mut doc = row.map_err(|e| e.to_string())?;
+        doc.chunks = chunks.remove(&doc.id).unwrap_or_default();
+        documents.push(doc);
+    }
+    Ok(documents)
+}
+
+/// 规范化文件路径，使同一文件的不同写法（相对路径、`..`、符号链接）对应同一文档
+fn canonical_path(path: &str) -> Result<String, String> {
+    let canonical = fs::canonicalize(path).map_err(|e| format!("路径无法访问 {}: {}", path, e))?;
+    let canonical = canonical.to_string_lossy().to_string();
+    // Windows 下去掉 `\\?\` 前缀，保留常见的盘符路径形式
+    #[cfg(windows)]
+    let canonical = match canonical.strip_prefix(r"\\?\") {
+        Some(rest) if !rest.starts_with("UNC\\") => rest.to_string(),
+        _ => canonical,
+    };
+    Ok(canonical)
+}
+
+/// 查询文档（不含文本块），列顺序与 [`document_from_row`] 对应
+const DOCUMENT_SELECT: &str = "SELECT id, kb_id, path, title, content, metadata, created_at,
+        content_hash, modified_at, source_missing
+     FROM documents";
+
+/// 文档摘要的筛选条件，参数依次为知识库 ID、LIKE 搜索模式、源文件缺失状态
+const SUMMARY_FILTER: &str = "WHERE d.kb_id = ?1
+       AND (?2 IS NULL OR d.title LIKE ?2 ESCAPE '\\' OR d.path LIKE ?2 ESCAPE '\\')
+       AND (?3 IS NULL OR d.source_missing = ?3)";
+
+/// 每页文档数上限
+const MAX_PAGE_SIZE: usize = 500;
+
+/// 按条件筛选、排序并分页查询文档摘要，不读取正文
+fn query_document_summaries(
+    conn: &Connection,
+    kb_id: &str,
+    query: &DocumentListQuery,
+) -> Result<DocumentPage, String> {
+    let search = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| format!("%{}%", escape_like(s)));
+    let tag = query
+        .tag
+        .as_deref()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty());
+    let limit = query.limit.clamp(1, MAX_PAGE_SIZE);
+
+    let sort_column = match query.sort_by {
+        DocumentSortField::Title => "d.title COLLATE NOCASE",
+        DocumentSortField::Path => "d.path",
+        DocumentSortField::CreatedAt => "d.created_at",
+        DocumentSortField::ModifiedAt => "d.modified_at",
+        DocumentSortField::WordCount => "CAST(json_extract(d.metadata, '$.wordCount') AS INTEGER)",
+    };
+    let direction = if query.descending { "DESC" } else { "ASC" };
+    // 标签和关键词保存在元数据 JSON 中，按标签筛选时读出全部候选后再过滤分页
+    let (sql_limit, sql_offset) = match tag {
+        Some(_) => (-1, 0),
+        None => (limit as i64, query.offset as i64),
+    };
+    // 排序字段相同时按创建时间排列，保证分页稳定
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT d.id, d.kb_id, d.path, d.title, d.metadata, d.created_at, d.modified_at,
+                    d.source_missing,
+                    (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id)
+             FROM documents d {}
+             ORDER BY {} {}, d.created_at, d.id
+             LIMIT ?4 OFFSET ?5",
+            SUMMARY_FILTER, sort_column, direction
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![kb_id, search, query.source_missing, sql_limit, sql_offset],
+            summary_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+    let mut items = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let total = match tag {
+        Some(tag) => {
+            items.retain(|doc| doc.tags.contains(&tag));
+            let total = items.len();
+            items = items.into_iter().skip(query.offset).take(limit).collect();
+            total
+        }
+        None => conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM documents d {}", SUMMARY_FILTER),
+                params![kb_id, search, query.source_missing],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| e.to_string())? as usize,
+    };
+    Ok(DocumentPage {
+        items,
+        total,
+        offset: query.offset,
+        limit,
+    })
+}
+
+fn summary_from_row(row: &Row) -> rusqlite::Result<DocumentSummary> {
+    let metadata: String = row.get(4)?;
+    let metadata: DocumentMetadata = serde_json::from_str(&metadata).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(DocumentSummary {
+        id: row.get(0)?,
+        kb_id: row.get(1)?,
+        path: row.get(2)?,
+        title: row.get(3)?,
+        word_count: metadata.word_count,
+        line_count: metadata.line_count,
+        chunk_count: row.get::<_, i64>(8)? as usize,
+        page_count: metadata.page_count,
+        tags: metadata.all_tags(),
+        created_at: row.get(5)?,
+        modified_at: row.get(6)?,
+        source_missing: row.get(7)?,
+    })
+}
+
+/// 转义 LIKE 中的通配符，配合 `ESCAPE '\'` 使用
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// 查询知识库及其文档数量
+const KB_SELECT: &str = "SELECT kb.id, kb.name, kb.description, kb.created_at, kb.updated_at,
+        (SELECT COUNT(*) FROM documents d WHERE d.kb_id = kb.id), kb.chunking,
+        kb.icon, kb.color, kb.tags
+     FROM knowledge_bases kb";
+
+fn kb_from_row(row: &Row) -> rusqlite::Result<KnowledgeBase> {
+    // 旧版数据库没有分块配置，使用默认值
+    let chunking = match row.get::<_, Option<String>>(6)? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        None => ChunkingConfig::default(),
+    };
+    let tags = match row.get::<_, Option<String>>(9)? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(9, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        None => Vec::new(),
+    };
+    Ok(KnowledgeBase {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        document_count: row.get::<_, i64>(5)? as usize,
+        chunking,
+        icon: row.get(7)?,
+        color: row.get(8)?,
+        tags,
+    })
+}
+
+fn document_from_row(row: &Row) -> rusqlite::Result<Document> {
+    let metadata: String = row.get(5)?;
+    let created_at: DateTime<Utc> = row.get(6)?;
+    Ok(Document {
+        id: row.get(0)?,
+        kb_id: row.get(1)?,
+        path: row.get(2)?,
+        title: row.get(3)?,
+        content: row.get(4)?,
+        chunks: Vec::new(),
+        metadata: serde_json::from_str(&metadata).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        created_at,
+        content_hash: row.get(7)?,
+        modified_at: row.get(8)?,
+        source_missing: row.get(9)?,
+    })
+}
+
+/// 从 `offset` 列开始读取文本块
+fn chunk_from_row(row: &Row, offset: usize) -> rusqlite::Result<Chunk> {
+    let headings: String = row.get(offset + 4)?;
+    Ok(Chunk {
+        id: row.get(offset)?,
+        content: row.get(offset + 1)?,
+        start_line: row.get::<_, i64>(offset + 2)? as usize,
+        end_line: row.get::<_, i64>(offset + 3)? as usize,
+        headings: serde_json::from_str(&headings).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                offset + 4,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })?,
+        page: row
+            .get::<_, Option<i64>>(offset + 5)?
+            .map(|page| page as usize),
+    })
+}
+
+fn conversation_from_row(row: &Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        kb_id: row.get(1)?,
+        title: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+fn message_from_row(row: &Row) -> rusqlite::Result<ConversationMessage> {
+    let citations: String = row.get(3)?;
+    Ok(ConversationMessage {
+        id: row.get(0)?,
+        role: row.get(1)?,
+        content: row.get(2)?,
+        citations: serde_json::from_str(&citations).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        created_at: row.get(4)?,
+    })
+}
+
+fn insert_knowledge_base(conn: &Connection, kb: &KnowledgeBase) -> Result<(), String> {
+    let chunking = serde_json::to_string(&kb.chunking).map_err(|e| e.to_string())?;
+    let tags = serde_json::to_string(&kb.tags).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO knowledge_bases (id, name, description, created_at, updated_at, chunking,
+                                      icon, color, tags)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            kb.id,
+            kb.name,
+            kb.description,
+            kb.created_at,
+            kb.updated_at,
+            chunking,
+            kb.icon,
+            kb.color,
+            tags
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-        let text = trimmed[level..].trim().to_string();
-        if text.is_empty() {
-            return None;
+/// 复制文档到目标知识库，文本块及向量一并复制，返回副本 ID
+fn copy_document(conn: &Connection, doc_id: &str, target_kb_id: &str) -> Result<String, String> {
+    let new_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO documents (id, kb_id, path, title, content, metadata, created_at,
+                                content_hash, modified_at, source_missing, terms_hash)
+         SELECT ?2, ?3, path, title, content, metadata, ?4, content_hash, modified_at,
+                source_missing, terms_hash
+         FROM documents WHERE id = ?1",
+        params![doc_id, new_id, target_kb_id, Utc::now()],
+    )
+    .map_err(|e| e.to_string())?;
+    // 副本沿用源文档的分词结果
+    conn.execute(
+        "INSERT INTO document_terms (document_id, term, count)
+         SELECT ?2, term, count FROM document_terms WHERE document_id = ?1",
+        params![doc_id, new_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let chunk_ids: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM chunks WHERE document_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![doc_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    for chunk_id in &chunk_ids {
+        let new_chunk_id = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO chunks (id, document_id, position, content, start_line, end_line,
+                                 headings, page)
+             SELECT ?2, ?3, position, content, start_line, end_line, headings, page
+             FROM chunks WHERE id = ?1",
+            params![chunk_id, new_chunk_id, new_id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO chunk_embeddings (chunk_id, provider, vector)
+             SELECT ?2, provider, vector FROM chunk_embeddings WHERE chunk_id = ?1",
+            params![chunk_id, new_chunk_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(new_id)
+}
+
+/// 去掉首尾空白，空字符串视为未设置
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// 颜色只接受 `#RGB` 或 `#RRGGBB`
+fn validate_color(color: &str) -> Result<(), String> {
+    let valid = color.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("无效的颜色: {}", color))
+    }
+}
+
+/// 去掉空标签，按不区分大小写去重并保留首次出现的写法
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
         }
+    }
+    normalized
+}
+
+/// 写入文档及其文本块
+fn insert_document(conn: &Connection, doc: &Document) -> Result<(), String> {
+    let metadata = serde_json::to_string(&doc.metadata).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO documents (id, kb_id, path, title, content, metadata, created_at,
+                                content_hash, modified_at, source_missing)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            doc.id,
+            doc.kb_id,
+            doc.path,
+            doc.title,
+            doc.content,
+            metadata,
+            doc.created_at,
+            doc.content_hash,
+            doc.modified_at,
+            doc.source_missing
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    insert_chunks(conn, doc)
+}
 
-        Some((level as u8, text))
+/// 用新内容替换文档，原文本块（及其向量）被删除后重新写入
+fn update_document(conn: &Connection, doc: &Document) -> Result<(), String> {
+    let metadata = serde_json::to_string(&doc.metadata).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM chunks WHERE document_id = ?1", params![doc.id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM document_terms WHERE document_id = ?1",
+        params![doc.id],
+    )
+    .map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE documents SET title = ?2, content = ?3, metadata = ?4, content_hash = ?5,
+                    modified_at = ?6, source_missing = 0
+             WHERE id = ?1",
+            params![
+                doc.id,
+                doc.title,
+                doc.content,
+                metadata,
+                doc.content_hash,
+                doc.modified_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    // 文档可能已在读取文件期间被删除
+    if updated == 0 {
+        return Ok(());
     }
+    insert_chunks(conn, doc)
+}
+
+/// 记录文档的最新修改时间并清除缺失标记
+fn touch_document(
+    conn: &Connection,
+    id: &str,
+    modified_at: Option<DateTime<Utc>>,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE documents SET modified_at = ?2, source_missing = 0 WHERE id = ?1",
+        params![id, modified_at],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn insert_chunks(conn: &Connection, doc: &Document) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO chunks (id, document_id, position, content, start_line, end_line, headings, page)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .map_err(|e| e.to_string())?;
+    for (position, chunk) in doc.chunks.iter().enumerate() {
+        let headings = serde_json::to_string(&chunk.headings).map_err(|e| e.to_string())?;
+        stmt.execute(params![
+            chunk.id,
+            doc.id,
+            position as i64,
+            chunk.content,
+            chunk.start_line as i64,
+            chunk.end_line as i64,
+            headings,
+            chunk.page.map(|page| page as i64)
+        ])
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 更新知识库修改时间
+fn touch_knowledge_base(conn: &Connection, kb_id: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE knowledge_bases SET updated_at = ?1 WHERE id = ?2",
+        params![Utc::now(), kb_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::ChunkingStrategyKind;
+
+    #[test]
+    fn test_migrate_legacy_json() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let doc_path = dir.join("note.md");
+        fs::write(&doc_path, "# Note\n\nhello").unwrap();
+
+        let now = Utc::now();
+        let data = StorageData {
+            knowledge_bases: vec![serde_json::to_value(KnowledgeBase {
+                id: "kb-1".to_string(),
+                name: "Legacy".to_string(),
+                description: None,
+                created_at: now,
+                updated_at: now,
+                document_count: 1,
+                chunking: ChunkingConfig::default(),
+                icon: None,
+                color: None,
+                tags: Vec::new(),
+            })
+            .unwrap()],
+            documents: vec![serde_json::to_value(Document {
+                id: "doc-1".to_string(),
+                kb_id: "kb-1".to_string(),
+                path: doc_path.to_string_lossy().to_string(),
+                title: "Note".to_string(),
+                content: "# Note\n\nhello".to_string(),
+                chunks: vec![Chunk {
+                    id: "chunk-1".to_string(),
+                    content: "# Note\n\nhello".to_string(),
+                    start_line: 1,
+                    end_line: 3,
+                    headings: vec!["Note".to_string()],
+                    page: None,
+                }],
+                metadata: DocumentMetadata {
+                    word_count: 9,
+                    line_count: 3,
+                    headings: Vec::new(),
+                    keywords: None,
+                    page_count: None,
+                    tags: Vec::new(),
+                },
+                created_at: now,
+                content_hash: String::new(),
+                modified_at: None,
+                source_missing: false,
+            })
+            .unwrap()],
+        };
+        fs::write(dir.join(LEGACY_FILE), serde_json::to_string(&data).unwrap()).unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        assert!(!dir.join(LEGACY_FILE).exists());
+        assert!(dir.join("kb_data.json.migrated").exists());
+
+        let kbs = service.list_knowledge_bases().unwrap();
+        assert_eq!(kbs.len(), 1);
+        assert_eq!(kbs[0].document_count, 1);
+        let docs = service.get_documents("kb-1").unwrap();
+        assert_eq!(docs[0].chunks[0].headings, vec!["Note".to_string()]);
+        drop(service);
+
+        // 再次打开不会重复迁移
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        // 重新导入已迁移的同路径文档时更新原文档而不是新增
+        let reimported = service
+            .import_document("kb-1", &doc_path.to_string_lossy())
+            .unwrap();
+        assert_eq!(reimported.id, "doc-1");
+        assert_eq!(service.get_documents("kb-1").unwrap().len(), 1);
+        assert!(service.import_document("missing", "x.md").is_err());
+        let chunks = service.get_documents("kb-1").unwrap()[0].chunks.len();
+        drop(service);
+
+        // 旧文件未能改名时再次迁移不会重复写入
+        fs::rename(dir.join("kb_data.json.migrated"), dir.join(LEGACY_FILE)).unwrap();
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        assert!(!dir.join(LEGACY_FILE).exists());
+        let docs = service.get_documents("kb-1").unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].chunks.len(), chunks);
+
+        service.delete_knowledge_base("kb-1").unwrap();
+        assert!(service.get_documents("kb-1").unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bulk_import_refreshes_cache() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.md");
+        let b = dir.join("b.md");
+        fs::write(&a, "# A\n\nalpha").unwrap();
+        fs::write(&b, "# B\n\nbeta").unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        // 预先填充缓存
+        assert!(service.get_documents(&kb.id).unwrap().is_empty());
+        assert_eq!(service.list_knowledge_bases().unwrap()[0].document_count, 0);
+
+        let paths = vec![
+            a.to_string_lossy().to_string(),
+            dir.join("missing.md").to_string_lossy().to_string(),
+            b.to_string_lossy().to_string(),
+        ];
+        let imported = service.import_documents(&kb.id, &paths).unwrap();
+        assert_eq!(imported.len(), 2);
+
+        let docs = service.get_documents(&kb.id).unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(
+            service
+                .get_knowledge_base(&kb.id)
+                .unwrap()
+                .unwrap()
+                .document_count,
+            2
+        );
+
+        service.delete_document(&docs[0].id).unwrap();
+        assert_eq!(service.get_documents(&kb.id).unwrap().len(), 1);
+        assert_eq!(service.list_knowledge_bases().unwrap()[0].document_count, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_embeddings_are_persisted_per_provider() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.md");
+        fs::write(&path, "# A\n\nalpha\n---\nbeta").unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        let doc = service
+            .import_document(&kb.id, &path.to_string_lossy())
+            .unwrap();
+
+        let provider = embedding_service::HashingEmbedder::new(16);
+        assert_eq!(service.embed_knowledge_base(&kb.id, &provider).unwrap(), 2);
+        // 已有向量的文本块不会重复计算
+        assert_eq!(service.embed_knowledge_base(&kb.id, &provider).unwrap(), 0);
+        drop(service);
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let embeddings = service.get_embeddings(&kb.id, &provider.id()).unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[&doc.chunks[0].id].len(), 16);
+        assert!(service.get_embeddings(&kb.id, "other").unwrap().is_empty());
+
+        service.delete_document(&doc.id).unwrap();
+        assert!(service
+            .get_embeddings(&kb.id, &provider.id())
+            .unwrap()
+            .is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 写入文件并显式设置修改时间，避免文件系统时间精度影响测试
+    fn write_with_mtime(path: &Path, content: &str, secs: u64) {
+        fs::write(path, content).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reimport_and_sync() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.md");
+        let b = dir.join("b.md");
+        write_with_mtime(&a, "# A\n\nalpha", 1_000);
+        write_with_mtime(&b, "# B\n\nbeta", 1_000);
+        let a_path = a.to_string_lossy().to_string();
+        let b_path = b.to_string_lossy().to_string();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        let first = service.import_document(&kb.id, &a_path).unwrap();
+        assert_eq!(first.content_hash.len(), 64);
+
+        // 重复导入同一路径不会产生新文档，文本块保持不变
+        let imported = service
+            .import_documents(&kb.id, &[a_path.clone(), b_path.clone(), a_path.clone()])
+            .unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].id, first.id);
+        assert_eq!(imported[0].chunks[0].id, first.chunks[0].id);
+        assert_eq!(service.get_documents(&kb.id).unwrap().len(), 2);
+        // 同一文件的不同写法视为同一文档
+        let alias = dir.join("store").join("..").join("a.md");
+        let again = service
+            .import_document(&kb.id, &alias.to_string_lossy())
+            .unwrap();
+        assert_eq!(
+            (again.id, again.path),
+            (first.id.clone(), first.path.clone())
+        );
+        assert_eq!(service.get_documents(&kb.id).unwrap().len(), 2);
+
+        // 修改时间变化但内容相同的文件不重新分块
+        write_with_mtime(&a, "# A\n\nalpha", 2_000);
+        let report = service.sync_knowledge_base(&kb.id).unwrap();
+        assert!(!report.has_changes());
+        assert_eq!(report.unchanged, 2);
+        let docs = service.get_documents(&kb.id).unwrap();
+        assert_eq!(docs[0].chunks[0].id, first.chunks[0].id);
+
+        write_with_mtime(&a, "# A\n\nalpha 已修改", 3_000);
+        fs::remove_file(&b).unwrap();
+        let report = service.sync_knowledge_base(&kb.id).unwrap();
+        assert_eq!(report.updated, vec![a_path.clone()]);
+        assert_eq!(report.missing, vec![b_path.clone()]);
+
+        let docs = service.get_documents(&kb.id).unwrap();
+        assert_eq!(docs[0].id, first.id);
+        assert!(docs[0].content.contains("已修改"));
+        assert_ne!(docs[0].chunks[0].id, first.chunks[0].id);
+        assert!(docs[1].source_missing);
+
+        // 缺失标记只在首次发现时报告，文件恢复后清除
+        assert!(!service.sync_knowledge_base(&kb.id).unwrap().has_changes());
+        write_with_mtime(&b, "# B\n\nbeta", 4_000);
+        let report = service.sync_knowledge_base(&kb.id).unwrap();
+        assert_eq!(report.restored, vec![b_path]);
+        assert!(!service.get_documents(&kb.id).unwrap()[1].source_missing);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
     #[test]
-    fn test_parse_heading() {
-        let service = KnowledgeBaseService {
-            storage_path: PathBuf::from("/tmp/test.json"),
+    fn test_import_folder_with_filters() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        let folder = dir.join("notes");
+        for sub in ["guide", "drafts", "build", ".private"] {
+            fs::create_dir_all(folder.join(sub)).unwrap();
+        }
+        fs::write(folder.join("a.md"), "# A\n\nalpha").unwrap();
+        fs::write(folder.join("guide/b.MARKDOWN"), "# B\n\nbeta").unwrap();
+        fs::write(folder.join("guide/readme.txt"), "text").unwrap();
+        fs::write(folder.join("guide/logo.png"), [0x89, 0x50]).unwrap();
+        fs::write(folder.join("drafts/c.md"), "# C").unwrap();
+        fs::write(folder.join("build/d.md"), "# D").unwrap();
+        fs::write(folder.join(".private/e.md"), "# E").unwrap();
+        fs::write(folder.join("bad.md"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(folder.join(".gitignore"), "build/\n").unwrap();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        let options = FolderImportOptions {
+            exclude: vec!["drafts".to_string()],
+            ..FolderImportOptions::default()
         };
 
+        let progress = Mutex::new(Vec::new());
+        let report = service
+            .import_folder(&kb.id, &folder, &options, &|p| {
+                progress.lock().unwrap().push((p.processed, p.total));
+            })
+            .unwrap();
+        let names: Vec<(String, ImportStatus)> = report
+            .files
+            .iter()
+            .map(|f| (relative_path(&folder, Path::new(&f.path)), f.status))
+            .collect();
         assert_eq!(
-            service.parse_heading("# Title"),
-            Some((1, "Title".to_string()))
+            names,
+            vec![
+                ("a.md".to_string(), ImportStatus::Imported),
+                ("bad.md".to_string(), ImportStatus::Failed),
+                ("guide/b.MARKDOWN".to_string(), ImportStatus::Imported),
+                ("guide/readme.txt".to_string(), ImportStatus::Imported),
+            ]
         );
+        assert!(report.files[1].error.is_some());
+        let mut progress = progress.into_inner().unwrap();
+        progress.sort();
+        assert_eq!(progress, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+        // 再次导入时已导入的文件作为重复跳过
+        fs::write(folder.join("a.md"), "# A\n\nalpha 2").unwrap();
+        let report = service
+            .import_folder(&kb.id, &folder, &options, &|_| {})
+            .unwrap();
+        assert_eq!(report.count(ImportStatus::Updated), 1);
+        assert_eq!(report.count(ImportStatus::Duplicate), 2);
+        assert_eq!(service.get_documents(&kb.id).unwrap().len(), 3);
+
+        // 指定包含规则，并且不遵循忽略规则
+        let options = FolderImportOptions {
+            include: vec!["build/*.md".to_string(), "*.txt".to_string()],
+            respect_ignore: false,
+            ..FolderImportOptions::default()
+        };
+        let (files, _) = collect_folder_files(&folder, &options).unwrap();
+        let names: Vec<String> = files.iter().map(|f| relative_path(&folder, f)).collect();
+        assert_eq!(names, vec!["build/d.md", "guide/readme.txt"]);
+        assert!(collect_folder_files(&dir.join("missing"), &options).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_folder_keeps_partial_report() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        let folder = dir.join("notes");
+        fs::create_dir_all(&folder).unwrap();
+        for idx in 0..=IMPORT_BATCH_SIZE {
+            fs::write(
+                folder.join(format!("n{:03}.md", idx)),
+                format!("# N{}", idx),
+            )
+            .unwrap();
+        }
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        // 让第二批写入失败，第一批已提交的结果仍然返回
+        service
+            .conn()
+            .unwrap()
+            .execute_batch(&format!(
+                "CREATE TEMP TRIGGER fail_insert BEFORE INSERT ON documents
+                 WHEN NEW.path LIKE '%n{:03}.md'
+                 BEGIN SELECT RAISE(ABORT, '磁盘已满'); END;",
+                IMPORT_BATCH_SIZE
+            ))
+            .unwrap();
+
+        let report = service
+            .import_folder(&kb.id, &folder, &FolderImportOptions::default(), &|_| {})
+            .unwrap();
+        assert_eq!(report.count(ImportStatus::Imported), IMPORT_BATCH_SIZE);
+        assert_eq!(report.count(ImportStatus::Failed), 1);
+        let failed = report.files.last().unwrap();
+        assert!(failed.error.as_ref().unwrap().contains("磁盘已满"));
+        assert_eq!(
+            service.get_documents(&kb.id).unwrap().len(),
+            IMPORT_BATCH_SIZE
+        );
+
+        // 无法读取的目录作为失败结果返回，不影响其他文件（root 用户不受权限限制，跳过）
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let locked = folder.join("locked");
+            fs::create_dir_all(&locked).unwrap();
+            fs::write(locked.join("x.md"), "# X").unwrap();
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+            if fs::read_dir(&locked).is_err() {
+                let (files, unreadable) =
+                    collect_folder_files(&folder, &FolderImportOptions::default()).unwrap();
+                assert_eq!(files.len(), IMPORT_BATCH_SIZE + 1);
+                assert_eq!(unreadable.len(), 1);
+                assert_eq!(unreadable[0].status, ImportStatus::Failed);
+            }
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunking_config_rechunks() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        fs::write(&note, "# 标题\n\n第一段\n\n# 第二节\n\n第二段").unwrap();
+        let note_path = note.to_string_lossy().to_string();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        assert_eq!(kb.chunking, ChunkingConfig::default());
+        let doc = service.import_document(&kb.id, &note_path).unwrap();
+        assert_eq!(doc.chunks.len(), 2);
+
+        let config = ChunkingConfig {
+            strategy: ChunkingStrategyKind::Paragraph,
+            ..ChunkingConfig::default()
+        };
+        let invalid = ChunkingConfig {
+            max_tokens: 8,
+            ..config.clone()
+        };
+        assert!(service.set_chunking_config(&kb.id, invalid).is_err());
+        let updated = service.set_chunking_config(&kb.id, config.clone()).unwrap();
+        assert_eq!(updated.chunking, config);
+
+        // 配置随数据库持久化，重新分块后整篇合并为一个文本块
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        assert_eq!(
+            service
+                .get_knowledge_base(&kb.id)
+                .unwrap()
+                .unwrap()
+                .chunking,
+            config
+        );
+        let report = service.rechunk_knowledge_base(&kb.id).unwrap();
+        assert_eq!(report.updated, vec![note_path]);
+        let docs = service.get_documents(&kb.id).unwrap();
+        assert_eq!(docs[0].id, doc.id);
+        assert_eq!(docs[0].chunks.len(), 1);
+        assert!(service.set_chunking_config("missing", config).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keywords_and_tags() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let rust = dir.join("rust.md");
+        let sqlite = dir.join("sqlite.md");
+        fs::write(
+            &rust,
+            "---\ntags: [Rust, 笔记]\n---\n# 所有权\n\n所有权规则由编译器检查，所有权转移后原变量失效。",
+        )
+        .unwrap();
+        fs::write(
+            &sqlite,
+            "---\ntags:\n  - 笔记\n---\n# 数据库\n\nSQLite 数据库的 WAL 模式允许并发读取。",
+        )
+        .unwrap();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        service
+            .import_documents(
+                &kb.id,
+                &[
+                    rust.to_string_lossy().to_string(),
+                    sqlite.to_string_lossy().to_string(),
+                ],
+            )
+            .unwrap();
+
+        let docs = service.get_documents(&kb.id).unwrap();
+        let rust_doc = docs
+            .iter()
+            .find(|d| d.path == rust.to_string_lossy())
+            .unwrap();
+        assert_eq!(rust_doc.metadata.tags, vec!["Rust", "笔记"]);
+        assert_eq!(rust_doc.metadata.keywords.as_ref().unwrap()[0], "所有权");
+
+        // 标签与关键词合并计数，标签不区分大小写
+        let tags = service.list_tags(&kb.id).unwrap();
+        assert_eq!(tags.iter().find(|t| t.tag == "笔记").unwrap().count, 2);
+        assert_eq!(tags.iter().find(|t| t.tag == "rust").unwrap().count, 1);
+        let by_tag = service.get_documents_by_tag(&kb.id, "RUST").unwrap();
+        assert_eq!(by_tag.len(), 1);
+        assert_eq!(by_tag[0].id, rust_doc.id);
+
+        // 删除文档后标签计数随之更新
+        service.delete_document(&rust_doc.id).unwrap();
+        let tags = service.list_tags(&kb.id).unwrap();
+        assert!(tags.iter().all(|t| t.tag != "rust"));
+        assert_eq!(tags.iter().find(|t| t.tag == "笔记").unwrap().count, 1);
+
+        // 没有词语的文档记录分词时的哈希，之后不再重复分词
+        let numbers = dir.join("numbers.md");
+        fs::write(&numbers, "123 456").unwrap();
+        let doc = service
+            .import_document(&kb.id, &numbers.to_string_lossy())
+            .unwrap();
+        assert_eq!(doc.metadata.keywords, Some(Vec::new()));
+        let pending = |service: &KnowledgeBaseService| {
+            kbs_with_pending_terms(&service.conn().unwrap()).unwrap()
+        };
+        assert!(pending(&service).is_empty());
+
+        // 内容变化的文档重新分词
+        service
+            .conn()
+            .unwrap()
+            .execute(
+                "UPDATE documents SET content_hash = 'changed' WHERE id = ?1",
+                params![doc.id],
+            )
+            .unwrap();
+        assert_eq!(pending(&service), vec![kb.id.clone()]);
+        drop(service);
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        assert!(pending(&service).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keywords_rerank_when_documents_change() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.md");
+        let b = dir.join("b.md");
+        fs::write(&a, "alpha zeta").unwrap();
+        fs::write(&b, "alpha").unwrap();
+        let a_path = a.to_string_lossy().to_string();
+        let b_path = b.to_string_lossy().to_string();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        let other = service
+            .create_knowledge_base("其他".to_string(), None)
+            .unwrap();
+        let keywords = |service: &KnowledgeBaseService, kb_id: &str, path: &str| {
+            service
+                .get_documents(kb_id)
+                .unwrap()
+                .into_iter()
+                .find(|doc| doc.path == path)
+                .and_then(|doc| doc.metadata.keywords)
+                .unwrap()
+        };
+
+        // 只在 a 中出现的 zeta IDF 更高，排在前面
+        let docs = service
+            .import_documents(&kb.id, &[a_path.clone(), b_path.clone()])
+            .unwrap();
+        assert_eq!(keywords(&service, &kb.id, &a_path), vec!["zeta", "alpha"]);
+
+        // 删除 b 后不重新分词，但 a 的关键词按新的文档频率重新排序
+        service.delete_document(&docs[1].id).unwrap();
+        assert_eq!(keywords(&service, &kb.id, &a_path), vec!["alpha", "zeta"]);
+
+        // 移动文档后两边的知识库都重新排序
+        let b_doc = service.import_document(&kb.id, &b_path).unwrap();
+        assert_eq!(keywords(&service, &kb.id, &a_path), vec!["zeta", "alpha"]);
+        service.move_documents(&[b_doc.id], &other.id).unwrap();
+        assert_eq!(keywords(&service, &kb.id, &a_path), vec!["alpha", "zeta"]);
+        assert_eq!(keywords(&service, &other.id, &b_path), vec!["alpha"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_and_transfer_documents() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        let other = dir.join("other.md");
+        fs::write(&note, "# 笔记\n\n第一段").unwrap();
+        fs::write(&other, "# 其他\n\n第二段").unwrap();
+        let note_path = note.to_string_lossy().to_string();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let source = service
+            .create_knowledge_base("源".to_string(), None)
+            .unwrap();
+        let target = service
+            .create_knowledge_base("目标".to_string(), None)
+            .unwrap();
+
+        let input = KnowledgeBaseInput {
+            name: "  资料  ".to_string(),
+            description: Some(" ".to_string()),
+            icon: Some("📚".to_string()),
+            color: Some("#3B82F6".to_string()),
+            tags: vec!["Rust".to_string(), "rust".to_string(), " ".to_string()],
+        };
+        let updated = service
+            .update_knowledge_base(&source.id, input.clone())
+            .unwrap();
+        assert_eq!(updated.name, "资料");
+        assert_eq!(updated.description, None);
+        assert_eq!(updated.icon.as_deref(), Some("📚"));
+        assert_eq!(updated.tags, vec!["Rust"]);
+        assert!(updated.updated_at > source.updated_at);
+        let invalid = KnowledgeBaseInput {
+            color: Some("blue".to_string()),
+            ..input.clone()
+        };
+        assert!(service.update_knowledge_base(&source.id, invalid).is_err());
+        assert!(service.update_knowledge_base("missing", input).is_err());
+
+        let docs = service
+            .import_documents(
+                &source.id,
+                &[note_path.clone(), other.to_string_lossy().to_string()],
+            )
+            .unwrap();
+        let provider = embedding_service::HashingEmbedder::new(8);
+        service.embed_knowledge_base(&source.id, &provider).unwrap();
+
+        // 复制：副本使用新的 ID，向量一并复制
+        let copies = service
+            .copy_documents(&[docs[0].id.clone()], &target.id)
+            .unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_ne!(copies[0].id, docs[0].id);
+        assert_ne!(copies[0].chunks[0].id, docs[0].chunks[0].id);
+        assert_eq!(copies[0].content, docs[0].content);
+        let vectors = service.get_embeddings(&target.id, &provider.id()).unwrap();
+        assert!(vectors.contains_key(&copies[0].chunks[0].id));
+
+        // 目标已有同路径文档时整体失败
+        assert!(service
+            .move_documents(&[docs[1].id.clone(), docs[0].id.clone()], &target.id)
+            .is_err());
+        assert_eq!(service.get_documents(&source.id).unwrap().len(), 2);
+
+        let moved = service
+            .move_documents(&[docs[1].id.clone()], &target.id)
+            .unwrap();
+        assert_eq!(moved[0].id, docs[1].id);
+        let kbs = service.list_knowledge_bases().unwrap();
+        let count = |id: &str| kbs.iter().find(|kb| kb.id == id).unwrap().document_count;
+        assert_eq!((count(&source.id), count(&target.id)), (1, 2));
+        assert!(service
+            .get_embeddings(&target.id, &provider.id())
+            .unwrap()
+            .contains_key(&docs[1].chunks[0].id));
+
+        assert!(service
+            .move_documents(&[docs[0].id.clone()], "missing")
+            .is_err());
+        assert!(service
+            .copy_documents(&["missing".to_string()], &target.id)
+            .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_document_summaries() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [
+            ("b.md", "---\ntags: [Rust]\n---\n# Beta\n\n短文"),
+            ("a.md", "# alpha\n\n较长的一段正文内容"),
+            ("c.md", "# Gamma\n\n第一段\n\n# 第二节\n\n第二段"),
+        ];
+        let paths: Vec<String> = files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.join(name);
+                fs::write(&path, content).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = service
+            .create_knowledge_base("KB".to_string(), None)
+            .unwrap();
+        let docs = service.import_documents(&kb.id, &paths).unwrap();
+
+        let list = |query: DocumentListQuery| -> Vec<String> {
+            service
+                .list_document_summaries(&kb.id, &query)
+                .unwrap()
+                .items
+                .into_iter()
+                .map(|doc| doc.title)
+                .collect()
+        };
+        let page = service
+            .list_document_summaries(&kb.id, &DocumentListQuery::default())
+            .unwrap();
+        assert_eq!(page.total, 3);
+        let gamma = page.items.iter().find(|doc| doc.title == "Gamma").unwrap();
+        assert_eq!(gamma.chunk_count, 2);
+        assert_eq!(gamma.word_count, docs[2].metadata.word_count);
+
+        let by_title = DocumentListQuery {
+            sort_by: DocumentSortField::Title,
+            ..DocumentListQuery::default()
+        };
+        assert_eq!(list(by_title.clone()), vec!["alpha", "Beta", "Gamma"]);
+        let second_page = DocumentListQuery {
+            offset: 1,
+            limit: 1,
+            descending: true,
+            ..by_title
+        };
+        let page = service
+            .list_document_summaries(&kb.id, &second_page)
+            .unwrap();
+        assert_eq!((page.total, page.items.len()), (3, 1));
+        assert_eq!(page.items[0].title, "Beta");
+
+        let search = DocumentListQuery {
+            search: Some("A.MD".to_string()),
+            ..DocumentListQuery::default()
+        };
+        assert_eq!(list(search), vec!["alpha"]);
+        // 通配符按字面匹配
+        let wildcard = DocumentListQuery {
+            search: Some("_".to_string()),
+            ..DocumentListQuery::default()
+        };
+        assert!(list(wildcard).is_empty());
+        let by_words = DocumentListQuery {
+            sort_by: DocumentSortField::WordCount,
+            descending: true,
+            limit: 1,
+            ..DocumentListQuery::default()
+        };
+        let longest = docs
+            .iter()
+            .max_by_key(|doc| doc.metadata.word_count)
+            .unwrap();
+        assert_eq!(list(by_words), vec![longest.title.clone()]);
+        let tagged = DocumentListQuery {
+            tag: Some("rust".to_string()),
+            ..DocumentListQuery::default()
+        };
+        assert_eq!(list(tagged.clone()), vec!["Beta"]);
+        let tagged_page = service
+            .list_document_summaries(
+                &kb.id,
+                &DocumentListQuery {
+                    offset: 1,
+                    ..tagged
+                },
+            )
+            .unwrap();
+        assert_eq!((tagged_page.total, tagged_page.items.len()), (1, 0));
+
+        fs::remove_file(&paths[1]).unwrap();
+        service.sync_knowledge_base(&kb.id).unwrap();
+        let missing = DocumentListQuery {
+            source_missing: Some(true),
+            ..DocumentListQuery::default()
+        };
+        assert_eq!(list(missing), vec!["alpha"]);
+
+        let full = service.get_document(&docs[2].id).unwrap().unwrap();
+        assert_eq!(full.content, docs[2].content);
+        assert_eq!(full.chunks.len(), 2);
+        assert_eq!(full.chunks[1].id, docs[2].chunks[1].id);
+        assert!(service.get_document("missing").unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let kb = service
+            .create_knowledge_base("保留".to_string(), None)
+            .unwrap();
+        drop(service);
+        // 模拟旧版本数据库，重新打开时迁移并自动备份
+        Connection::open(dir.join(DB_FILE))
+            .unwrap()
+            .pragma_update(None, "user_version", 1)
+            .unwrap();
+        // 无法解析的旧版 JSON 被重命名，不影响打开
+        fs::write(dir.join(LEGACY_FILE), "{ not json").unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        assert!(dir.join("kb_data.json.corrupt").exists());
+        let status = service.storage_status().unwrap();
+        assert!(status.quarantined.is_none());
+        assert_eq!(status.backups.len(), 1);
+        let backup = status.backups[0].name.clone();
+
+        service.delete_knowledge_base(&kb.id).unwrap();
+        service
+            .create_knowledge_base("新建".to_string(), None)
+            .unwrap();
+        let status = service.restore_backup(&backup).unwrap();
+        let kbs = service.list_knowledge_bases().unwrap();
+        assert_eq!(kbs.len(), 1);
+        assert_eq!(kbs[0].id, kb.id);
+        // 恢复前的数据也已备份
+        assert!(status
+            .backups
+            .iter()
+            .any(|b| b.name.contains("before-restore")));
+        assert!(service.restore_backup("missing.db").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_oldest_backup() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let backup_dir = dir.join(BACKUP_DIR);
+        for idx in 0..5 {
+            service
+                .create_knowledge_base(format!("KB {}", idx), None)
+                .unwrap();
+            kb_schema::backup_database(&service.conn().unwrap(), &backup_dir, "test").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let backups = service.storage_status().unwrap().backups;
+        assert_eq!(backups.len(), 5);
+
+        // 最旧的备份只包含第一个知识库
+        let oldest = backups.last().unwrap().name.clone();
+        service.restore_backup(&oldest).unwrap();
+        let kbs = service.list_knowledge_bases().unwrap();
+        assert_eq!(kbs.len(), 1);
+        assert_eq!(kbs[0].name, "KB 0");
+        let backups = service.storage_status().unwrap().backups;
+        assert_eq!(backups.len(), 5);
+        assert!(backups[0].name.contains("before-restore"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        let dir = std::env::temp_dir().join(format!("kb-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // 数据库还不存在时返回空的知识库列表，也不创建文件
+        let service = KnowledgeBaseService::open_read_only(&dir).unwrap();
+        assert!(service.list_knowledge_bases().unwrap().is_empty());
+        assert!(!dir.join(DB_FILE).exists());
+
+        let service = KnowledgeBaseService::open(&dir).unwrap();
+        let kb = service
+            .create_knowledge_base("笔记".to_string(), None)
+            .unwrap();
+        let doc_path = dir.join("note.md");
+        fs::write(&doc_path, "# Note\n\nhello").unwrap();
+        service
+            .import_document(&kb.id, &doc_path.to_string_lossy())
+            .unwrap();
+        drop(service);
+
+        let service = KnowledgeBaseService::open_read_only(&dir).unwrap();
+        assert_eq!(service.list_knowledge_bases().unwrap().len(), 1);
+        assert_eq!(service.get_documents(&kb.id).unwrap().len(), 1);
+        assert!(service
+            .create_knowledge_base("新".to_string(), None)
+            .is_err());
+
+        // 损坏的数据库直接报错，不会被隔离
+        let broken = dir.join("broken");
+        fs::create_dir_all(&broken).unwrap();
+        fs::write(broken.join(DB_FILE), "not a database").unwrap();
+        assert!(KnowledgeBaseService::open_read_only(&broken).is_err());
         assert_eq!(
-            service.parse_heading("## Section"),
-            Some((2, "Section".to_string()))
+            fs::read_to_string(broken.join(DB_FILE)).unwrap(),
+            "not a database"
         );
-        assert_eq!(service.parse_heading("No heading"), None);
+        assert!(!broken.join(BACKUP_DIR).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,340 @@
+//! 大模型客户端
+//! 调用 OpenAI 兼容的 `/chat/completions` 接口，`provider` 为 `azure` 时使用 Azure OpenAI 的鉴权方式
+
+use crate::models::AiConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// 对话消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+/// 流式输出的结束方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEnd {
+    Completed,
+    Cancelled,
+}
+
+/// Azure OpenAI 默认的 `api-version`
+const DEFAULT_AZURE_API_VERSION: &str = "2024-06-01";
+
+pub struct LlmClient {
+    endpoint: String,
+    api_key: Option<String>,
+    azure: bool,
+    model: String,
+    max_tokens: u32,
+    temperature: f32,
+    agent: ureq::Agent,
+}
+
+impl LlmClient {
+    pub fn new(config: &AiConfig) -> Self {
+        let azure = config.provider == "azure";
+        let mut endpoint = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+        if azure {
+            // Azure 的 base_url 形如 https://{resource}.openai.azure.com/openai/deployments/{deployment}
+            let version = config
+                .api_version
+                .as_deref()
+                .filter(|v| !v.is_empty())
+                .unwrap_or(DEFAULT_AZURE_API_VERSION);
+            endpoint = format!("{}?api-version={}", endpoint, version);
+        }
+
+        Self {
+            endpoint,
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            azure,
+            model: config.model.clone(),
+            max_tokens: config.max_tokens,
+            temperature: config.temperature,
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(15))
+                .timeout_read(Duration::from_secs(300))
+                .build(),
+        }
+    }
+
+    fn request(&self) -> ureq::Request {
+        let request = self.agent.post(&self.endpoint);
+        match &self.api_key {
+            Some(key) if self.azure => request.set("api-key", key),
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+
+    fn body(&self, messages: &[ChatMessage], stream: bool) -> serde_json::Value {
+        json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": self.max_tokens,
+            "temperature": self.temperature,
+            "stream": stream,
+        })
+    }
+
+    /// 发送对话请求并返回完整回复
+    pub fn chat(&self, messages: &[ChatMessage]) -> Result<String, String> {
+        let response: ChatResponse = self
+            .request()
+            .send_json(self.body(messages, false))
+            .map_err(request_error)?
+            .into_json()
+            .map_err(|e| format!("AI 响应解析失败: {}", e))?;
+
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| "AI 响应中没有内容".to_string())
+    }
+
+    /// 发送最小请求以验证地址、密钥和模型是否可用
+    pub fn ping(&self) -> Result<(), String> {
+        let body = json!({
+            "model": self.model,
+            "messages": [ChatMessage::user("ping")],
+            "max_tokens": 1,
+            "stream": false,
+        });
+        let response: ChatResponse = self
+            .request()
+            .timeout(Duration::from_secs(30))
+            .send_json(body)
+            .map_err(request_error)?
+            .into_json()
+            .map_err(|e| format!("AI 响应解析失败: {}", e))?;
+        if response.choices.is_empty() {
+            return Err("AI 响应中没有内容".to_string());
+        }
+        Ok(())
+    }
+
+    /// 以服务器推送事件（SSE）流式接收回复，每收到一段文本调用一次 `on_token`
+    ///
+    /// `cancelled` 被置位后在下一个事件处停止读取。
+    pub fn chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        cancelled: &AtomicBool,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<StreamEnd, String> {
+        let response = self
+            .request()
+            .set("Accept", "text/event-stream")
+            .send_json(self.body(messages, true))
+            .map_err(request_error)?;
+
+        let reader = std::io::BufReader::new(response.into_reader());
+        let mut data = String::new();
+        let mut finished = false;
+
+        for line in reader.lines() {
+            if cancelled.load(Ordering::Relaxed) {
+                return Ok(StreamEnd::Cancelled);
+            }
+            let line = line.map_err(|e| format!("AI 响应流读取失败: {}", e))?;
+
+            // 空行表示一个事件结束
+            if !line.is_empty() {
+                if let Some(value) = line.strip_prefix("data:") {
+                    if !data.is_empty() {
+                        data.push('\n');
+                    }
+                    data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                }
+                continue;
+            }
+            if data.is_empty() {
+                continue;
+            }
+            let event = std::mem::take(&mut data);
+            if event.trim() == "[DONE]" {
+                return Ok(StreamEnd::Completed);
+            }
+
+            let chunk: serde_json::Value =
+                serde_json::from_str(&event).map_err(|e| format!("AI 响应解析失败: {}", e))?;
+            if let Some(error) = chunk.get("error") {
+                return Err(format!("AI 请求失败: {}", error));
+            }
+            let choice = &chunk["choices"][0];
+            if let Some(token) = choice["delta"]["content"].as_str() {
+                if !token.is_empty() {
+                    on_token(token);
+                }
+            }
+            finished |= choice["finish_reason"].is_string();
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            Ok(StreamEnd::Cancelled)
+        } else if finished {
+            Ok(StreamEnd::Completed)
+        } else {
+            Err("AI 响应流意外中断".to_string())
+        }
+    }
+}
+
+/// 将请求错误转换为带响应内容的提示
+fn request_error(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(code, response) => {
+            let body = response.into_string().unwrap_or_default();
+            format!("AI 请求失败 ({}): {}", code, body.trim())
+        }
+        other => format!("AI 请求失败: {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{StubResponse, StubServer};
+
+    #[test]
+    fn test_chat_against_stub_server() {
+        let server = StubServer::start(vec![
+            StubResponse::json(
+                r##"{"choices":[{"message":{"role":"assistant","content":"# 总结"}}]}"##,
+            ),
+            StubResponse::status(401, r#"{"error":"invalid key"}"#),
+        ]);
+        let client = LlmClient::new(&AiConfig {
+            base_url: format!("{}/v1", server.url),
+            api_key: Some("sk-test".to_string()),
+            model: "local-model".to_string(),
+            ..AiConfig::default()
+        });
+
+        let messages = vec![ChatMessage::system("sys"), ChatMessage::user("hi")];
+        assert_eq!(client.chat(&messages).unwrap(), "# 总结");
+        let error = client.chat(&messages).unwrap_err();
+        assert!(error.contains("401") && error.contains("invalid key"));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/chat/completions");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer sk-test"));
+        let body = requests[0].json();
+        assert_eq!(body["model"], "local-model");
+        assert_eq!(body["messages"][1]["content"], "hi");
+        assert_eq!(body["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_azure_endpoint_and_ping() {
+        let server = StubServer::start(vec![StubResponse::json(
+            r#"{"choices":[{"message":{"role":"assistant","content":"p"}}]}"#,
+        )]);
+        let client = LlmClient::new(&AiConfig {
+            provider: "azure".to_string(),
+            base_url: format!("{}/openai/deployments/gpt-4o/", server.url),
+            api_key: Some("azure-key".to_string()),
+            ..AiConfig::default()
+        });
+        client.ping().unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0].path,
+            "/openai/deployments/gpt-4o/chat/completions?api-version=2024-06-01"
+        );
+        assert_eq!(requests[0].header("api-key"), Some("azure-key"));
+        assert_eq!(requests[0].header("authorization"), None);
+        assert_eq!(requests[0].json()["max_tokens"], 1);
+    }
+
+    #[test]
+    fn test_chat_stream_tokens_and_cancel() {
+        let events = [
+            r#"{"choices":[{"delta":{"role":"assistant"}}]}"#,
+            r#"{"choices":[{"delta":{"content":"你好"}}]}"#,
+            r#"{"choices":[{"delta":{"content":"，世界"},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+        let server = StubServer::start(vec![
+            StubResponse::sse(&events),
+            StubResponse::sse(&events),
+            StubResponse::sse(&events[..2]),
+        ]);
+        let client = LlmClient::new(&AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        });
+        let messages = vec![ChatMessage::user("hi")];
+
+        let cancelled = AtomicBool::new(false);
+        let mut tokens = Vec::new();
+        let end = client
+            .chat_stream(&messages, &cancelled, |t| tokens.push(t.to_string()))
+            .unwrap();
+        assert_eq!(end, StreamEnd::Completed);
+        assert_eq!(tokens, vec!["你好", "，世界"]);
+
+        // 收到第一段文本后取消
+        let mut tokens = Vec::new();
+        let end = client
+            .chat_stream(&messages, &cancelled, |t| {
+                tokens.push(t.to_string());
+                cancelled.store(true, Ordering::Relaxed);
+            })
+            .unwrap();
+        assert_eq!(end, StreamEnd::Cancelled);
+        assert_eq!(tokens, vec!["你好"]);
+
+        // 没有结束标记的流视为中断
+        cancelled.store(false, Ordering::Relaxed);
+        let error = client
+            .chat_stream(&messages, &cancelled, |_| {})
+            .unwrap_err();
+        assert!(error.contains("中断"));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].json()["stream"], true);
+        assert_eq!(requests[0].header("accept"), Some("text/event-stream"));
+    }
+}
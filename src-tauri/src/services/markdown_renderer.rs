@@ -0,0 +1,257 @@
+//! Markdown 渲染工具
+//! 基于 pulldown-cmark 输出格式良好的 (X)HTML，供各导出器共用
+
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
+
+/// 链接类型，用于改写渲染结果中的地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Link,
+    Image,
+}
+
+/// 渲染结果中的标题
+#[derive(Debug, Clone)]
+pub struct RenderedHeading {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+}
+
+/// 渲染结果
+#[derive(Debug, Clone, Default)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub headings: Vec<RenderedHeading>,
+}
+
+impl RenderedMarkdown {
+    /// 第一个一级标题
+    pub fn title(&self) -> Option<&str> {
+        self.headings
+            .iter()
+            .find(|h| h.level == 1)
+            .map(|h| h.text.as_str())
+    }
+}
+
+/// Front matter 中的常用字段
+#[derive(Debug, Clone, Default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub weight: Option<i64>,
+}
+
+/// 拆分 YAML front matter，返回解析结果和正文
+pub fn split_front_matter(content: &str) -> (FrontMatter, &str) {
+    let mut front_matter = FrontMatter::default();
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (front_matter, content);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            return (front_matter, &rest[offset..]);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            match key.trim() {
+                "title" if !value.is_empty() => front_matter.title = Some(value.to_string()),
+                "weight" | "order" => front_matter.weight = value.parse().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    // 没有结束标记，视为普通正文
+    (FrontMatter::default(), content)
+}
+
+/// 为标题生成锚点
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// 转义 XML/HTML 文本
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 渲染 Markdown，标题自动生成锚点
+///
+/// `rewrite` 可以改写链接和图片地址，返回 `None` 表示保留原地址
+pub fn render<F>(content: &str, mut rewrite: F) -> RenderedMarkdown
+where
+    F: FnMut(LinkKind, &str) -> Option<String>,
+{
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut events: Vec<Event> = Parser::new_ext(content, options).collect();
+    let mut headings = Vec::new();
+    let mut used_anchors: HashMap<String, usize> = HashMap::new();
+
+    for idx in 0..events.len() {
+        if let Event::Start(Tag::Heading { level, id, .. }) = &events[idx] {
+            let level = heading_level(*level);
+            let existing = id.clone();
+
+            // 收集标题文本
+            let mut text = String::new();
+            for event in &events[idx + 1..] {
+                match event {
+                    Event::End(TagEnd::Heading(_)) => break,
+                    Event::Text(t) | Event::Code(t) => text.push_str(t),
+                    _ => {}
+                }
+            }
+
+            let anchor = match existing {
+                Some(id) => id.to_string(),
+                None => {
+                    let base = slugify(&text);
+                    let count = used_anchors.entry(base.clone()).or_insert(0);
+                    *count += 1;
+                    if *count == 1 {
+                        base
+                    } else {
+                        format!("{}-{}", base, count)
+                    }
+                }
+            };
+
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[idx] {
+                *id = Some(CowStr::from(anchor.clone()));
+            }
+            headings.push(RenderedHeading {
+                level,
+                text: text.trim().to_string(),
+                anchor,
+            });
+            continue;
+        }
+
+        let (kind, dest_url) = match &mut events[idx] {
+            Event::Start(Tag::Link { dest_url, .. }) => (LinkKind::Link, dest_url),
+            Event::Start(Tag::Image { dest_url, .. }) => (LinkKind::Image, dest_url),
+            _ => continue,
+        };
+        if let Some(url) = rewrite(kind, dest_url) {
+            *dest_url = CowStr::from(url);
+        }
+    }
+
+    let mut output = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(
+        &mut output,
+        events.into_iter().map(|event| match event {
+            Event::Html(raw) => Event::Html(close_void_elements(&raw).into()),
+            Event::InlineHtml(raw) => Event::InlineHtml(close_void_elements(&raw).into()),
+            other => other,
+        }),
+    );
+
+    RenderedMarkdown {
+        html: output,
+        headings,
+    }
+}
+
+/// 将原始 HTML 中的空元素改为自闭合形式，保证输出可作为 XHTML 解析
+fn close_void_elements(raw: &str) -> String {
+    const VOID_ELEMENTS: [&str; 8] = ["br", "hr", "img", "input", "meta", "link", "col", "wbr"];
+
+    let mut output = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..=end];
+        let name: String = tag[1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if VOID_ELEMENTS.contains(&name.as_str()) && !tag.ends_with("/>") {
+            output.push_str(&tag[..tag.len() - 1]);
+            output.push_str(" />");
+        } else {
+            output.push_str(tag);
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// 判断地址是否为本地相对路径
+pub fn is_local_url(url: &str) -> bool {
+    !(url.is_empty()
+        || url.starts_with('#')
+        || url.starts_with('/')
+        || url.starts_with("data:")
+        || url.starts_with("mailto:")
+        || url.contains("://"))
+}
+
+/// 提取 Markdown 的纯文本内容
+pub fn plain_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    for event in Parser::new_ext(content, Options::ENABLE_TABLES) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) if !text.ends_with(' ') => {
+                text.push(' ')
+            }
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
@@ -0,0 +1,985 @@
+//! 本地 MCP（Model Context Protocol）服务
+//! 通过 JSON-RPC 向外部 AI 代理提供工作区搜索、读取文件和知识库检索工具，
+//! 支持标准输入输出（`--mcp` 启动参数）和只监听本机的 HTTP 两种传输方式；
+//! 所有工具都是只读的，且只有设置中启用的工具可以调用
+
+use crate::commands::{search_recursive, SearchResult};
+use crate::models::{McpSettings, McpToolInfo};
+use crate::services::knowledge_base_service::KnowledgeBaseService;
+use crate::services::search_service::{self, SearchService};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Response, Server};
+use uuid::Uuid;
+
+const SETTINGS_FILE: &str = "mcp_settings.json";
+/// 客户端未声明或声明了不支持的版本时使用的协议版本
+const PROTOCOL_VERSION: &str = "2025-03-26";
+const SUPPORTED_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+/// `read_file` 可读取的最大文件大小
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+/// HTTP 请求体的大小上限
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// `search_workspace` 返回的文件数上限
+const MAX_SEARCH_FILES: usize = 50;
+/// `search_knowledge_base` 返回的文本块数上限
+const MAX_SEARCH_CHUNKS: usize = 50;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// 工具定义
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    input_schema: fn() -> Value,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "search_workspace",
+        description: "在工作区的 Markdown 文件中搜索文字（不区分大小写），返回匹配的文件和行",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "要搜索的文字" },
+                    "root": { "type": "string", "description": "只搜索该目录，须位于工作区内" }
+                },
+                "required": ["query"]
+            })
+        },
+    },
+    Tool {
+        name: "read_file",
+        description: "读取工作区内的文本文件",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "文件的绝对路径" }
+                },
+                "required": ["path"]
+            })
+        },
+    },
+    Tool {
+        name: "list_knowledge_bases",
+        description: "列出所有知识库及其文档数量",
+        input_schema: || json!({ "type": "object", "properties": {} }),
+    },
+    Tool {
+        name: "search_knowledge_base",
+        description: "在指定知识库中全文检索文本块，返回内容、来源路径和行号",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "kbId": { "type": "string", "description": "知识库 ID" },
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_CHUNKS }
+                },
+                "required": ["kbId", "query"]
+            })
+        },
+    },
+];
+
+/// 列出所有工具及其在当前设置下是否启用
+pub fn tool_infos(settings: &McpSettings) -> Vec<McpToolInfo> {
+    TOOLS
+        .iter()
+        .map(|tool| McpToolInfo {
+            name: tool.name.to_string(),
+            description: tool.description.to_string(),
+            enabled: settings.enabled_tools.iter().any(|t| t == tool.name),
+        })
+        .collect()
+}
+
+/// MCP 设置存储，保存在 mcp_settings.json
+pub struct McpSettingsStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl McpSettingsStore {
+    /// 创建服务实例
+    pub fn new() -> Result<Self, String> {
+        let storage_dir = dirs::data_dir()
+            .ok_or("无法获取数据目录")?
+            .join("bun-codeview");
+
+        Self::open(&storage_dir)
+    }
+
+    /// 使用指定目录下的设置文件
+    pub fn open(storage_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(storage_dir).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path: storage_dir.join(SETTINGS_FILE),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// 读取设置，文件不存在时返回默认设置；还没有访问令牌时生成并保存
+    pub fn get(&self) -> Result<McpSettings, String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "MCP 设置锁已损坏".to_string())?;
+        let mut settings = self.read()?;
+        if settings.token.is_empty() {
+            settings.token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+            self.write(&settings)?;
+        }
+        Ok(settings)
+    }
+
+    /// 校验并保存设置，访问令牌保持不变
+    pub fn save(&self, mut settings: McpSettings) -> Result<McpSettings, String> {
+        if let Some(unknown) = settings
+            .enabled_tools
+            .iter()
+            .find(|name| !TOOLS.iter().any(|t| t.name == name.as_str()))
+        {
+            return Err(format!("未知的 MCP 工具: {}", unknown));
+        }
+        if settings.port == 0 {
+            return Err("端口不能为 0".to_string());
+        }
+        let mut seen = HashSet::new();
+        settings
+            .enabled_tools
+            .retain(|name| seen.insert(name.clone()));
+        settings
+            .workspace_roots
+            .retain(|root| !root.trim().is_empty());
+
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "MCP 设置锁已损坏".to_string())?;
+        settings.token = self.read()?.token;
+        self.write(&settings)?;
+        Ok(settings)
+    }
+
+    fn read(&self) -> Result<McpSettings, String> {
+        if !self.path.exists() {
+            return Ok(McpSettings::default());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("MCP 设置解析失败: {}", e))
+    }
+
+    fn write(&self, settings: &McpSettings) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+}
+
+/// JSON-RPC 错误
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// 处理 MCP 消息，与传输方式无关
+pub struct McpServer<'a> {
+    kb_service: &'a KnowledgeBaseService,
+    settings: McpSettings,
+}
+
+impl<'a> McpServer<'a> {
+    pub fn new(kb_service: &'a KnowledgeBaseService, settings: McpSettings) -> Self {
+        Self {
+            kb_service,
+            settings,
+        }
+    }
+
+    /// 处理一条 JSON-RPC 消息（或批量消息），通知不需要回复时返回 `None`
+    pub fn handle_message(&self, message: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(message) {
+            Ok(Value::Array(batch)) if !batch.is_empty() => {
+                let responses: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|item| self.handle_value(item))
+                    .collect();
+                if responses.is_empty() {
+                    return None;
+                }
+                Value::Array(responses)
+            }
+            Ok(value) => self.handle_value(value)?,
+            Err(e) => error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, format!("JSON 解析失败: {}", e)),
+            ),
+        };
+        Some(response.to_string())
+    }
+
+    fn handle_value(&self, value: Value) -> Option<Value> {
+        let id = value.get("id").cloned();
+        let Some(method) = value.get("method").and_then(Value::as_str) else {
+            // 客户端发来的响应（本服务不发起请求）直接忽略
+            if value.get("result").is_some() || value.get("error").is_some() {
+                return None;
+            }
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "缺少 method"),
+            ));
+        };
+        let params = value.get("params").cloned().unwrap_or(Value::Null);
+        let outcome = self.handle_request(method, &params);
+
+        // 没有 id 的是通知，不回复
+        let id = id?;
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn handle_request(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|v| SUPPORTED_VERSIONS.contains(v))
+                    .unwrap_or(PROTOCOL_VERSION);
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": {
+                        "name": "md-notebook",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({
+                "tools": self
+                    .enabled_tools()
+                    .map(|tool| json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "inputSchema": (tool.input_schema)(),
+                        "annotations": { "readOnlyHint": true }
+                    }))
+                    .collect::<Vec<_>>()
+            })),
+            "tools/call" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, "缺少工具名称"))?;
+                let tool = self
+                    .enabled_tools()
+                    .find(|tool| tool.name == name)
+                    .ok_or_else(|| {
+                        RpcError::new(INVALID_PARAMS, format!("工具不存在或未启用: {}", name))
+                    })?;
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+                // 工具执行失败作为结果返回，便于模型看到错误并调整
+                Ok(match self.call_tool(tool.name, &arguments) {
+                    Ok(text) => json!({
+                        "content": [{ "type": "text", "text": text }],
+                        "isError": false
+                    }),
+                    Err(error) => json!({
+                        "content": [{ "type": "text", "text": error }],
+                        "isError": true
+                    }),
+                })
+            }
+            method if method.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("不支持的方法: {}", method),
+            )),
+        }
+    }
+
+    fn enabled_tools(&self) -> impl Iterator<Item = &'static Tool> + '_ {
+        TOOLS
+            .iter()
+            .filter(|tool| self.settings.enabled_tools.iter().any(|t| t == tool.name))
+    }
+
+    fn call_tool(&self, name: &str, arguments: &Value) -> Result<String, String> {
+        match name {
+            "search_workspace" => {
+                let query = string_arg(arguments, "query")?.to_lowercase();
+                if query.trim().is_empty() {
+                    return Err("搜索内容不能为空".to_string());
+                }
+                let roots = match arguments.get("root").and_then(Value::as_str) {
+                    Some(root) => vec![self.resolve_path(root)?],
+                    None => self.workspace_roots()?,
+                };
+                let mut results: Vec<SearchResult> = Vec::new();
+                for root in roots {
+                    search_workspace_dir(&root, &query, &mut results)?;
+                    if results.len() >= MAX_SEARCH_FILES {
+                        break;
+                    }
+                }
+                results.truncate(MAX_SEARCH_FILES);
+                to_json(&results)
+            }
+            "read_file" => {
+                let path = self.resolve_path(string_arg(arguments, "path")?)?;
+                let meta = fs::metadata(&path).map_err(|e| e.to_string())?;
+                if !meta.is_file() {
+                    return Err(format!("不是文件: {}", path.display()));
+                }
+                if meta.len() > MAX_READ_BYTES {
+                    return Err(format!("文件超过 {} 字节，无法读取", MAX_READ_BYTES));
+                }
+                fs::read_to_string(&path).map_err(|e| e.to_string())
+            }
+            "list_knowledge_bases" => {
+                let kbs: Vec<Value> = self
+                    .kb_service
+                    .list_knowledge_bases()?
+                    .into_iter()
+                    .map(|kb| {
+                        json!({
+                            "id": kb.id,
+                            "name": kb.name,
+                            "description": kb.description,
+                            "documentCount": kb.document_count,
+                            "tags": kb.tags,
+                        })
+                    })
+                    .collect();
+                to_json(&kbs)
+            }
+            "search_knowledge_base" => {
+                let kb_id = string_arg(arguments, "kbId")?;
+                let query = string_arg(arguments, "query")?;
+                let limit = arguments
+                    .get("limit")
+                    .and_then(Value::as_u64)
+                    .map_or(search_service::DEFAULT_LIMIT, |n| n as usize)
+                    .clamp(1, MAX_SEARCH_CHUNKS);
+                self.kb_service
+                    .get_knowledge_base(kb_id)?
+                    .ok_or("知识库不存在")?;
+                let documents = self.kb_service.get_documents(kb_id)?;
+                let chunks: Vec<Value> = SearchService::search(&documents, query, limit)
+                    .into_iter()
+                    .map(|chunk| {
+                        json!({
+                            "documentTitle": chunk.document_title,
+                            "path": chunk.path,
+                            "startLine": chunk.start_line,
+                            "endLine": chunk.end_line,
+                            "page": chunk.page,
+                            "headings": chunk.headings,
+                            "content": chunk.content,
+                            "score": chunk.score,
+                        })
+                    })
+                    .collect();
+                to_json(&chunks)
+            }
+            _ => Err(format!("未知的工具: {}", name)),
+        }
+    }
+
+    /// 允许访问的目录，未在设置中指定时文件工具不可用
+    fn workspace_roots(&self) -> Result<Vec<PathBuf>, String> {
+        if self.settings.workspace_roots.is_empty() {
+            return Err("未设置允许访问的工作区目录".to_string());
+        }
+        Ok(self
+            .settings
+            .workspace_roots
+            .iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .filter(|root| root.is_dir())
+            .collect())
+    }
+
+    /// 解析路径（含符号链接和 `..`），不在允许访问的目录内时报错
+    fn resolve_path(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved =
+            fs::canonicalize(path).map_err(|e| format!("路径无法访问 {}: {}", path, e))?;
+        if self
+            .workspace_roots()?
+            .iter()
+            .any(|root| resolved.starts_with(root))
+        {
+            Ok(resolved)
+        } else {
+            Err(format!("路径不在允许访问的工作区内: {}", path))
+        }
+    }
+}
+
+/// 在目录中搜索 Markdown 文件，不跟随符号链接，避免经由链接搜索到工作区之外
+fn search_workspace_dir(
+    dir: &Path,
+    query: &str,
+    results: &mut Vec<SearchResult>,
+) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.')
+            || matches!(name.as_str(), "node_modules" | "target" | "dist" | "build")
+        {
+            continue;
+        }
+
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+        if file_type.is_dir() {
+            search_workspace_dir(&entry.path(), query, results)?;
+        } else if file_type.is_file() {
+            search_recursive(&entry.path(), query, results)?;
+        }
+        if results.len() >= MAX_SEARCH_FILES {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn string_arg<'v>(arguments: &'v Value, name: &str) -> Result<&'v str, String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("缺少参数 {}", name))
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message }
+    })
+}
+
+/// 标准输入输出传输：每行一条 JSON-RPC 消息，直到输入结束
+pub fn serve_stdio(
+    handler: impl Fn(&str) -> Option<String>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), String> {
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handler(&line) {
+            writeln!(output, "{}", response).map_err(|e| e.to_string())?;
+            output.flush().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 运行中的 HTTP 服务，`stop` 或被丢弃时停止
+pub struct McpHttpServer {
+    pub url: String,
+    server: Arc<Server>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl McpHttpServer {
+    /// 在 `127.0.0.1:port` 启动服务（端口为 0 时自动分配），`POST /mcp` 接收 JSON-RPC 消息，
+    /// 请求须携带 `token` 作为 Bearer 令牌
+    pub fn start(
+        port: u16,
+        token: String,
+        handler: impl Fn(&str) -> Option<String> + Send + 'static,
+    ) -> Result<Self, String> {
+        let server =
+            Server::http(("127.0.0.1", port)).map_err(|e| format!("MCP 服务启动失败: {}", e))?;
+        let addr = server.server_addr().to_ip().ok_or("MCP 服务地址无效")?;
+        let server = Arc::new(server);
+
+        let incoming = server.clone();
+        let handle = std::thread::spawn(move || {
+            for mut request in incoming.incoming_requests() {
+                let response = http_response(&mut request, &token, &handler);
+                if let Err(e) = request.respond(response) {
+                    log::warn!("MCP 响应发送失败: {}", e);
+                }
+            }
+        });
+
+        Ok(Self {
+            url: format!("http://{}/mcp", addr),
+            server,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for McpHttpServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// HTTP 服务状态，作为 Tauri 托管状态保存运行中的服务
+#[derive(Default)]
+pub struct McpHttpState {
+    server: Mutex<Option<McpHttpServer>>,
+}
+
+impl McpHttpState {
+    /// 启动服务并返回地址，已在运行时先停止旧的服务
+    pub fn start(
+        &self,
+        port: u16,
+        token: String,
+        handler: impl Fn(&str) -> Option<String> + Send + 'static,
+    ) -> Result<String, String> {
+        let mut current = self
+            .server
+            .lock()
+            .map_err(|_| "MCP 服务锁已损坏".to_string())?;
+        if let Some(previous) = current.take() {
+            previous.stop();
+        }
+        let server = McpHttpServer::start(port, token, handler)?;
+        let url = server.url.clone();
+        *current = Some(server);
+        Ok(url)
+    }
+
+    /// 停止服务，未在运行时返回 false
+    pub fn stop(&self) -> bool {
+        match self.server.lock().ok().and_then(|mut s| s.take()) {
+            Some(server) => {
+                server.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 运行中时返回服务地址
+    pub fn url(&self) -> Option<String> {
+        self.server
+            .lock()
+            .ok()
+            .and_then(|s| s.as_ref().map(|server| server.url.clone()))
+    }
+}
+
+fn http_response(
+    request: &mut tiny_http::Request,
+    token: &str,
+    handler: &impl Fn(&str) -> Option<String>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let text = |status: u16, body: &str| Response::from_string(body).with_status_code(status);
+
+    if request.url().split('?').next() != Some("/mcp") {
+        return text(404, "Not Found");
+    }
+    if *request.method() != Method::Post {
+        return text(405, "Method Not Allowed");
+    }
+    // 拒绝来自非本机网页的请求，防止 DNS 重绑定
+    let origin = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Origin"))
+        .map(|h| h.value.as_str().to_string());
+    if origin.is_some_and(|origin| !is_local_origin(&origin)) {
+        return text(403, "Forbidden");
+    }
+    // 本机的其他进程也可以连接端口，只接受携带访问令牌的请求
+    let authorized = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|value| !token.is_empty() && value.trim() == token);
+    if !authorized {
+        return text(401, "Unauthorized").with_header(
+            Header::from_bytes(&b"WWW-Authenticate"[..], &b"Bearer"[..]).expect("valid header"),
+        );
+    }
+    if request.body_length().unwrap_or(0) > MAX_BODY_BYTES {
+        return text(413, "Payload Too Large");
+    }
+
+    let mut body = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_BYTES as u64)
+        .read_to_string(&mut body)
+    {
+        return text(400, &e.to_string());
+    }
+    match handler(&body) {
+        Some(response) => Response::from_string(response).with_header(
+            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                .expect("valid header"),
+        ),
+        None => text(202, ""),
+    }
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin
+        .split("://")
+        .nth(1)
+        .unwrap_or(origin)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn setup() -> (PathBuf, KnowledgeBaseService, McpSettings) {
+        let dir = std::env::temp_dir().join(format!("mcp-test-{}", Uuid::new_v4()));
+        let workspace = dir.join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        fs::write(
+            workspace.join("rust.md"),
+            "# Rust\n\n借用检查器阻止数据竞争。",
+        )
+        .unwrap();
+        fs::write(dir.join("secret.md"), "私密").unwrap();
+
+        let kb_service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = kb_service
+            .create_knowledge_base("笔记".to_string(), None)
+            .unwrap();
+        kb_service
+            .import_document(&kb.id, &workspace.join("rust.md").to_string_lossy())
+            .unwrap();
+
+        let settings = McpSettings {
+            enabled_tools: TOOLS.iter().map(|tool| tool.name.to_string()).collect(),
+            workspace_roots: vec![workspace.to_string_lossy().to_string()],
+            ..McpSettings::default()
+        };
+        (dir, kb_service, settings)
+    }
+
+    fn call(server: &McpServer, id: u64, method: &str, params: Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = server.handle_message(&message.to_string()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn tool_text(response: &Value) -> (&str, bool) {
+        let result = &response["result"];
+        (
+            result["content"][0]["text"].as_str().unwrap(),
+            result["isError"].as_bool().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_enabled_tools_and_workspace_access() {
+        let (dir, kb_service, settings) = setup();
+        let workspace = dir.join("workspace");
+        let server = McpServer::new(&kb_service, settings.clone());
+
+        let init = call(
+            &server,
+            1,
+            "initialize",
+            json!({ "protocolVersion": "2024-11-05", "capabilities": {} }),
+        );
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(server
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .is_none());
+
+        let tools = call(&server, 2, "tools/list", json!({}));
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "search_workspace",
+                "read_file",
+                "list_knowledge_bases",
+                "search_knowledge_base"
+            ]
+        );
+
+        let found = call(
+            &server,
+            3,
+            "tools/call",
+            json!({ "name": "search_workspace", "arguments": { "query": "借用" } }),
+        );
+        let (text, is_error) = tool_text(&found);
+        assert!(!is_error);
+        assert!(text.contains("rust.md"));
+
+        let note = workspace.join("rust.md").to_string_lossy().to_string();
+        let read = call(
+            &server,
+            4,
+            "tools/call",
+            json!({ "name": "read_file", "arguments": { "path": note } }),
+        );
+        assert_eq!(
+            tool_text(&read),
+            ("# Rust\n\n借用检查器阻止数据竞争。", false)
+        );
+
+        // 工作区之外的文件（包括经由 `..`）不可读取
+        let outside = workspace.join("..").join("secret.md");
+        let denied = call(
+            &server,
+            5,
+            "tools/call",
+            json!({ "name": "read_file", "arguments": { "path": outside } }),
+        );
+        let (text, is_error) = tool_text(&denied);
+        assert!(is_error);
+        assert!(text.contains("不在允许访问的工作区内"));
+
+        let kbs = call(
+            &server,
+            6,
+            "tools/call",
+            json!({ "name": "list_knowledge_bases", "arguments": {} }),
+        );
+        let kbs: Value = serde_json::from_str(tool_text(&kbs).0).unwrap();
+        assert_eq!(kbs[0]["name"], "笔记");
+        assert_eq!(kbs[0]["documentCount"], 1);
+
+        let chunks = call(
+            &server,
+            7,
+            "tools/call",
+            json!({
+                "name": "search_knowledge_base",
+                "arguments": { "kbId": kbs[0]["id"], "query": "数据竞争", "limit": 3 }
+            }),
+        );
+        let chunks: Value = serde_json::from_str(tool_text(&chunks).0).unwrap();
+        assert!(chunks[0]["content"]
+            .as_str()
+            .unwrap()
+            .contains("借用检查器"));
+
+        // 未启用的工具视为不存在
+        let mut disabled = settings.clone();
+        disabled.enabled_tools.retain(|name| name != "read_file");
+        let server = McpServer::new(&kb_service, disabled);
+        let rejected = call(
+            &server,
+            8,
+            "tools/call",
+            json!({ "name": "read_file", "arguments": { "path": note } }),
+        );
+        assert_eq!(rejected["error"]["code"], INVALID_PARAMS);
+        let rejected = call(
+            &server,
+            9,
+            "tools/call",
+            json!({ "name": "write_file", "arguments": { "path": note, "content": "改写" } }),
+        );
+        assert_eq!(rejected["error"]["code"], INVALID_PARAMS);
+
+        // 默认不启用任何工具；未设置工作区时文件工具不可用
+        let server = McpServer::new(&kb_service, McpSettings::default());
+        let tools = call(&server, 10, "tools/list", json!({}));
+        assert!(tools["result"]["tools"].as_array().unwrap().is_empty());
+        let no_roots = McpSettings {
+            workspace_roots: Vec::new(),
+            ..settings.clone()
+        };
+        let server = McpServer::new(&kb_service, no_roots);
+        let found = call(
+            &server,
+            11,
+            "tools/call",
+            json!({ "name": "search_workspace", "arguments": { "query": "借用" } }),
+        );
+        let (text, is_error) = tool_text(&found);
+        assert!(is_error);
+        assert!(text.contains("未设置允许访问的工作区目录"));
+
+        assert_eq!(
+            call(&server, 12, "resources/list", json!({}))["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        let parse_error: Value =
+            serde_json::from_str(&server.handle_message("{").unwrap()).unwrap();
+        assert_eq!(parse_error["error"]["code"], PARSE_ERROR);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_search_skips_symlinks_out_of_workspace() {
+        let (dir, kb_service, settings) = setup();
+        let workspace = dir.join("workspace");
+        std::os::unix::fs::symlink(&dir, workspace.join("outside")).unwrap();
+        std::os::unix::fs::symlink(dir.join("secret.md"), workspace.join("leak.md")).unwrap();
+        let server = McpServer::new(&kb_service, settings);
+
+        let found = call(
+            &server,
+            1,
+            "tools/call",
+            json!({ "name": "search_workspace", "arguments": { "query": "私密" } }),
+        );
+        assert_eq!(tool_text(&found), ("[]", false));
+
+        let found = call(
+            &server,
+            2,
+            "tools/call",
+            json!({ "name": "search_workspace", "arguments": { "query": "借用" } }),
+        );
+        let results: Vec<Value> = serde_json::from_str(tool_text(&found).0).unwrap();
+        assert_eq!(results.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stdio_and_http_transports() {
+        let (dir, kb_service, settings) = setup();
+        let server = McpServer::new(&kb_service, settings.clone());
+
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+            "\n\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"[{"jsonrpc":"2.0","id":2,"method":"ping"},{"jsonrpc":"2.0","id":3,"method":"ping"}]"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+        serve_stdio(|m| server.handle_message(m), input.as_bytes(), &mut output).unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], json!({ "jsonrpc": "2.0", "id": 1, "result": {} }));
+        assert_eq!(lines[1].as_array().unwrap().len(), 2);
+
+        drop(server);
+        // 访问令牌在首次读取设置时生成，保存设置时保持不变
+        let store = McpSettingsStore::open(&dir.join("settings")).unwrap();
+        let token = store.get().unwrap().token;
+        assert_eq!(token.len(), 64);
+        let saved = store
+            .save(McpSettings {
+                token: String::new(),
+                ..settings.clone()
+            })
+            .unwrap();
+        assert_eq!(saved.token, token);
+        assert_eq!(store.get().unwrap().token, token);
+
+        let kb_service = Arc::new(kb_service);
+        let http = McpHttpServer::start(0, token.clone(), move |message| {
+            McpServer::new(&kb_service, settings.clone()).handle_message(message)
+        })
+        .unwrap();
+        assert!(http.url.starts_with("http://127.0.0.1:"));
+        let bearer = format!("Bearer {}", token);
+        let post = || ureq::post(&http.url).set("Authorization", &bearer);
+
+        let response: Value = post()
+            .send_string(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(response["result"]["tools"].as_array().unwrap().len(), 4);
+
+        let accepted = post()
+            .send_string(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .unwrap();
+        assert_eq!(accepted.status(), 202);
+
+        // 缺少令牌或令牌错误时拒绝
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        assert!(matches!(
+            ureq::post(&http.url).send_string(ping),
+            Err(ureq::Error::Status(401, _))
+        ));
+        assert!(matches!(
+            ureq::post(&http.url)
+                .set("Authorization", "Bearer wrong")
+                .send_string(ping),
+            Err(ureq::Error::Status(401, _))
+        ));
+
+        let forbidden = post()
+            .set("Origin", "http://evil.example")
+            .send_string(ping);
+        assert!(matches!(forbidden, Err(ureq::Error::Status(403, _))));
+        assert!(post()
+            .set("Origin", "http://localhost:1420")
+            .send_string(ping)
+            .is_ok());
+        assert!(matches!(
+            ureq::get(&http.url).call(),
+            Err(ureq::Error::Status(405, _))
+        ));
+
+        http.stop();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,2 +1,27 @@
+pub mod ai_settings_service;
+pub mod auto_sync;
+pub mod bundle_service;
+pub mod chat_service;
+pub mod chunking;
+pub mod embedding_service;
+pub mod epub_service;
+pub mod export_service;
+pub mod extractors;
+pub mod generate_service;
 pub mod history_service;
+pub mod kb_schema;
+pub mod keyword_service;
 pub mod knowledge_base_service;
+pub mod llm_client;
+pub mod markdown_renderer;
+pub mod mcp_server;
+pub mod pptx_service;
+pub mod prompt_template_service;
+pub mod report_service;
+pub mod search_service;
+pub mod secret_store;
+pub mod site_service;
+pub mod slides_service;
+
+#[cfg(test)]
+pub(crate) mod test_support;
@@ -0,0 +1,938 @@
+//! PPTX 导出服务
+//! 将 Markdown 大纲转换为 PowerPoint 演示文稿：标题作为幻灯片标题，列表作为要点，图片单独成页，
+//! `Note:` / `Notes:` 之后的内容写入演讲者备注
+
+use crate::models::GenerateResult;
+use crate::services::markdown_renderer::{self, escape_xml, is_local_url};
+use pulldown_cmark::{Event, Parser};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// 16:9 幻灯片尺寸（EMU）
+const SLIDE_WIDTH: i64 = 12_192_000;
+const SLIDE_HEIGHT: i64 = 6_858_000;
+const MARGIN: i64 = 457_200;
+const TITLE_HEIGHT: i64 = 1_143_000;
+
+/// 幻灯片版式
+#[derive(Debug, Clone, PartialEq)]
+enum SlideKind {
+    /// 封面或章节页
+    Title { subtitle: Option<String> },
+    /// 标题加要点
+    Content { bullets: Vec<(u8, String)> },
+    /// 图片页
+    Picture { image: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PptSlide {
+    title: String,
+    kind: SlideKind,
+    /// 演讲者备注
+    notes: Option<String>,
+}
+
+/// 嵌入的图片
+struct Media {
+    file_name: String,
+    data: Vec<u8>,
+    width: i64,
+    height: i64,
+}
+
+pub struct PptxService;
+
+impl PptxService {
+    /// 将 Markdown 文件导出为 PPTX
+    pub fn export_file(source_path: &Path, output_path: &Path) -> Result<(), String> {
+        let content = fs::read_to_string(source_path).map_err(|e| e.to_string())?;
+        let base_dir = source_path.parent().unwrap_or(Path::new("."));
+        Self::write_presentation(&content, base_dir, output_path)
+    }
+
+    /// 将生成的 PPT 大纲保存为 PPTX，返回指向新文件的结果
+    pub fn save_generate_result(
+        result: &GenerateResult,
+        output_path: Option<&Path>,
+    ) -> Result<GenerateResult, String> {
+        let source = Path::new(&result.output_path);
+        let output = match output_path {
+            Some(path) => path.to_path_buf(),
+            None if result.output_path.is_empty() => return Err("未指定 PPTX 输出路径".to_string()),
+            None => source.with_extension("pptx"),
+        };
+        let base_dir = source.parent().unwrap_or(Path::new("."));
+        Self::write_presentation(&result.content, base_dir, &output)?;
+
+        Ok(GenerateResult {
+            success: true,
+            output_path: output.to_string_lossy().to_string(),
+            content: result.content.clone(),
+        })
+    }
+
+    /// 写出演示文稿
+    pub fn write_presentation(
+        content: &str,
+        base_dir: &Path,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let slides = Self::parse_outline(content, base_dir);
+        if slides.is_empty() {
+            return Err("大纲中没有可生成的幻灯片".to_string());
+        }
+
+        let file = fs::File::create(output_path).map_err(|e| e.to_string())?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default();
+
+        let mut media = Vec::new();
+        let mut slide_xml = Vec::new();
+        for slide in &slides {
+            let (xml, image) = match &slide.kind {
+                SlideKind::Title { subtitle } => {
+                    (title_slide_xml(&slide.title, subtitle.as_deref()), None)
+                }
+                SlideKind::Content { bullets } => (content_slide_xml(&slide.title, bullets), None),
+                SlideKind::Picture { image } => {
+                    let data = fs::read(image).map_err(|e| e.to_string())?;
+                    let extension = image
+                        .extension()
+                        .map(|e| e.to_string_lossy().to_lowercase())
+                        .unwrap_or_default();
+                    let (width, height) = image_size(&data).unwrap_or((4, 3));
+                    let item = Media {
+                        file_name: format!("image{}.{}", media.len() + 1, extension),
+                        data,
+                        width,
+                        height,
+                    };
+                    let xml = picture_slide_xml(&slide.title, &item);
+                    media.push(item);
+                    (xml, media.last().map(|m| m.file_name.clone()))
+                }
+            };
+            slide_xml.push((xml, image));
+        }
+        // 有备注的幻灯片序号（从 1 开始），备注页与幻灯片同号
+        let noted: Vec<usize> = slides
+            .iter()
+            .enumerate()
+            .filter(|(_, slide)| slide.notes.is_some())
+            .map(|(idx, _)| idx + 1)
+            .collect();
+        let has_notes = !noted.is_empty();
+
+        write_entry(
+            &mut zip,
+            "[Content_Types].xml",
+            options,
+            &content_types_xml(slides.len(), &noted),
+        )?;
+        write_entry(&mut zip, "_rels/.rels", options, ROOT_RELS)?;
+        write_entry(
+            &mut zip,
+            "ppt/presentation.xml",
+            options,
+            &presentation_xml(slides.len(), has_notes),
+        )?;
+        write_entry(
+            &mut zip,
+            "ppt/_rels/presentation.xml.rels",
+            options,
+            &presentation_rels(slides.len(), has_notes),
+        )?;
+        write_entry(
+            &mut zip,
+            "ppt/slideMasters/slideMaster1.xml",
+            options,
+            SLIDE_MASTER,
+        )?;
+        write_entry(
+            &mut zip,
+            "ppt/slideMasters/_rels/slideMaster1.xml.rels",
+            options,
+            SLIDE_MASTER_RELS,
+        )?;
+        write_entry(
+            &mut zip,
+            "ppt/slideLayouts/slideLayout1.xml",
+            options,
+            SLIDE_LAYOUT,
+        )?;
+        write_entry(
+            &mut zip,
+            "ppt/slideLayouts/_rels/slideLayout1.xml.rels",
+            options,
+            SLIDE_LAYOUT_RELS,
+        )?;
+        write_entry(&mut zip, "ppt/theme/theme1.xml", options, THEME)?;
+        if has_notes {
+            write_entry(
+                &mut zip,
+                "ppt/notesMasters/notesMaster1.xml",
+                options,
+                NOTES_MASTER,
+            )?;
+            write_entry(
+                &mut zip,
+                "ppt/notesMasters/_rels/notesMaster1.xml.rels",
+                options,
+                NOTES_MASTER_RELS,
+            )?;
+            write_entry(&mut zip, "ppt/theme/theme2.xml", options, THEME)?;
+        }
+
+        for (idx, ((xml, image), slide)) in slide_xml.iter().zip(&slides).enumerate() {
+            write_entry(
+                &mut zip,
+                &format!("ppt/slides/slide{}.xml", idx + 1),
+                options,
+                xml,
+            )?;
+            write_entry(
+                &mut zip,
+                &format!("ppt/slides/_rels/slide{}.xml.rels", idx + 1),
+                options,
+                &slide_rels(image.as_deref(), slide.notes.is_some().then_some(idx + 1)),
+            )?;
+            if let Some(notes) = &slide.notes {
+                write_entry(
+                    &mut zip,
+                    &format!("ppt/notesSlides/notesSlide{}.xml", idx + 1),
+                    options,
+                    &notes_slide_xml(notes),
+                )?;
+                write_entry(
+                    &mut zip,
+                    &format!("ppt/notesSlides/_rels/notesSlide{}.xml.rels", idx + 1),
+                    options,
+                    &notes_slide_rels(idx + 1),
+                )?;
+            }
+        }
+
+        for item in &media {
+            zip.start_file(format!("ppt/media/{}", item.file_name), options)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(&item.data).map_err(|e| e.to_string())?;
+        }
+
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 解析 Markdown 大纲
+    ///
+    /// 一级标题生成封面/章节页，二级及以下标题开始新的内容页，
+    /// 列表和段落成为要点，单独成行的图片生成图片页；
+    /// `Note:` / `Notes:` 开始的备注持续到下一个 `---` 或标题，归入当前幻灯片
+    fn parse_outline(content: &str, base_dir: &Path) -> Vec<PptSlide> {
+        let (_, body) = markdown_renderer::split_front_matter(content);
+        let mut slides: Vec<PptSlide> = Vec::new();
+        let mut current: Option<PptSlide> = None;
+        let mut notes: Option<Vec<&str>> = None;
+        let mut in_fence = false;
+
+        for line in body.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                if let Some(notes) = notes.as_mut() {
+                    notes.push(line);
+                }
+                continue;
+            }
+            if !in_fence && trimmed == "---" {
+                attach_notes(&mut slides, &mut current, notes.take());
+                continue;
+            }
+            if let Some(lines) = notes.as_mut() {
+                if in_fence || parse_heading(trimmed).is_none() {
+                    lines.push(line);
+                    continue;
+                }
+                attach_notes(&mut slides, &mut current, notes.take());
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+            if !in_fence {
+                if let Some(rest) = trimmed
+                    .strip_prefix("Note:")
+                    .or_else(|| trimmed.strip_prefix("Notes:"))
+                {
+                    notes = Some(vec![rest.trim_start()]);
+                    continue;
+                }
+            }
+
+            if !in_fence {
+                if let Some((level, text)) = parse_heading(trimmed) {
+                    slides.extend(current.take());
+                    current = Some(PptSlide {
+                        title: text,
+                        kind: if level == 1 {
+                            SlideKind::Title { subtitle: None }
+                        } else {
+                            SlideKind::Content {
+                                bullets: Vec::new(),
+                            }
+                        },
+                        notes: None,
+                    });
+                    continue;
+                }
+
+                if let Some((alt, url)) = parse_image(trimmed) {
+                    let image = base_dir.join(url);
+                    if is_local_url(url) && image.is_file() && image_extension_supported(&image) {
+                        let title = match &current {
+                            Some(slide) if alt.is_empty() => slide.title.clone(),
+                            _ => alt,
+                        };
+                        // 空的内容页由图片页代替，备注随之转到图片页
+                        let mut picture_notes = None;
+                        if let Some(slide) = current.take() {
+                            let empty = matches!(&slide.kind, SlideKind::Content { bullets } if bullets.is_empty());
+                            if empty {
+                                picture_notes = slide.notes;
+                            } else {
+                                slides.push(slide);
+                            }
+                        }
+                        slides.push(PptSlide {
+                            title,
+                            kind: SlideKind::Picture { image },
+                            notes: picture_notes,
+                        });
+                        continue;
+                    }
+                }
+            }
+
+            let (level, text) = if in_fence {
+                (1, line.trim_end().to_string())
+            } else {
+                parse_bullet(line)
+            };
+            if text.is_empty() {
+                continue;
+            }
+
+            let slide = current.get_or_insert_with(|| PptSlide {
+                title: String::new(),
+                kind: SlideKind::Content {
+                    bullets: Vec::new(),
+                },
+                notes: None,
+            });
+            match &mut slide.kind {
+                SlideKind::Title { subtitle } => match subtitle {
+                    Some(existing) => {
+                        existing.push('\n');
+                        existing.push_str(&text);
+                    }
+                    None => *subtitle = Some(text),
+                },
+                SlideKind::Content { bullets } => bullets.push((level, text)),
+                SlideKind::Picture { .. } => {}
+            }
+        }
+        attach_notes(&mut slides, &mut current, notes);
+        slides.extend(current);
+        slides
+    }
+}
+
+/// 将备注归入当前幻灯片，没有当前幻灯片时归入上一张；出现在所有幻灯片之前的备注被忽略
+fn attach_notes(slides: &mut [PptSlide], current: &mut Option<PptSlide>, lines: Option<Vec<&str>>) {
+    let Some(lines) = lines else {
+        return;
+    };
+    let text = lines.join("\n").trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    if let Some(slide) = current.as_mut().or(slides.last_mut()) {
+        match &mut slide.notes {
+            Some(existing) => {
+                existing.push_str("\n\n");
+                existing.push_str(&text);
+            }
+            None => slide.notes = Some(text),
+        }
+    }
+}
+
+fn parse_heading(line: &str) -> Option<(u8, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) || !line[level..].starts_with(' ') {
+        return None;
+    }
+    Some((level as u8, inline_text(&line[level..])))
+}
+
+/// 去掉行内 Markdown 标记，原样保留行内 HTML 文本
+fn inline_text(text: &str) -> String {
+    let mut output = String::new();
+    for event in Parser::new(text) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::InlineHtml(t) => output.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => output.push(' '),
+            _ => {}
+        }
+    }
+    output.trim().to_string()
+}
+
+/// 解析单独成行的图片 `![alt](url)`
+fn parse_image(line: &str) -> Option<(String, &str)> {
+    let rest = line.strip_prefix("![")?;
+    let (alt, rest) = rest.split_once("](")?;
+    let url = rest.strip_suffix(')')?;
+    let url = url.split_whitespace().next()?;
+    Some((alt.to_string(), url))
+}
+
+/// 解析列表项，返回缩进层级和纯文本
+fn parse_bullet(line: &str) -> (u8, String) {
+    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+    let trimmed = line.trim();
+    let item = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))
+        .or_else(|| {
+            let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+            (digits > 0)
+                .then(|| &trimmed[digits..])
+                .and_then(|r| r.strip_prefix(". ").or_else(|| r.strip_prefix(") ")))
+        });
+    let item = item
+        .unwrap_or(trimmed)
+        .trim_start_matches("[ ] ")
+        .trim_start_matches("[x] ");
+    let level = (indent / 2).min(4) as u8;
+    (level, inline_text(item))
+}
+
+fn image_extension_supported(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| matches!(e.as_str(), "png" | "jpg" | "jpeg" | "gif"))
+}
+
+/// 读取 PNG/JPEG/GIF 的像素尺寸
+fn image_size(data: &[u8]) -> Option<(i64, i64)> {
+    let be16 = |b: &[u8]| i64::from(u16::from_be_bytes([b[0], b[1]]));
+
+    if data.starts_with(b"\x89PNG") && data.len() >= 24 {
+        let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+        let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+        return Some((i64::from(width), i64::from(height)));
+    }
+    if data.starts_with(b"GIF") && data.len() >= 10 {
+        let width = u16::from_le_bytes([data[6], data[7]]);
+        let height = u16::from_le_bytes([data[8], data[9]]);
+        return Some((i64::from(width), i64::from(height)));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let mut pos = 2;
+        while pos + 9 < data.len() {
+            if data[pos] != 0xFF {
+                pos += 1;
+                continue;
+            }
+            let marker = data[pos + 1];
+            let length = be16(&data[pos + 2..]) as usize;
+            // SOF0..SOF15（排除 DHT/JPG/DAC）
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(&data[pos + 7..]), be16(&data[pos + 5..])));
+            }
+            pos += 2 + length;
+        }
+    }
+    None
+}
+
+fn write_entry(
+    zip: &mut ZipWriter<fs::File>,
+    name: &str,
+    options: SimpleFileOptions,
+    content: &str,
+) -> Result<(), String> {
+    zip.start_file(name, options).map_err(|e| e.to_string())?;
+    zip.write_all(content.as_bytes()).map_err(|e| e.to_string())
+}
+
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+const NS: &str = r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main""#;
+const REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const REL_TYPE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="ppt/presentation.xml"/></Relationships>"#;
+
+const SLIDE_MASTER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<p:sldMaster xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main"><p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/></p:spTree></p:cSld><p:clrMap bg1="lt1" tx1="dk1" bg2="lt2" tx2="dk2" accent1="accent1" accent2="accent2" accent3="accent3" accent4="accent4" accent5="accent5" accent6="accent6" hlink="hlink" folHlink="folHlink"/><p:sldLayoutIdLst><p:sldLayoutId id="2147483649" r:id="rId1"/></p:sldLayoutIdLst></p:sldMaster>"#;
+
+const SLIDE_MASTER_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideLayout" Target="../slideLayouts/slideLayout1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme" Target="../theme/theme1.xml"/></Relationships>"#;
+
+const SLIDE_LAYOUT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<p:sldLayout xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main" type="blank" preserve="1"><p:cSld name="Blank"><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/></p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sldLayout>"#;
+
+const SLIDE_LAYOUT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideMaster" Target="../slideMasters/slideMaster1.xml"/></Relationships>"#;
+
+const THEME: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" name="Office Theme"><a:themeElements><a:clrScheme name="Office"><a:dk1><a:sysClr val="windowText" lastClr="000000"/></a:dk1><a:lt1><a:sysClr val="window" lastClr="FFFFFF"/></a:lt1><a:dk2><a:srgbClr val="44546A"/></a:dk2><a:lt2><a:srgbClr val="E7E6E6"/></a:lt2><a:accent1><a:srgbClr val="4472C4"/></a:accent1><a:accent2><a:srgbClr val="ED7D31"/></a:accent2><a:accent3><a:srgbClr val="A5A5A5"/></a:accent3><a:accent4><a:srgbClr val="FFC000"/></a:accent4><a:accent5><a:srgbClr val="5B9BD5"/></a:accent5><a:accent6><a:srgbClr val="70AD47"/></a:accent6><a:hlink><a:srgbClr val="0563C1"/></a:hlink><a:folHlink><a:srgbClr val="954F72"/></a:folHlink></a:clrScheme><a:fontScheme name="Office"><a:majorFont><a:latin typeface="Calibri Light"/><a:ea typeface=""/><a:cs typeface=""/></a:majorFont><a:minorFont><a:latin typeface="Calibri"/><a:ea typeface=""/><a:cs typeface=""/></a:minorFont></a:fontScheme><a:fmtScheme name="Office"><a:fillStyleLst><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:fillStyleLst><a:lnStyleLst><a:ln w="6350"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln><a:ln w="12700"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln><a:ln w="19050"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln></a:lnStyleLst><a:effectStyleLst><a:effectStyle><a:effectLst/></a:effectStyle><a:effectStyle><a:effectLst/></a:effectStyle><a:effectStyle><a:effectLst/></a:effectStyle></a:effectStyleLst><a:bgFillStyleLst><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:bgFillStyleLst></a:fmtScheme></a:themeElements></a:theme>"#;
+
+/// 备注母版，只含备注正文占位符
+const NOTES_MASTER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<p:notesMaster xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main"><p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/><p:sp><p:nvSpPr><p:cNvPr id="2" name="Notes Placeholder"/><p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:spPr><a:xfrm><a:off x="685800" y="4800600"/><a:ext cx="5486400" cy="3600450"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr><p:txBody><a:bodyPr/><a:lstStyle/><a:p><a:endParaRPr lang="zh-CN"/></a:p></p:txBody></p:sp></p:spTree></p:cSld><p:clrMap bg1="lt1" tx1="dk1" bg2="lt2" tx2="dk2" accent1="accent1" accent2="accent2" accent3="accent3" accent4="accent4" accent5="accent5" accent6="accent6" hlink="hlink" folHlink="folHlink"/></p:notesMaster>"#;
+
+const NOTES_MASTER_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme" Target="../theme/theme2.xml"/></Relationships>"#;
+
+fn content_types_xml(slide_count: usize, noted: &[usize]) -> String {
+    let mut overrides = String::new();
+    for idx in 1..=slide_count {
+        overrides.push_str(&format!(
+            r#"<Override PartName="/ppt/slides/slide{}.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.slide+xml"/>"#,
+            idx
+        ));
+    }
+    if !noted.is_empty() {
+        overrides.push_str(r#"<Override PartName="/ppt/notesMasters/notesMaster1.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.notesMaster+xml"/><Override PartName="/ppt/theme/theme2.xml" ContentType="application/vnd.openxmlformats-officedocument.theme+xml"/>"#);
+    }
+    for idx in noted {
+        overrides.push_str(&format!(
+            r#"<Override PartName="/ppt/notesSlides/notesSlide{}.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.notesSlide+xml"/>"#,
+            idx
+        ));
+    }
+    format!(
+        r#"{}
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Default Extension="png" ContentType="image/png"/><Default Extension="jpg" ContentType="image/jpeg"/><Default Extension="jpeg" ContentType="image/jpeg"/><Default Extension="gif" ContentType="image/gif"/><Override PartName="/ppt/presentation.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml"/><Override PartName="/ppt/slideMasters/slideMaster1.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.slideMaster+xml"/><Override PartName="/ppt/slideLayouts/slideLayout1.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.slideLayout+xml"/><Override PartName="/ppt/theme/theme1.xml" ContentType="application/vnd.openxmlformats-officedocument.theme+xml"/>{}</Types>"#,
+        XML_HEADER, overrides
+    )
+}
+
+/// 备注母版的关系 ID 排在所有幻灯片之后
+fn presentation_xml(slide_count: usize, has_notes: bool) -> String {
+    let slide_ids: String = (0..slide_count)
+        .map(|idx| format!(r#"<p:sldId id="{}" r:id="rId{}"/>"#, 256 + idx, idx + 3))
+        .collect();
+    let notes_master = if has_notes {
+        format!(
+            r#"<p:notesMasterIdLst><p:notesMasterId r:id="rId{}"/></p:notesMasterIdLst>"#,
+            slide_count + 3
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        r#"{}
+<p:presentation {}><p:sldMasterIdLst><p:sldMasterId id="2147483648" r:id="rId1"/></p:sldMasterIdLst>{}<p:sldIdLst>{}</p:sldIdLst><p:sldSz cx="{}" cy="{}"/><p:notesSz cx="6858000" cy="9144000"/></p:presentation>"#,
+        XML_HEADER, NS, notes_master, slide_ids, SLIDE_WIDTH, SLIDE_HEIGHT
+    )
+}
+
+fn presentation_rels(slide_count: usize, has_notes: bool) -> String {
+    let mut rels = format!(
+        r#"<Relationship Id="rId1" Type="{0}/slideMaster" Target="slideMasters/slideMaster1.xml"/><Relationship Id="rId2" Type="{0}/theme" Target="theme/theme1.xml"/>"#,
+        REL_TYPE
+    );
+    for idx in 0..slide_count {
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="{}/slide" Target="slides/slide{}.xml"/>"#,
+            idx + 3,
+            REL_TYPE,
+            idx + 1
+        ));
+    }
+    if has_notes {
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="{}/notesMaster" Target="notesMasters/notesMaster1.xml"/>"#,
+            slide_count + 3,
+            REL_TYPE
+        ));
+    }
+    format!(
+        r#"{}
+<Relationships xmlns="{}">{}</Relationships>"#,
+        XML_HEADER, REL_NS, rels
+    )
+}
+
+fn slide_rels(image: Option<&str>, notes_slide: Option<usize>) -> String {
+    let mut rels = format!(
+        r#"<Relationship Id="rId1" Type="{}/slideLayout" Target="../slideLayouts/slideLayout1.xml"/>"#,
+        REL_TYPE
+    );
+    if let Some(image) = image {
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId2" Type="{}/image" Target="../media/{}"/>"#,
+            REL_TYPE, image
+        ));
+    }
+    if let Some(idx) = notes_slide {
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId3" Type="{}/notesSlide" Target="../notesSlides/notesSlide{}.xml"/>"#,
+            REL_TYPE, idx
+        ));
+    }
+    format!(
+        r#"{}
+<Relationships xmlns="{}">{}</Relationships>"#,
+        XML_HEADER, REL_NS, rels
+    )
+}
+
+fn notes_slide_rels(slide: usize) -> String {
+    format!(
+        r#"{}
+<Relationships xmlns="{}"><Relationship Id="rId1" Type="{2}/notesMaster" Target="../notesMasters/notesMaster1.xml"/><Relationship Id="rId2" Type="{2}/slide" Target="../slides/slide{3}.xml"/></Relationships>"#,
+        XML_HEADER, REL_NS, REL_TYPE, slide
+    )
+}
+
+/// 备注页，每行备注一个段落
+fn notes_slide_xml(notes: &str) -> String {
+    let paragraphs: String = notes
+        .lines()
+        .map(|line| format!("<a:p>{}</a:p>", run(line, 1200, false)))
+        .collect();
+    format!(
+        r#"{}
+<p:notes {}><p:cSld><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/><p:sp><p:nvSpPr><p:cNvPr id="2" name="Notes Placeholder"/><p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:spPr/><p:txBody><a:bodyPr/><a:lstStyle/>{}</p:txBody></p:sp></p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:notes>"#,
+        XML_HEADER, NS, paragraphs
+    )
+}
+
+fn slide_xml(shapes: &str) -> String {
+    format!(
+        r#"{}
+<p:sld {}><p:cSld><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/>{}</p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sld>"#,
+        XML_HEADER, NS, shapes
+    )
+}
+
+/// 文本框形状，`paragraphs` 为已生成的 `<a:p>` 元素
+fn text_shape(
+    id: u32,
+    name: &str,
+    rect: (i64, i64, i64, i64),
+    anchor: &str,
+    paragraphs: &str,
+) -> String {
+    let (x, y, cx, cy) = rect;
+    format!(
+        r#"<p:sp><p:nvSpPr><p:cNvPr id="{id}" name="{name}"/><p:cNvSpPr txBox="1"/><p:nvPr/></p:nvSpPr><p:spPr><a:xfrm><a:off x="{x}" y="{y}"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom><a:noFill/></p:spPr><p:txBody><a:bodyPr wrap="square" anchor="{anchor}"><a:normAutofit/></a:bodyPr><a:lstStyle/>{paragraphs}</p:txBody></p:sp>"#
+    )
+}
+
+fn run(text: &str, size: u32, bold: bool) -> String {
+    format!(
+        r#"<a:r><a:rPr lang="zh-CN" sz="{}" b="{}" dirty="0"/><a:t>{}</a:t></a:r>"#,
+        size,
+        u8::from(bold),
+        escape_xml(text)
+    )
+}
+
+fn title_paragraph(text: &str, size: u32, align: &str) -> String {
+    format!(
+        r#"<a:p><a:pPr algn="{}"/>{}</a:p>"#,
+        align,
+        run(text, size, true)
+    )
+}
+
+fn title_slide_xml(title: &str, subtitle: Option<&str>) -> String {
+    let mut shapes = text_shape(
+        2,
+        "Title",
+        (
+            MARGIN,
+            SLIDE_HEIGHT / 3,
+            SLIDE_WIDTH - 2 * MARGIN,
+            TITLE_HEIGHT * 3 / 2,
+        ),
+        "b",
+        &title_paragraph(title, 4400, "ctr"),
+    );
+    if let Some(subtitle) = subtitle {
+        let paragraphs: String = subtitle
+            .lines()
+            .map(|line| {
+                format!(
+                    r#"<a:p><a:pPr algn="ctr"/>{}</a:p>"#,
+                    run(line, 2400, false)
+                )
+            })
+            .collect();
+        shapes.push_str(&text_shape(
+            3,
+            "Subtitle",
+            (
+                MARGIN,
+                SLIDE_HEIGHT / 3 + TITLE_HEIGHT * 3 / 2,
+                SLIDE_WIDTH - 2 * MARGIN,
+                TITLE_HEIGHT * 2,
+            ),
+            "t",
+            &paragraphs,
+        ));
+    }
+    slide_xml(&shapes)
+}
+
+fn content_slide_xml(title: &str, bullets: &[(u8, String)]) -> String {
+    let mut shapes = text_shape(
+        2,
+        "Title",
+        (MARGIN, MARGIN / 2, SLIDE_WIDTH - 2 * MARGIN, TITLE_HEIGHT),
+        "ctr",
+        &title_paragraph(title, 3600, "l"),
+    );
+    if !bullets.is_empty() {
+        let paragraphs: String = bullets
+            .iter()
+            .map(|(level, text)| {
+                let indent = 342_900;
+                let margin = indent * (i64::from(*level) + 1);
+                format!(
+                    r#"<a:p><a:pPr marL="{}" lvl="{}" indent="-{}"><a:buFont typeface="Arial"/><a:buChar char="•"/></a:pPr>{}</a:p>"#,
+                    margin,
+                    level,
+                    indent,
+                    run(text, 2400 - 200 * u32::from(*level), false)
+                )
+            })
+            .collect();
+        shapes.push_str(&text_shape(
+            3,
+            "Content",
+            (
+                MARGIN,
+                MARGIN / 2 + TITLE_HEIGHT,
+                SLIDE_WIDTH - 2 * MARGIN,
+                SLIDE_HEIGHT - TITLE_HEIGHT - MARGIN * 3 / 2,
+            ),
+            "t",
+            &paragraphs,
+        ));
+    }
+    slide_xml(&shapes)
+}
+
+fn picture_slide_xml(title: &str, media: &Media) -> String {
+    let mut shapes = String::new();
+    let mut top = MARGIN;
+    if !title.is_empty() {
+        shapes.push_str(&text_shape(
+            2,
+            "Title",
+            (MARGIN, MARGIN / 2, SLIDE_WIDTH - 2 * MARGIN, TITLE_HEIGHT),
+            "ctr",
+            &title_paragraph(title, 3200, "l"),
+        ));
+        top = MARGIN / 2 + TITLE_HEIGHT;
+    }
+
+    // 按比例缩放并居中
+    let max_width = SLIDE_WIDTH - 2 * MARGIN;
+    let max_height = SLIDE_HEIGHT - top - MARGIN;
+    let (width, height) = (media.width.max(1), media.height.max(1));
+    let (cx, cy) = if width * max_height > height * max_width {
+        (max_width, max_width * height / width)
+    } else {
+        (max_height * width / height, max_height)
+    };
+    let x = (SLIDE_WIDTH - cx) / 2;
+    let y = top + (max_height - cy) / 2;
+
+    shapes.push_str(&format!(
+        r#"<p:pic><p:nvPicPr><p:cNvPr id="4" name="{name}"/><p:cNvPicPr><a:picLocks noChangeAspect="1"/></p:cNvPicPr><p:nvPr/></p:nvPicPr><p:blipFill><a:blip r:embed="rId2"/><a:stretch><a:fillRect/></a:stretch></p:blipFill><p:spPr><a:xfrm><a:off x="{x}" y="{y}"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr></p:pic>"#,
+        name = escape_xml(&media.file_name)
+    ));
+    slide_xml(&shapes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use std::io::Read;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse_outline() {
+        let dir = std::env::temp_dir().join(format!("pptx-outline-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("arch.png"), png(800, 600)).unwrap();
+
+        let slides = PptxService::parse_outline(
+            "# Roadmap\n\nQ3 planning\n\n## Goals\n\n- Ship **v2**\n  - Beta in [June](x.md)\n1. Hire\n\n## Architecture\n\n![](arch.png)\n",
+            &dir,
+        );
+
+        assert_eq!(slides.len(), 3);
+        assert_eq!(
+            slides[0].kind,
+            SlideKind::Title {
+                subtitle: Some("Q3 planning".to_string())
+            }
+        );
+        assert_eq!(
+            slides[1].kind,
+            SlideKind::Content {
+                bullets: vec![
+                    (0, "Ship v2".to_string()),
+                    (1, "Beta in June".to_string()),
+                    (0, "Hire".to_string()),
+                ]
+            }
+        );
+        assert_eq!(slides[2].title, "Architecture");
+        assert!(matches!(slides[2].kind, SlideKind::Picture { .. }));
+
+        // 备注持续到 `---` 或下一个标题，不出现在幻灯片上；代码块中的 `---` 保留
+        let slides = PptxService::parse_outline(
+            "## Intro\n\n- Hello\n\nNote: say hi\n- and smile\n\n---\n\n- After\n\n## Code\n\n```yaml\n---\nkey: value\n```\n\nNotes: explain\n## End\n",
+            &dir,
+        );
+        assert_eq!(slides.len(), 3);
+        assert_eq!(
+            slides[0].kind,
+            SlideKind::Content {
+                bullets: vec![(0, "Hello".to_string()), (0, "After".to_string())]
+            }
+        );
+        assert_eq!(slides[0].notes.as_deref(), Some("say hi\n- and smile"));
+        assert_eq!(
+            slides[1].kind,
+            SlideKind::Content {
+                bullets: vec![(1, "---".to_string()), (1, "key: value".to_string())]
+            }
+        );
+        assert_eq!(slides[1].notes.as_deref(), Some("explain"));
+        assert_eq!(slides[2].notes, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_generate_result_as_pptx() {
+        let dir = std::env::temp_dir().join(format!("pptx-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("chart.png"), png(400, 200)).unwrap();
+
+        let result = GenerateResult {
+            success: true,
+            output_path: dir.join("outline.md").to_string_lossy().to_string(),
+            content:
+                "# Deck <One>\n\n## Points\n\n- a & b\n\nNote: mention <b>\n\n---\n\n![Chart](chart.png)\n"
+                    .to_string(),
+        };
+        let saved = PptxService::save_generate_result(&result, None).unwrap();
+        assert!(saved.output_path.ends_with("outline.pptx"));
+
+        let mut archive =
+            zip::ZipArchive::new(fs::File::open(&saved.output_path).unwrap()).unwrap();
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        assert!(names.contains(&"ppt/media/image1.png".to_string()));
+
+        for name in names
+            .iter()
+            .filter(|n| n.ends_with(".xml") || n.ends_with(".rels"))
+        {
+            let mut xml = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+
+            let mut reader = Reader::from_str(&xml);
+            let mut depth = 0i32;
+            loop {
+                match reader.read_event() {
+                    Ok(Event::Start(_)) => depth += 1,
+                    Ok(Event::End(_)) => depth -= 1,
+                    Ok(Event::Eof) => break,
+                    Ok(_) => {}
+                    Err(e) => panic!("{} 不是格式良好的 XML: {}", name, e),
+                }
+            }
+            assert_eq!(depth, 0, "{}", name);
+
+            // 关系指向的部件必须存在
+            if let Some(dir_name) = name.strip_suffix(".rels") {
+                let base = dir_name.replace("_rels/", "");
+                let base = Path::new(&base)
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                for target in xml.split("Target=\"").skip(1) {
+                    let target = target.split('"').next().unwrap();
+                    let mut resolved = base.clone();
+                    for part in target.split('/') {
+                        match part {
+                            ".." => {
+                                resolved.pop();
+                            }
+                            part => resolved.push(part),
+                        }
+                    }
+                    let resolved = resolved.to_string_lossy().replace('\\', "/");
+                    assert!(
+                        names.contains(&resolved),
+                        "{} 引用了不存在的 {}",
+                        name,
+                        resolved
+                    );
+                }
+            }
+        }
+
+        let mut slide = String::new();
+        archive
+            .by_name("ppt/slides/slide1.xml")
+            .unwrap()
+            .read_to_string(&mut slide)
+            .unwrap();
+        assert!(slide.contains("Deck &lt;One&gt;"));
+        let mut notes = String::new();
+        archive
+            .by_name("ppt/notesSlides/notesSlide2.xml")
+            .unwrap()
+            .read_to_string(&mut notes)
+            .unwrap();
+        assert!(notes.contains("mention &lt;b&gt;"));
+        assert!(names.contains(&"ppt/notesMasters/notesMaster1.xml".to_string()));
+        let mut types = String::new();
+        archive
+            .by_name("[Content_Types].xml")
+            .unwrap()
+            .read_to_string(&mut types)
+            .unwrap();
+        assert_eq!(types.matches("/ppt/slides/slide").count(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
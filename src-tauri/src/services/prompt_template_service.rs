@@ -0,0 +1,279 @@
+//! 提示词模板服务
+//! 内置总结、PPT 大纲、专题报告模板，用户模板与对内置模板的修改保存在 prompt_templates.json
+
+use crate::models::{PromptTemplate, PromptTemplateInput};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+const TEMPLATE_FILE: &str = "prompt_templates.json";
+
+const SYSTEM_PROMPT: &str =
+    "你是一名知识库助理。只依据用户提供的资料作答，不要编造资料中没有的事实。\
+使用 Markdown 输出，引用资料时在句末用 [编号] 标注来源。";
+
+fn builtin_template(id: &str, name: &str, description: &str, task: &str) -> PromptTemplate {
+    PromptTemplate {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        system_prompt: SYSTEM_PROMPT.to_string(),
+        user_prompt: format!(
+            "{}\n请使用{{{{language}}}}撰写，篇幅{{{{length}}}}。\n\n以下是资料：\n\n{{{{context}}}}",
+            task
+        ),
+        built_in: true,
+        updated_at: None,
+    }
+}
+
+/// 内置模板
+pub fn builtin_templates() -> Vec<PromptTemplate> {
+    vec![
+        builtin_template(
+            "summary",
+            "总结",
+            "对知识库内容撰写结构化总结",
+            "请对知识库「{{kb_name}}」中关于「{{topic}}」的资料撰写结构化总结，包含概述、要点和结论。",
+        ),
+        builtin_template(
+            "pptOutline",
+            "PPT 大纲",
+            "生成可直接导出为 PPTX 的演示大纲",
+            "请根据资料为「{{topic}}」生成 PPT 大纲：用一级标题作为演示标题，每个二级标题对应一页幻灯片，\
+页内使用不超过 5 条的列表要点，不要输出其他说明文字。",
+        ),
+        builtin_template(
+            "topicReport",
+            "专题报告",
+            "围绕指定主题撰写带来源标注的报告",
+            "请围绕主题「{{topic}}」撰写专题报告，包含背景、现状分析、关键发现和结论建议，并在相关语句后标注来源编号。",
+        ),
+    ]
+}
+
+/// 替换模板中的 `{{变量}}`，未知变量保持原样
+pub fn render(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match variables.get(name) {
+                    Some(value) => output.push_str(value),
+                    None => output.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 提示词模板服务
+pub struct PromptTemplateService {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl PromptTemplateService {
+    /// 创建服务实例
+    pub fn new() -> Result<Self, String> {
+        let storage_dir = dirs::data_dir()
+            .ok_or("无法获取数据目录")?
+            .join("bun-codeview")
+            .join("knowledge_bases");
+
+        Self::open(&storage_dir)
+    }
+
+    /// 使用指定目录下的模板文件
+    pub fn open(storage_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(storage_dir).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path: storage_dir.join(TEMPLATE_FILE),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// 读取已保存的模板（用户模板及被修改的内置模板）
+    fn load(&self) -> Result<Vec<PromptTemplate>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("模板文件解析失败: {}", e))
+    }
+
+    fn save(&self, templates: &[PromptTemplate]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(templates).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+
+    /// 列出所有模板：内置模板在前，用户模板按创建顺序
+    pub fn list(&self) -> Result<Vec<PromptTemplate>, String> {
+        let _guard = self.lock.lock().map_err(|_| "模板锁已损坏".to_string())?;
+        let mut saved = self.load()?;
+
+        let mut templates: Vec<PromptTemplate> = builtin_templates()
+            .into_iter()
+            .map(
+                |builtin| match saved.iter().position(|t| t.id == builtin.id) {
+                    Some(idx) => PromptTemplate {
+                        built_in: true,
+                        ..saved.remove(idx)
+                    },
+                    None => builtin,
+                },
+            )
+            .collect();
+        templates.extend(saved);
+        Ok(templates)
+    }
+
+    /// 获取模板
+    pub fn get(&self, id: &str) -> Result<PromptTemplate, String> {
+        self.list()?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("模板不存在: {}", id))
+    }
+
+    /// 创建用户模板
+    pub fn create(&self, input: PromptTemplateInput) -> Result<PromptTemplate, String> {
+        validate(&input)?;
+        let _guard = self.lock.lock().map_err(|_| "模板锁已损坏".to_string())?;
+        let mut saved = self.load()?;
+
+        let template = PromptTemplate {
+            id: format!("custom-{}", &Uuid::new_v4().simple().to_string()[..8]),
+            name: input.name.trim().to_string(),
+            description: input.description,
+            system_prompt: input.system_prompt,
+            user_prompt: input.user_prompt,
+            built_in: false,
+            updated_at: Some(Utc::now()),
+        };
+        saved.push(template.clone());
+        self.save(&saved)?;
+        Ok(template)
+    }
+
+    /// 更新模板；修改内置模板时保存为覆盖版本
+    pub fn update(&self, id: &str, input: PromptTemplateInput) -> Result<PromptTemplate, String> {
+        validate(&input)?;
+        let _guard = self.lock.lock().map_err(|_| "模板锁已损坏".to_string())?;
+        let mut saved = self.load()?;
+
+        let built_in = builtin_templates().iter().any(|t| t.id == id);
+        let position = saved.iter().position(|t| t.id == id);
+        if position.is_none() && !built_in {
+            return Err(format!("模板不存在: {}", id));
+        }
+
+        let template = PromptTemplate {
+            id: id.to_string(),
+            name: input.name.trim().to_string(),
+            description: input.description,
+            system_prompt: input.system_prompt,
+            user_prompt: input.user_prompt,
+            built_in,
+            updated_at: Some(Utc::now()),
+        };
+        match position {
+            Some(idx) => saved[idx] = template.clone(),
+            None => saved.push(template.clone()),
+        }
+        self.save(&saved)?;
+        Ok(template)
+    }
+
+    /// 删除用户模板；对内置模板则恢复默认内容
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|_| "模板锁已损坏".to_string())?;
+        let mut saved = self.load()?;
+
+        let before = saved.len();
+        saved.retain(|t| t.id != id);
+        if saved.len() == before && !builtin_templates().iter().any(|t| t.id == id) {
+            return Err(format!("模板不存在: {}", id));
+        }
+        self.save(&saved)
+    }
+}
+
+fn validate(input: &PromptTemplateInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("模板名称不能为空".to_string());
+    }
+    if input.user_prompt.trim().is_empty() {
+        return Err("模板内容不能为空".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables() {
+        let variables: HashMap<&str, String> = [
+            ("topic", "Rust".to_string()),
+            ("language", "English".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            render(
+                "Topic: {{ topic }} in {{language}} {{unknown}} {{",
+                &variables
+            ),
+            "Topic: Rust in English {{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn test_template_crud() {
+        let dir = std::env::temp_dir().join(format!("template-test-{}", Uuid::new_v4()));
+        let service = PromptTemplateService::open(&dir).unwrap();
+        assert_eq!(service.list().unwrap().len(), 3);
+
+        let input = |name: &str| PromptTemplateInput {
+            name: name.to_string(),
+            description: None,
+            system_prompt: String::new(),
+            user_prompt: "列出「{{topic}}」的术语表\n\n{{context}}".to_string(),
+        };
+        let custom = service.create(input("术语表")).unwrap();
+        assert!(custom.id.starts_with("custom-"));
+        assert!(service.create(input(" ")).is_err());
+
+        // 修改内置模板后可通过删除恢复默认
+        let summary = service.update("summary", input("我的总结")).unwrap();
+        assert!(summary.built_in);
+        let templates = service.list().unwrap();
+        assert_eq!(templates.len(), 4);
+        assert_eq!(templates[0].name, "我的总结");
+        assert_eq!(templates[3].id, custom.id);
+
+        service.delete("summary").unwrap();
+        assert_eq!(service.get("summary").unwrap().name, "总结");
+        service.delete(&custom.id).unwrap();
+        assert!(service.get(&custom.id).is_err());
+        assert!(service.delete("missing").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
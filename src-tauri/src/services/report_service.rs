@@ -0,0 +1,589 @@
+//! 专题报告生成服务
+//! 按“检索资料 → 拟定大纲 → 逐章撰写 → 汇总保存”分步生成，每一步的结果保存在任务中，
+//! 模型调用失败或被取消后可以从未完成的步骤继续
+
+use crate::models::{
+    AiConfig, ChunkSearchResult, GenerateRequest, GenerateType, PromptTemplate, ReportJob,
+    ReportJobStatus, ReportOutline, ReportSection,
+};
+use crate::services::chat_service::extract_citations;
+use crate::services::generate_service::{GenerateService, MAX_CONTEXT_CHARS};
+use crate::services::knowledge_base_service::KnowledgeBaseService;
+use crate::services::llm_client::{ChatMessage, LlmClient};
+use crate::services::prompt_template_service;
+use chrono::Utc;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+/// 大纲的章节数上限
+const MAX_SECTIONS: usize = 8;
+/// 每个章节的要点数上限
+const MAX_POINTS: usize = 5;
+
+/// 报告生成的步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportStep {
+    Retrieve,
+    Outline,
+    Section,
+    Assemble,
+}
+
+/// 步骤完成事件；继续生成时已完成的步骤也会按顺序重新发送
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportProgress {
+    pub job_id: String,
+    pub step: ReportStep,
+    /// 章节序号（从 0 开始），仅 `section` 步骤有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub content: String,
+}
+
+pub struct ReportService;
+
+impl ReportService {
+    /// 检索步骤：校验请求、选取与主题相关的资料并保存新任务
+    pub fn create_job(
+        kb_service: &KnowledgeBaseService,
+        request: &GenerateRequest,
+        output_dir: &Path,
+    ) -> Result<ReportJob, String> {
+        let topic = request
+            .topic
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or("专题报告需要指定主题")?;
+        let kb = kb_service
+            .get_knowledge_base(&request.kb_id)?
+            .ok_or("知识库不存在")?;
+
+        let documents = kb_service.get_documents(&kb.id)?;
+        let sources = GenerateService::select_chunks(&documents, Some(topic), MAX_CONTEXT_CHARS);
+        if sources.is_empty() {
+            return Err("知识库中没有可用的文档内容".to_string());
+        }
+
+        let now = Utc::now();
+        let job = ReportJob {
+            id: Uuid::new_v4().to_string(),
+            request: GenerateRequest {
+                generate_type: GenerateType::TopicReport,
+                ..request.clone()
+            },
+            output_dir: output_dir.to_string_lossy().to_string(),
+            sources,
+            outline: None,
+            status: ReportJobStatus::Running,
+            output_path: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        kb_service.save_report_job(&job)?;
+        Ok(job)
+    }
+
+    /// 执行任务中未完成的步骤，结束后保存任务状态
+    pub fn run(
+        kb_service: &KnowledgeBaseService,
+        job: &mut ReportJob,
+        template: &PromptTemplate,
+        config: &AiConfig,
+        cancelled: &AtomicBool,
+        mut on_progress: impl FnMut(ReportProgress),
+    ) {
+        job.status = ReportJobStatus::Running;
+        job.error = None;
+        let outcome = Self::save(kb_service, job).and_then(|_| {
+            Self::advance(
+                kb_service,
+                job,
+                template,
+                config,
+                cancelled,
+                &mut on_progress,
+            )
+        });
+
+        match outcome {
+            Ok(true) => job.status = ReportJobStatus::Completed,
+            Ok(false) => job.status = ReportJobStatus::Cancelled,
+            Err(error) => {
+                job.status = ReportJobStatus::Failed;
+                job.error = Some(error);
+            }
+        }
+        if let Err(e) = Self::save(kb_service, job) {
+            log::error!("保存专题报告任务失败: {}", e);
+            job.status = ReportJobStatus::Failed;
+            job.error = Some(
+                job.error
+                    .take()
+                    .map_or(e.clone(), |prev| format!("{}；{}", prev, e)),
+            );
+        }
+    }
+
+    /// 依次执行各步骤；被取消时返回 `Ok(false)`
+    fn advance(
+        kb_service: &KnowledgeBaseService,
+        job: &mut ReportJob,
+        template: &PromptTemplate,
+        config: &AiConfig,
+        cancelled: &AtomicBool,
+        on_progress: &mut impl FnMut(ReportProgress),
+    ) -> Result<bool, String> {
+        let kb = kb_service
+            .get_knowledge_base(&job.request.kb_id)?
+            .ok_or("知识库不存在")?;
+        let client = LlmClient::new(config);
+        let variables = GenerateService::template_variables(&kb.name, &job.request, &job.sources);
+        let system = prompt_template_service::render(&template.system_prompt, &variables);
+        // 模板中的撰写要求和资料放在每一步的说明之后，修改模板同样影响大纲和各章节
+        let task = prompt_template_service::render(&template.user_prompt, &variables);
+        let messages = |prompt: String| {
+            let mut messages = Vec::new();
+            if !system.trim().is_empty() {
+                messages.push(ChatMessage::system(system.clone()));
+            }
+            messages.push(ChatMessage::user(prompt));
+            messages
+        };
+        let job_id = job.id.clone();
+        let progress =
+            |step, section: Option<usize>, title: Option<&str>, content: String| ReportProgress {
+                job_id: job_id.clone(),
+                step,
+                section,
+                title: title.map(str::to_string),
+                content,
+            };
+
+        on_progress(progress(
+            ReportStep::Retrieve,
+            None,
+            None,
+            format!("已选取 {} 个相关文本块", job.sources.len()),
+        ));
+
+        let outline = match job.outline.clone() {
+            Some(outline) => outline,
+            None => {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Ok(false);
+                }
+                let prompt = format!(
+                    "本次只需为下面要求的专题报告拟定大纲：第一行用一级标题写报告标题，\
+每个章节使用二级标题，章节下用不超过 3 条列表概括要点，章节数不超过 {max} 个。\
+只输出大纲。\n\n{task}",
+                    max = MAX_SECTIONS,
+                    task = task,
+                );
+                let reply = client.chat(&messages(prompt))?;
+                let outline = parse_outline(&reply, &format!("{}专题报告", variables["topic"]))?;
+                job.outline = Some(outline.clone());
+                Self::save(kb_service, job)?;
+                outline
+            }
+        };
+        on_progress(progress(
+            ReportStep::Outline,
+            None,
+            Some(&outline.title),
+            outline_markdown(&outline),
+        ));
+
+        let total = outline.sections.len();
+        for (idx, section) in outline.sections.iter().enumerate() {
+            let content = match &section.content {
+                Some(content) => content.clone(),
+                None => {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return Ok(false);
+                    }
+                    let points = if section.points.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            "需覆盖以下要点：\n{}\n",
+                            section
+                                .points
+                                .iter()
+                                .map(|p| format!("- {}", p))
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        )
+                    };
+                    let prompt = format!(
+                        "专题报告的大纲如下：\n\n{outline}\n\n本次只需撰写第 {n}/{total} 章「{title}」的正文。\
+{points}只输出正文，不要重复章节标题，需要时可使用三级及以下标题。\
+依据资料在相关语句后用 [编号] 标注来源，本章篇幅按全文要求的比例分配。\n\n{task}",
+                        outline = outline_markdown(&outline),
+                        n = idx + 1,
+                        total = total,
+                        title = section.title,
+                        points = points,
+                        task = task,
+                    );
+                    let reply = client.chat(&messages(prompt))?;
+                    let content = strip_heading(&reply, &section.title);
+                    if let Some(saved) = job.outline.as_mut() {
+                        saved.sections[idx].content = Some(content.clone());
+                    }
+                    Self::save(kb_service, job)?;
+                    content
+                }
+            };
+            on_progress(progress(
+                ReportStep::Section,
+                Some(idx),
+                Some(&section.title),
+                content,
+            ));
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let outline = job.outline.as_ref().ok_or("报告大纲不存在")?;
+        let report = assemble(outline, &job.sources);
+        let path = GenerateService::save_output(
+            Path::new(&job.output_dir),
+            &kb.name,
+            &GenerateType::TopicReport,
+            &report,
+            false,
+        )?;
+        job.output_path = Some(path.to_string_lossy().to_string());
+        on_progress(progress(
+            ReportStep::Assemble,
+            None,
+            Some(&outline.title),
+            report,
+        ));
+        Ok(true)
+    }
+
+    fn save(kb_service: &KnowledgeBaseService, job: &mut ReportJob) -> Result<(), String> {
+        job.updated_at = Utc::now();
+        kb_service.save_report_job(job)
+    }
+}
+
+/// 解析模型输出的大纲：一级标题为报告标题，二级标题为章节，其下的列表项为要点；
+/// 没有二级标题时退回到编号列表
+pub fn parse_outline(text: &str, default_title: &str) -> Result<ReportOutline, String> {
+    let mut title = None;
+    let mut sections: Vec<ReportSection> = Vec::new();
+    let mut numbered: Vec<ReportSection> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix("## ") {
+            sections.push(section(heading));
+        } else if let Some(heading) = line.strip_prefix("# ") {
+            if title.is_none() && sections.is_empty() {
+                title = Some(clean_title(heading));
+            }
+        } else if let Some(point) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| line.strip_prefix(marker))
+        {
+            let target = if sections.is_empty() {
+                numbered.last_mut()
+            } else {
+                sections.last_mut()
+            };
+            if let Some(target) = target {
+                if target.points.len() < MAX_POINTS && !point.trim().is_empty() {
+                    target.points.push(clean_title(point));
+                }
+            }
+        } else if let Some(heading) = strip_number(line) {
+            numbered.push(section(heading));
+        }
+    }
+
+    if sections.is_empty() {
+        sections = numbered;
+    }
+    sections.retain(|s| !s.title.is_empty());
+    sections.truncate(MAX_SECTIONS);
+    if sections.is_empty() {
+        return Err("未能从模型回复中解析出报告大纲".to_string());
+    }
+    Ok(ReportOutline {
+        title: title
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| default_title.to_string()),
+        sections,
+    })
+}
+
+fn section(title: &str) -> ReportSection {
+    ReportSection {
+        title: clean_title(title),
+        points: Vec::new(),
+        content: None,
+    }
+}
+
+/// 去掉标题两侧的空白和加粗标记
+fn clean_title(title: &str) -> String {
+    title.trim().trim_matches('*').trim().to_string()
+}
+
+/// 识别 `1. 标题`、`1、标题` 形式的编号行
+fn strip_number(line: &str) -> Option<&str> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &line[digits..];
+    rest.strip_prefix(". ")
+        .or_else(|| rest.strip_prefix('、'))
+        .filter(|title| !title.trim().is_empty())
+}
+
+/// 模型重复输出章节标题时去掉开头的标题行
+fn strip_heading(content: &str, title: &str) -> String {
+    let content = content.trim();
+    match content.split_once('\n') {
+        Some((first, rest)) if first.starts_with('#') && first.contains(title) => {
+            rest.trim().to_string()
+        }
+        None if content.starts_with('#') && content.contains(title) => String::new(),
+        _ => content.to_string(),
+    }
+}
+
+/// 将大纲渲染为 Markdown
+pub fn outline_markdown(outline: &ReportOutline) -> String {
+    let mut lines = vec![format!("# {}", outline.title)];
+    for section in &outline.sections {
+        lines.push(format!("## {}", section.title));
+        lines.extend(section.points.iter().map(|p| format!("- {}", p)));
+    }
+    lines.join("\n")
+}
+
+/// 汇总各章节正文，末尾列出正文中引用过的资料
+pub fn assemble(outline: &ReportOutline, sources: &[ChunkSearchResult]) -> String {
+    let mut parts = vec![format!("# {}", outline.title)];
+    for section in &outline.sections {
+        parts.push(format!(
+            "## {}\n\n{}",
+            section.title,
+            section.content.as_deref().unwrap_or_default().trim()
+        ));
+    }
+
+    let body = parts.join("\n\n");
+    let citations = extract_citations(&body, sources);
+    if citations.is_empty() {
+        return body;
+    }
+    let mut references: Vec<_> = citations
+        .iter()
+        .map(|c| {
+            let location = match c.page {
+                Some(page) => format!("第 {} 页", page),
+                None => format!("第 {}-{} 行", c.start_line, c.end_line),
+            };
+            (
+                c.index,
+                format!(
+                    "[{}] {}（{} {}）",
+                    c.index, c.document_title, c.path, location
+                ),
+            )
+        })
+        .collect();
+    references.sort_by_key(|(index, _)| *index);
+    let references: Vec<String> = references.into_iter().map(|(_, line)| line).collect();
+    format!("{}\n\n## 参考资料\n\n{}\n", body, references.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::prompt_template_service::builtin_templates;
+    use crate::services::test_support::{StubResponse, StubServer};
+    use std::fs;
+
+    fn reply(content: &str) -> StubResponse {
+        StubResponse::json(
+            serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": content}}]
+            })
+            .to_string(),
+        )
+    }
+
+    #[test]
+    fn test_parse_outline() {
+        let outline = parse_outline(
+            "# Rust 并发\n\n## 背景\n- 多核\n- **线程**\n## 所有权与借用\n- 数据竞争\n",
+            "默认",
+        )
+        .unwrap();
+        assert_eq!(outline.title, "Rust 并发");
+        assert_eq!(outline.sections.len(), 2);
+        assert_eq!(outline.sections[0].points, vec!["多核", "线程"]);
+        assert_eq!(outline.sections[1].title, "所有权与借用");
+
+        // 没有二级标题时使用编号列表
+        let outline = parse_outline("大纲如下：\n1. 背景\n   - 要点\n2、结论", "默认").unwrap();
+        assert_eq!(outline.title, "默认");
+        assert_eq!(
+            outline
+                .sections
+                .iter()
+                .map(|s| s.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["背景", "结论"]
+        );
+        assert_eq!(outline.sections[0].points, vec!["要点"]);
+
+        assert!(parse_outline("无法生成大纲", "默认").is_err());
+    }
+
+    #[test]
+    fn test_report_resumes_after_failure() {
+        let dir = std::env::temp_dir().join(format!("report-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("rust.md");
+        fs::write(
+            &note,
+            "# Rust\n\n所有权系统保证内存安全。\n---\n## 并发\n\n借用检查器阻止数据竞争。",
+        )
+        .unwrap();
+
+        let kb_service = KnowledgeBaseService::open(&dir.join("store")).unwrap();
+        let kb = kb_service
+            .create_knowledge_base("Rust 笔记".to_string(), None)
+            .unwrap();
+        kb_service
+            .import_document(&kb.id, &note.to_string_lossy())
+            .unwrap();
+
+        let mut request = GenerateRequest {
+            kb_id: kb.id.clone(),
+            generate_type: GenerateType::TopicReport,
+            topic: None,
+            language: None,
+            length: None,
+            output_dir: None,
+        };
+        let out_dir = dir.join("out");
+        assert!(ReportService::create_job(&kb_service, &request, &out_dir).is_err());
+        request.topic = Some("数据竞争".to_string());
+        let mut job = ReportService::create_job(&kb_service, &request, &out_dir).unwrap();
+        assert!(job.sources[0].content.contains("借用检查器"));
+
+        // 第二章撰写失败：大纲和第一章已保存
+        let server = StubServer::start(vec![
+            reply("# 数据竞争报告\n## 问题\n- 竞争条件\n## 解决\n- 借用检查"),
+            reply("## 问题\n\n多个线程同时写入 [1]。"),
+            StubResponse::status(500, "boom"),
+        ]);
+        let config = AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        };
+        let mut template = builtin_templates()
+            .into_iter()
+            .find(|t| t.id == "topicReport")
+            .unwrap();
+        template.user_prompt = format!("面向运维团队撰写。{}", template.user_prompt);
+        let mut steps = Vec::new();
+        ReportService::run(
+            &kb_service,
+            &mut job,
+            &template,
+            &config,
+            &AtomicBool::new(false),
+            |p| steps.push((p.step, p.section)),
+        );
+        assert_eq!(job.status, ReportJobStatus::Failed);
+        assert!(job.error.is_some());
+        assert_eq!(
+            steps,
+            vec![
+                (ReportStep::Retrieve, None),
+                (ReportStep::Outline, None),
+                (ReportStep::Section, Some(0)),
+            ]
+        );
+        let requests = server.requests();
+        let prompt = |idx: usize| {
+            requests[idx].json()["messages"][1]["content"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        // 大纲和章节的提示词都包含模板中的要求和资料
+        assert!(prompt(0).contains("拟定大纲"));
+        assert!(prompt(0).contains("面向运维团队撰写。请围绕主题「数据竞争」"));
+        assert!(prompt(0).contains("借用检查器"));
+        assert!(prompt(1).contains("第 1/2 章「问题」"));
+        assert!(prompt(1).contains("- 竞争条件"));
+        assert!(prompt(1).contains("面向运维团队撰写。"));
+
+        let saved = kb_service.get_report_job(&job.id).unwrap().unwrap();
+        assert_eq!(saved.status, ReportJobStatus::Failed);
+        let outline = saved.outline.clone().unwrap();
+        assert_eq!(
+            outline.sections[0].content.as_deref(),
+            Some("多个线程同时写入 [1]。")
+        );
+        assert!(outline.sections[1].content.is_none());
+
+        // 继续生成时只请求未完成的章节
+        let server = StubServer::start(vec![reply("编译期拒绝数据竞争 [1][9]。")]);
+        let config = AiConfig {
+            base_url: server.url.clone(),
+            ..AiConfig::default()
+        };
+        let mut job = saved;
+        ReportService::run(
+            &kb_service,
+            &mut job,
+            &template,
+            &config,
+            &AtomicBool::new(false),
+            |_| {},
+        );
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(job.status, ReportJobStatus::Completed);
+        assert!(job.error.is_none());
+
+        let path = job.output_path.clone().unwrap();
+        assert!(path.contains("Rust-笔记-topic-report-"));
+        let report = fs::read_to_string(&path).unwrap();
+        assert!(report.starts_with("# 数据竞争报告\n\n## 问题\n\n多个线程同时写入 [1]。"));
+        assert!(report.contains("## 解决\n\n编译期拒绝数据竞争 [1][9]。"));
+        assert!(report.contains(&format!(
+            "## 参考资料\n\n[1] Rust（{} 第 4-6 行）",
+            note.to_string_lossy()
+        )));
+        assert_eq!(
+            kb_service.list_report_jobs(&kb.id).unwrap()[0].status,
+            ReportJobStatus::Completed
+        );
+
+        // 删除知识库时一并删除任务
+        kb_service.delete_knowledge_base(&kb.id).unwrap();
+        assert!(kb_service.get_report_job(&job.id).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,387 @@
+//! 知识库全文搜索服务
+//! 基于 BM25 对文本块排序，分词兼顾英文单词与中日韩字符（单字 + 双字）
+
+use crate::models::{ChunkSearchResult, Document};
+use crate::services::embedding_service::cosine_similarity;
+use crate::services::markdown_renderer::escape_xml;
+use std::collections::HashMap;
+
+/// BM25 词频饱和参数
+const K1: f64 = 1.2;
+/// BM25 长度归一化参数
+const B: f64 = 0.75;
+/// 每条结果最多返回的摘要数
+const MAX_SNIPPETS: usize = 3;
+/// 摘要中命中位置前后保留的字符数
+const SNIPPET_CONTEXT: usize = 40;
+
+/// 默认返回结果数
+pub const DEFAULT_LIMIT: usize = 20;
+/// 混合检索中向量相似度的默认权重
+pub const DEFAULT_VECTOR_WEIGHT: f64 = 0.5;
+
+/// 是否为中日韩字符
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // 平假名、片假名
+        | 0x3400..=0x4DBF    // CJK 扩展 A
+        | 0x4E00..=0x9FFF    // CJK 统一汉字
+        | 0xAC00..=0xD7AF    // 韩文音节
+        | 0xF900..=0xFAFF    // CJK 兼容汉字
+        | 0x20000..=0x2A6DF) // CJK 扩展 B
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// 分词：连续字母数字组成一个词，中日韩字符输出单字及相邻双字
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            if let Some(prev) = prev_cjk {
+                tokens.push(format!("{}{}", prev, c));
+            }
+            tokens.push(c.to_string());
+            prev_cjk = Some(c);
+        } else {
+            prev_cjk = None;
+            if c.is_alphanumeric() {
+                word.push(fold(c));
+            } else if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+pub struct SearchService;
+
+impl SearchService {
+    /// 计算每个文本块的 BM25 分数，返回 `(文档下标, 文本块下标, 分数)`，按分数降序
+    pub fn rank(documents: &[Document], query: &str) -> Vec<(usize, usize, f64)> {
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        // 标题路径参与检索，使章节名也能命中
+        let mut term_freqs: Vec<(usize, usize, HashMap<&str, usize>, usize)> = Vec::new();
+        let mut doc_freq: HashMap<&str, usize> = HashMap::new();
+        for (doc_idx, doc) in documents.iter().enumerate() {
+            for (chunk_idx, chunk) in doc.chunks.iter().enumerate() {
+                let mut tokens = tokenize(&chunk.content);
+                tokens.extend(chunk.headings.iter().flat_map(|h| tokenize(h)));
+
+                let mut tf: HashMap<&str, usize> = HashMap::new();
+                for token in &tokens {
+                    if let Some(term) = query_terms.iter().find(|t| *t == token) {
+                        *tf.entry(term.as_str()).or_default() += 1;
+                    }
+                }
+                for term in tf.keys() {
+                    *doc_freq.entry(term).or_default() += 1;
+                }
+                term_freqs.push((doc_idx, chunk_idx, tf, tokens.len()));
+            }
+        }
+
+        let total = term_freqs.len() as f64;
+        if total == 0.0 {
+            return Vec::new();
+        }
+        let avg_len = term_freqs.iter().map(|t| t.3).sum::<usize>() as f64 / total;
+
+        let mut ranked: Vec<(usize, usize, f64)> = term_freqs
+            .iter()
+            .filter(|(_, _, tf, _)| !tf.is_empty())
+            .map(|(doc_idx, chunk_idx, tf, len)| {
+                let norm = K1 * (1.0 - B + B * *len as f64 / avg_len.max(1.0));
+                let score = tf
+                    .iter()
+                    .map(|(term, freq)| {
+                        let n = doc_freq[term] as f64;
+                        let idf = ((total - n + 0.5) / (n + 0.5) + 1.0).ln();
+                        let freq = *freq as f64;
+                        idf * freq * (K1 + 1.0) / (freq + norm)
+                    })
+                    .sum();
+                (*doc_idx, *chunk_idx, score)
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+        ranked
+    }
+
+    /// 搜索知识库文档，返回带高亮摘要的文本块
+    pub fn search(documents: &[Document], query: &str, limit: usize) -> Vec<ChunkSearchResult> {
+        Self::rank(documents, query)
+            .into_iter()
+            .take(limit)
+            .map(|(doc_idx, chunk_idx, score)| {
+                let doc = &documents[doc_idx];
+                Self::to_result(doc, chunk_idx, query, score)
+            })
+            .collect()
+    }
+
+    /// 混合检索：关键词 BM25 分数按最大值归一化后与向量余弦相似度加权求和
+    pub fn hybrid_search(
+        documents: &[Document],
+        query: &str,
+        query_vector: &[f32],
+        embeddings: &HashMap<String, Vec<f32>>,
+        vector_weight: f64,
+        limit: usize,
+    ) -> Vec<ChunkSearchResult> {
+        let vector_weight = vector_weight.clamp(0.0, 1.0);
+        let keyword: HashMap<(usize, usize), f64> = Self::rank(documents, query)
+            .into_iter()
+            .map(|(doc_idx, chunk_idx, score)| ((doc_idx, chunk_idx), score))
+            .collect();
+        let max_keyword = keyword.values().cloned().fold(0.0, f64::max);
+
+        let mut ranked: Vec<(usize, usize, f64)> = Vec::new();
+        for (doc_idx, doc) in documents.iter().enumerate() {
+            for (chunk_idx, chunk) in doc.chunks.iter().enumerate() {
+                let keyword_score = match keyword.get(&(doc_idx, chunk_idx)) {
+                    Some(score) if max_keyword > 0.0 => score / max_keyword,
+                    _ => 0.0,
+                };
+                let vector_score = embeddings
+                    .get(&chunk.id)
+                    .map(|v| cosine_similarity(query_vector, v).max(0.0) as f64)
+                    .unwrap_or(0.0);
+                let score = vector_weight * vector_score + (1.0 - vector_weight) * keyword_score;
+                if score > 0.0 {
+                    ranked.push((doc_idx, chunk_idx, score));
+                }
+            }
+        }
+
+        ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(doc_idx, chunk_idx, score)| {
+                Self::to_result(&documents[doc_idx], chunk_idx, query, score)
+            })
+            .collect()
+    }
+
+    /// 将文本块转换为搜索结果
+    pub fn to_result(
+        doc: &Document,
+        chunk_idx: usize,
+        query: &str,
+        score: f64,
+    ) -> ChunkSearchResult {
+        let chunk = &doc.chunks[chunk_idx];
+        ChunkSearchResult {
+            document_id: doc.id.clone(),
+            document_title: doc.title.clone(),
+            path: doc.path.clone(),
+            chunk_id: chunk.id.clone(),
+            content: chunk.content.clone(),
+            snippets: highlight(&chunk.content, query),
+            headings: chunk.headings.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            page: chunk.page,
+            score,
+        }
+    }
+}
+
+/// 生成带 `<mark>` 高亮的 HTML 摘要
+pub fn highlight(text: &str, query: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+    let mut marked = vec![false; chars.len()];
+
+    let mut terms = tokenize(query);
+    terms.sort();
+    terms.dedup();
+    for term in &terms {
+        let needle: Vec<char> = term.chars().collect();
+        let word = !needle.iter().any(|c| is_cjk(*c));
+        let mut start = 0;
+        while start + needle.len() <= folded.len() {
+            if folded[start..start + needle.len()] == needle[..] {
+                let end = start + needle.len();
+                // 英文词需完整匹配
+                let bounded = !word
+                    || ((start == 0 || !folded[start - 1].is_alphanumeric())
+                        && (end == folded.len() || !folded[end].is_alphanumeric()));
+                if bounded {
+                    marked[start..end].iter_mut().for_each(|m| *m = true);
+                    start = end;
+                    continue;
+                }
+            }
+            start += 1;
+        }
+    }
+
+    // 合并相邻命中为摘要窗口
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for (idx, _) in marked.iter().enumerate().filter(|(_, m)| **m) {
+        let from = idx.saturating_sub(SNIPPET_CONTEXT);
+        let to = (idx + 1 + SNIPPET_CONTEXT).min(chars.len());
+        match windows.last_mut() {
+            Some(last) if from <= last.1 => last.1 = to,
+            _ => windows.push((from, to)),
+        }
+    }
+
+    windows
+        .into_iter()
+        .take(MAX_SNIPPETS)
+        .map(|(from, to)| {
+            let mut snippet = String::new();
+            if from > 0 {
+                snippet.push('…');
+            }
+            let mut idx = from;
+            while idx < to {
+                let run_end = (idx..to).find(|i| marked[*i] != marked[idx]).unwrap_or(to);
+                let piece: String = chars[idx..run_end].iter().collect();
+                let piece = escape_xml(&piece.replace('\n', " "));
+                if marked[idx] {
+                    snippet.push_str(&format!("<mark>{}</mark>", piece));
+                } else {
+                    snippet.push_str(&piece);
+                }
+                idx = run_end;
+            }
+            if to < chars.len() {
+                snippet.push('…');
+            }
+            snippet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Chunk, DocumentMetadata};
+    use chrono::Utc;
+
+    fn document(id: &str, chunks: &[(&str, &[&str])]) -> Document {
+        Document {
+            id: id.to_string(),
+            kb_id: "kb".to_string(),
+            path: format!("/notes/{}.md", id),
+            title: id.to_string(),
+            content: String::new(),
+            chunks: chunks
+                .iter()
+                .enumerate()
+                .map(|(idx, (content, headings))| Chunk {
+                    id: format!("{}-{}", id, idx),
+                    content: content.to_string(),
+                    start_line: idx * 10 + 1,
+                    end_line: idx * 10 + 9,
+                    headings: headings.iter().map(|h| h.to_string()).collect(),
+                    page: None,
+                })
+                .collect(),
+            metadata: DocumentMetadata {
+                word_count: 0,
+                line_count: 0,
+                headings: Vec::new(),
+                keywords: None,
+                page_count: None,
+                tags: Vec::new(),
+            },
+            created_at: Utc::now(),
+            content_hash: String::new(),
+            modified_at: None,
+            source_missing: false,
+        }
+    }
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        assert_eq!(
+            tokenize("Rust 知识库"),
+            vec!["rust", "知", "知识", "识", "识库", "库"]
+        );
+    }
+
+    #[test]
+    fn test_search_ranks_and_highlights() {
+        let docs = vec![
+            document(
+                "a",
+                &[
+                    ("本地知识库支持全文搜索，知识库内容保存在本机。", &["概述"]),
+                    ("Export notes to EPUB & HTML.", &["导出"]),
+                ],
+            ),
+            document("b", &[("搜索引擎使用 BM25 排序。", &["Search"])]),
+        ];
+
+        let results = SearchService::search(&docs, "知识库", DEFAULT_LIMIT);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "a-0");
+        assert_eq!(results[0].headings, vec!["概述".to_string()]);
+        assert_eq!(results[0].start_line, 1);
+        assert_eq!(
+            results[0].snippets,
+            vec!["本地<mark>知识库</mark>支持全文搜索，<mark>知识库</mark>内容保存在本机。"]
+        );
+
+        // 标题也参与检索，英文按整词匹配并转义
+        let results = SearchService::search(&docs, "search html", DEFAULT_LIMIT);
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .any(|r| r.snippets == vec!["Export notes to EPUB &amp; <mark>HTML</mark>."]));
+        assert!(SearchService::search(&docs, "  ", DEFAULT_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn test_hybrid_search_uses_vectors() {
+        let docs = vec![document(
+            "a",
+            &[
+                ("向量检索能找到语义相近的段落", &[]),
+                ("BM25 keyword ranking", &[]),
+            ],
+        )];
+        let embeddings: HashMap<String, Vec<f32>> = [
+            ("a-0".to_string(), vec![1.0, 0.0]),
+            ("a-1".to_string(), vec![0.0, 1.0]),
+        ]
+        .into_iter()
+        .collect();
+
+        // 关键词只命中第二块，向量更接近第一块
+        let results =
+            SearchService::hybrid_search(&docs, "keyword", &[1.0, 0.0], &embeddings, 0.7, 10);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].chunk_id, "a-0");
+        assert!((results[0].score - 0.7).abs() < 1e-6);
+
+        let results =
+            SearchService::hybrid_search(&docs, "keyword", &[1.0, 0.0], &embeddings, 0.0, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "a-1");
+    }
+}
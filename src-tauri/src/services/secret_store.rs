@@ -0,0 +1,314 @@
+//! 密钥存储
+//! 优先使用系统钥匙串（macOS Keychain / Windows 凭据管理器 / Linux Secret Service），
+//! 钥匙串不可用时（如没有运行 Secret Service 的 Linux 桌面）最后退回到以 AES-256-GCM 加密的本地文件
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const KEY_FILE: &str = "secret.key";
+const SECRETS_FILE: &str = "secrets.json";
+
+/// 密钥存储接口
+pub trait SecretStore: Send + Sync {
+    fn get(&self, id: &str) -> Result<Option<String>, String>;
+    fn set(&self, id: &str, secret: &str) -> Result<(), String>;
+    fn delete(&self, id: &str) -> Result<(), String>;
+}
+
+/// 加密文件存储：随机生成的主密钥保存在 secret.key，密文保存在 secrets.json。
+/// 主密钥与密文放在同一目录，只能防止密钥以明文出现在配置中，仅在系统钥匙串不可用时使用
+pub struct FileSecretStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileSecretStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 读取主密钥，首次使用时生成
+    fn key(&self) -> Result<LessSafeKey, String> {
+        let path = self.dir.join(KEY_FILE);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+                let mut bytes = vec![0u8; AES_256_GCM.key_len()];
+                SystemRandom::new()
+                    .fill(&mut bytes)
+                    .map_err(|_| "生成密钥失败".to_string())?;
+                match open_private(&path, true) {
+                    Ok(mut file) => {
+                        file.write_all(&bytes).map_err(|e| e.to_string())?;
+                        bytes
+                    }
+                    // 其他进程同时生成了密钥
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        fs::read(&path).map_err(|e| e.to_string())?
+                    }
+                    Err(e) => return Err(e.to_string()),
+                }
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let key =
+            UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| "密钥文件已损坏".to_string())?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>, String> {
+        let path = self.dir.join(SECRETS_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let path = self.dir.join(SECRETS_FILE);
+        let content = serde_json::to_string_pretty(secrets).map_err(|e| e.to_string())?;
+        let mut file = open_private(&path, false).map_err(|e| e.to_string())?;
+        // 旧版本创建的文件可能权限较宽，写入前先收紧
+        restrict_permissions(&path)?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl SecretStore for FileSecretStore {
+    fn get(&self, id: &str) -> Result<Option<String>, String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "密钥存储锁已损坏".to_string())?;
+        let Some(encoded) = self.load()?.remove(id) else {
+            return Ok(None);
+        };
+
+        let mut data = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+        if data.len() < NONCE_LEN {
+            return Err("密文已损坏".to_string());
+        }
+        let mut ciphertext = data.split_off(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(&data).map_err(|_| "密文已损坏".to_string())?;
+        let plain = self
+            .key()?
+            .open_in_place(nonce, Aad::from(id.as_bytes()), &mut ciphertext)
+            .map_err(|_| "密钥解密失败".to_string())?;
+        String::from_utf8(plain.to_vec())
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    fn set(&self, id: &str, secret: &str) -> Result<(), String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "密钥存储锁已损坏".to_string())?;
+        let key = self.key()?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| "生成随机数失败".to_string())?;
+        let mut data = secret.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(id.as_bytes()),
+            &mut data,
+        )
+        .map_err(|_| "密钥加密失败".to_string())?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(data);
+        let mut secrets = self.load()?;
+        secrets.insert(id.to_string(), STANDARD.encode(payload));
+        self.save(&secrets)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "密钥存储锁已损坏".to_string())?;
+        let mut secrets = self.load()?;
+        if secrets.remove(id).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// 打开用于写入的文件，新建时直接设为仅当前用户可读写，不会先以默认权限创建
+fn open_private(path: &Path, create_new: bool) -> std::io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// 系统钥匙串存储
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub struct KeyringStore;
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+impl KeyringStore {
+    const SERVICE: &'static str = "bun-codeview";
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+impl SecretStore for KeyringStore {
+    fn get(&self, id: &str) -> Result<Option<String>, String> {
+        let entry = keyring::Entry::new(Self::SERVICE, id).map_err(|e| e.to_string())?;
+        match entry.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn set(&self, id: &str, secret: &str) -> Result<(), String> {
+        keyring::Entry::new(Self::SERVICE, id)
+            .and_then(|entry| entry.set_password(secret))
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        let entry = keyring::Entry::new(Self::SERVICE, id).map_err(|e| e.to_string())?;
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// 先尝试系统钥匙串，失败时使用加密文件
+pub struct FallbackSecretStore {
+    primary: Option<Box<dyn SecretStore>>,
+    fallback: FileSecretStore,
+}
+
+impl FallbackSecretStore {
+    pub fn new(dir: &Path) -> Self {
+        #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+        let primary: Option<Box<dyn SecretStore>> = Some(Box::new(KeyringStore));
+        #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+        let primary: Option<Box<dyn SecretStore>> = None;
+
+        Self {
+            primary,
+            fallback: FileSecretStore::new(dir),
+        }
+    }
+}
+
+impl SecretStore for FallbackSecretStore {
+    fn get(&self, id: &str) -> Result<Option<String>, String> {
+        if let Some(primary) = &self.primary {
+            match primary.get(id) {
+                Ok(Some(secret)) => return Ok(Some(secret)),
+                Ok(None) => {}
+                Err(e) => log::warn!("读取系统钥匙串失败: {}", e),
+            }
+        }
+        self.fallback.get(id)
+    }
+
+    fn set(&self, id: &str, secret: &str) -> Result<(), String> {
+        if let Some(primary) = &self.primary {
+            match primary.set(id, secret) {
+                // 写入钥匙串成功后清除文件中可能残留的旧值
+                Ok(()) => return self.fallback.delete(id),
+                Err(e) => log::warn!("写入系统钥匙串失败，改用加密文件: {}", e),
+            }
+        }
+        self.fallback.set(id, secret)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        if let Some(primary) = &self.primary {
+            if let Err(e) = primary.delete(id) {
+                log::warn!("删除系统钥匙串条目失败: {}", e);
+            }
+        }
+        self.fallback.delete(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_secret_store_encrypts() {
+        let dir = std::env::temp_dir().join(format!("secret-test-{}", uuid::Uuid::new_v4()));
+        let store = FileSecretStore::new(&dir);
+
+        assert_eq!(store.get("openai").unwrap(), None);
+        store.set("openai", "sk-secret-value").unwrap();
+        store.set("azure", "azure-key").unwrap();
+
+        let raw = fs::read_to_string(dir.join(SECRETS_FILE)).unwrap();
+        assert!(!raw.contains("sk-secret-value"));
+        assert_eq!(
+            FileSecretStore::new(&dir).get("openai").unwrap().as_deref(),
+            Some("sk-secret-value")
+        );
+
+        // 密文与条目 ID 绑定，不能挪作他用
+        let mut secrets = store.load().unwrap();
+        let openai = secrets["openai"].clone();
+        secrets.insert("azure".to_string(), openai);
+        store.save(&secrets).unwrap();
+        assert!(store.get("azure").is_err());
+
+        store.delete("openai").unwrap();
+        assert_eq!(store.get("openai").unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in [KEY_FILE, SECRETS_FILE] {
+                let mode = fs::metadata(dir.join(file)).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,530 @@
+//! 静态站点发布服务
+//! 将工作区中的 Markdown 文件渲染为带侧边栏导航和搜索的静态 HTML 站点
+
+use crate::commands::{read_dir_recursive, FileNode};
+use crate::services::markdown_renderer::{self, escape_xml, is_local_url, LinkKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const MANIFEST_FILE: &str = ".publish-manifest.json";
+const SEARCH_INDEX_FILE: &str = "search-index.json";
+const MANIFEST_VERSION: u32 = 1;
+/// 搜索索引中每页保留的正文长度
+const SEARCH_TEXT_LIMIT: usize = 5000;
+
+const SITE_CSS: &str = r#"body{margin:0;font-family:sans-serif;line-height:1.6;color:#24292f;}
+.layout{display:flex;min-height:100vh;}
+.sidebar{width:260px;flex-shrink:0;border-right:1px solid #e1e4e8;padding:1em;background:#f6f8fa;overflow-y:auto;}
+.sidebar ul{list-style:none;padding-left:1em;margin:0;}
+.sidebar>nav>ul{padding-left:0;}
+.sidebar a{color:#0366d6;text-decoration:none;}
+.sidebar a.active{font-weight:bold;}
+.sidebar .folder{font-weight:600;color:#57606a;}
+.search input{width:100%;box-sizing:border-box;padding:4px 6px;margin-bottom:0.5em;}
+.search-results{list-style:none;padding:0;margin:0 0 1em;}
+main{flex:1;padding:2em;max-width:860px;}
+pre{background:#f4f4f4;padding:1em;border-radius:4px;overflow-x:auto;}
+code{font-family:monospace;}
+blockquote{border-left:4px solid #ddd;padding-left:1em;color:#666;}
+table{border-collapse:collapse;width:100%;margin-bottom:1em;}
+th,td{border:1px solid #ddd;padding:8px;text-align:left;}
+th{background-color:#f2f2f2;}
+img{max-width:100%;}
+"#;
+
+const SEARCH_JS: &str = r#"(function(){
+  var input=document.getElementById('site-search');
+  var list=document.getElementById('site-search-results');
+  if(!input||!list)return;
+  var root=document.body.getAttribute('data-root')||'';
+  var index=null;
+  function load(){
+    if(index)return Promise.resolve(index);
+    return fetch(root+'search-index.json').then(function(r){return r.json();}).then(function(d){index=d;return d;});
+  }
+  input.addEventListener('input',function(){
+    var q=input.value.trim().toLowerCase();
+    list.innerHTML='';
+    if(!q)return;
+    load().then(function(entries){
+      entries.filter(function(e){
+        return e.title.toLowerCase().indexOf(q)>=0||e.text.toLowerCase().indexOf(q)>=0;
+      }).slice(0,20).forEach(function(e){
+        var li=document.createElement('li');
+        var a=document.createElement('a');
+        a.href=root+e.url;
+        a.textContent=e.title;
+        li.appendChild(a);
+        list.appendChild(li);
+      });
+    });
+  });
+})();
+"#;
+
+/// 发布选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 忽略增量记录，全部重新生成
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// 发布结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishReport {
+    pub output_dir: String,
+    pub rendered: usize,
+    pub unchanged: usize,
+    pub copied: usize,
+    pub removed: usize,
+}
+
+/// 搜索索引条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchEntry {
+    title: String,
+    url: String,
+    headings: Vec<String>,
+    text: String,
+}
+
+/// 源文件指纹
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileStamp {
+    modified: u64,
+    size: u64,
+}
+
+/// 增量构建记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishManifest {
+    version: u32,
+    title: String,
+    /// 站点中所有页面的相对路径，变化时侧边栏需要全部重建
+    pages: Vec<String>,
+    files: HashMap<String, FileStamp>,
+    search: HashMap<String, SearchEntry>,
+}
+
+pub struct SiteService;
+
+impl SiteService {
+    /// 将工作区发布为静态站点
+    pub fn publish(
+        root: &Path,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result<PublishReport, String> {
+        if !root.is_dir() {
+            return Err("路径不存在".to_string());
+        }
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+        let output_dir = output_dir.canonicalize().map_err(|e| e.to_string())?;
+        let root = root.canonicalize().map_err(|e| e.to_string())?;
+
+        let tree = prune_tree(read_dir_recursive(&root, 10)?, &output_dir);
+        let mut pages = Vec::new();
+        let mut attachments = Vec::new();
+        collect_files(&tree, &root, &mut pages, &mut attachments);
+
+        let title = options.title.clone().unwrap_or_else(|| {
+            root.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Site".to_string())
+        });
+
+        let manifest_path = output_dir.join(MANIFEST_FILE);
+        let previous = if options.force {
+            PublishManifest::default()
+        } else {
+            load_manifest(&manifest_path)
+        };
+        // 页面集合或站点标题变化时，所有页面的侧边栏都需要更新
+        let rebuild_all = previous.version != MANIFEST_VERSION
+            || previous.pages != pages
+            || previous.title != title;
+
+        let mut manifest = PublishManifest {
+            version: MANIFEST_VERSION,
+            title: title.clone(),
+            pages: pages.clone(),
+            ..Default::default()
+        };
+        let mut report = PublishReport {
+            output_dir: output_dir.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        for rel in &pages {
+            let source = root.join(rel);
+            let stamp = file_stamp(&source)?;
+            let target = output_dir.join(page_url(rel));
+            let unchanged = !rebuild_all
+                && previous.files.get(rel) == Some(&stamp)
+                && target.exists()
+                && previous.search.contains_key(rel);
+
+            if unchanged {
+                manifest
+                    .search
+                    .insert(rel.clone(), previous.search[rel].clone());
+                report.unchanged += 1;
+            } else {
+                let entry = Self::render_page(&source, rel, &target, &title, &tree, &root)?;
+                manifest.search.insert(rel.clone(), entry);
+                report.rendered += 1;
+            }
+            manifest.files.insert(rel.clone(), stamp);
+        }
+
+        for rel in &attachments {
+            let source = root.join(rel);
+            let stamp = file_stamp(&source)?;
+            let target = output_dir.join(rel);
+            if rebuild_all || previous.files.get(rel) != Some(&stamp) || !target.exists() {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::copy(&source, &target).map_err(|e| e.to_string())?;
+                report.copied += 1;
+            }
+            manifest.files.insert(rel.clone(), stamp);
+        }
+
+        // 删除源文件已不存在的输出
+        for rel in previous.files.keys() {
+            if manifest.files.contains_key(rel) {
+                continue;
+            }
+            let target = if is_markdown(rel) {
+                output_dir.join(page_url(rel))
+            } else {
+                output_dir.join(rel)
+            };
+            if target.is_file() && fs::remove_file(&target).is_ok() {
+                report.removed += 1;
+            }
+        }
+
+        if rebuild_all || report.rendered > 0 || report.removed > 0 {
+            if !pages.iter().any(|p| page_url(p) == "index.html") {
+                let index = match pages.first() {
+                    Some(first) => redirect_html(&page_url(first)),
+                    None => redirect_html("#"),
+                };
+                fs::write(output_dir.join("index.html"), index).map_err(|e| e.to_string())?;
+            }
+            fs::write(output_dir.join("site.css"), SITE_CSS).map_err(|e| e.to_string())?;
+            fs::write(output_dir.join("search.js"), SEARCH_JS).map_err(|e| e.to_string())?;
+
+            let search: Vec<&SearchEntry> = pages
+                .iter()
+                .filter_map(|p| manifest.search.get(p))
+                .collect();
+            let index = serde_json::to_string(&search).map_err(|e| e.to_string())?;
+            fs::write(output_dir.join(SEARCH_INDEX_FILE), index).map_err(|e| e.to_string())?;
+        }
+
+        let content = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        fs::write(&manifest_path, content).map_err(|e| e.to_string())?;
+
+        Ok(report)
+    }
+
+    /// 渲染单个页面并返回搜索索引条目
+    fn render_page(
+        source: &Path,
+        rel: &str,
+        target: &Path,
+        site_title: &str,
+        tree: &[FileNode],
+        root: &Path,
+    ) -> Result<SearchEntry, String> {
+        let content = fs::read_to_string(source).map_err(|e| e.to_string())?;
+        let (front_matter, body) = markdown_renderer::split_front_matter(&content);
+
+        let rendered = markdown_renderer::render(body, |kind, url| match kind {
+            LinkKind::Link => rewrite_page_link(url),
+            LinkKind::Image => None,
+        });
+
+        let url = page_url(rel);
+        let title = front_matter
+            .title
+            .or_else(|| rendered.title().map(str::to_string))
+            .unwrap_or_else(|| file_stem(rel));
+        let depth = rel.matches('/').count();
+        let prefix = "../".repeat(depth);
+
+        let mut sidebar = String::new();
+        render_sidebar(tree, root, &prefix, &url, &mut sidebar);
+
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - {site}</title>
+<link rel="stylesheet" href="{prefix}site.css">
+</head>
+<body data-root="{prefix}">
+<div class="layout">
+<aside class="sidebar">
+<div class="site-title"><a href="{prefix}index.html">{site}</a></div>
+<div class="search"><input id="site-search" type="search" placeholder="搜索"><ul id="site-search-results" class="search-results"></ul></div>
+<nav>{sidebar}</nav>
+</aside>
+<main>
+{body}</main>
+</div>
+<script src="{prefix}search.js"></script>
+</body>
+</html>
+"#,
+            title = escape_xml(&title),
+            site = escape_xml(site_title),
+            prefix = prefix,
+            sidebar = sidebar,
+            body = rendered.html
+        );
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(target, html).map_err(|e| e.to_string())?;
+
+        Ok(SearchEntry {
+            title,
+            url,
+            headings: rendered.headings.into_iter().map(|h| h.text).collect(),
+            text: markdown_renderer::plain_text(body)
+                .chars()
+                .take(SEARCH_TEXT_LIMIT)
+                .collect(),
+        })
+    }
+}
+
+/// 去掉输出目录和不包含 Markdown 的空目录
+fn prune_tree(nodes: Vec<FileNode>, output_dir: &Path) -> Vec<FileNode> {
+    nodes
+        .into_iter()
+        .filter(|node| !Path::new(&node.path).starts_with(output_dir))
+        .filter_map(|mut node| {
+            if node.is_directory {
+                let children = prune_tree(node.children.take().unwrap_or_default(), output_dir);
+                if children.is_empty() {
+                    return None;
+                }
+                node.children = Some(children);
+            }
+            Some(node)
+        })
+        .collect()
+}
+
+fn collect_files(
+    nodes: &[FileNode],
+    root: &Path,
+    pages: &mut Vec<String>,
+    attachments: &mut Vec<String>,
+) {
+    for node in nodes {
+        if let Some(children) = &node.children {
+            collect_files(children, root, pages, attachments);
+        } else if !node.is_directory {
+            let rel = relative_path(root, Path::new(&node.path));
+            if is_markdown(&rel) {
+                pages.push(rel);
+            } else {
+                attachments.push(rel);
+            }
+        }
+    }
+}
+
+fn render_sidebar(nodes: &[FileNode], root: &Path, prefix: &str, current: &str, out: &mut String) {
+    out.push_str("<ul>");
+    for node in nodes {
+        if node.is_directory {
+            let children = node.children.as_deref().unwrap_or_default();
+            if !contains_pages(children) {
+                continue;
+            }
+            out.push_str(&format!(
+                "<li><span class=\"folder\">{}</span>",
+                escape_xml(&node.name)
+            ));
+            render_sidebar(children, root, prefix, current, out);
+            out.push_str("</li>");
+        } else if is_markdown(&node.name) {
+            let url = page_url(&relative_path(root, Path::new(&node.path)));
+            let class = if url == current {
+                " class=\"active\""
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "<li><a href=\"{}{}\"{}>{}</a></li>",
+                prefix,
+                escape_xml(&url),
+                class,
+                escape_xml(&file_stem(&node.name))
+            ));
+        }
+    }
+    out.push_str("</ul>");
+}
+
+fn contains_pages(nodes: &[FileNode]) -> bool {
+    nodes.iter().any(|node| {
+        if node.is_directory {
+            contains_pages(node.children.as_deref().unwrap_or_default())
+        } else {
+            is_markdown(&node.name)
+        }
+    })
+}
+
+/// 将指向 Markdown 的站内链接改写为生成的 HTML 页面
+fn rewrite_page_link(url: &str) -> Option<String> {
+    if !is_local_url(url) {
+        return None;
+    }
+    let (path, fragment) = match url.split_once('#') {
+        Some((p, f)) => (p, Some(f)),
+        None => (url, None),
+    };
+    if !is_markdown(path) {
+        return None;
+    }
+    let page = format!("{}.html", &path[..path.len() - 3]);
+    Some(match fragment {
+        Some(f) => format!("{}#{}", page, f),
+        None => page,
+    })
+}
+
+fn load_manifest(path: &Path) -> PublishManifest {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn file_stamp(path: &Path) -> Result<FileStamp, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    Ok(FileStamp {
+        modified,
+        size: metadata.len(),
+    })
+}
+
+fn redirect_html(url: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><meta http-equiv="refresh" content="0; url={0}"></head><body><a href="{0}">{0}</a></body></html>"#,
+        escape_xml(url)
+    )
+}
+
+/// 统一使用 `/` 分隔的相对路径
+fn relative_path(root: &Path, path: &Path) -> String {
+    let rel: PathBuf = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.to_lowercase().ends_with(".md")
+}
+
+fn page_url(rel: &str) -> String {
+    format!("{}.html", &rel[..rel.len() - 3])
+}
+
+fn file_stem(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".md").unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_publish_incremental() {
+        let root = std::env::temp_dir().join(format!("site-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("guide")).unwrap();
+        fs::write(
+            root.join("index.md"),
+            "# Home\n\nRead the [guide](guide/setup.md#install).\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("guide/setup.md"),
+            "# Setup\n\n## Install\n\n![shot](shot.png)\n",
+        )
+        .unwrap();
+        fs::write(root.join("guide/shot.png"), [0u8; 4]).unwrap();
+        let output = root.join("_site");
+
+        let report = SiteService::publish(&root, &output, &PublishOptions::default()).unwrap();
+        assert_eq!(report.rendered, 2);
+        assert_eq!(report.copied, 1);
+
+        let home = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(home.contains("href=\"guide/setup.html#install\""));
+        assert!(home.contains("<a href=\"guide/setup.html\">setup</a>"));
+        let setup = fs::read_to_string(output.join("guide/setup.html")).unwrap();
+        assert!(setup.contains("href=\"../site.css\""));
+        assert!(output.join("guide/shot.png").exists());
+
+        let index: Vec<serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(output.join(SEARCH_INDEX_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[0]["url"], "guide/setup.html");
+        assert_eq!(index[0]["headings"][1], "Install");
+
+        // 未修改时不重新渲染
+        let report = SiteService::publish(&root, &output, &PublishOptions::default()).unwrap();
+        assert_eq!(report.rendered, 0);
+        assert_eq!(report.unchanged, 2);
+        assert_eq!(report.copied, 0);
+
+        // 只重新渲染修改过的文件
+        fs::write(root.join("index.md"), "# Home\n\nUpdated content here.\n").unwrap();
+        let report = SiteService::publish(&root, &output, &PublishOptions::default()).unwrap();
+        assert_eq!(report.rendered, 1);
+        assert_eq!(report.unchanged, 1);
+
+        // 删除页面后清理输出并重建导航
+        fs::remove_file(root.join("guide/setup.md")).unwrap();
+        let report = SiteService::publish(&root, &output, &PublishOptions::default()).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(!output.join("guide/setup.html").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+//! 幻灯片导出服务
+//! 以 `---` 分隔幻灯片，`Note:` 之后的内容作为演讲者备注，生成可离线播放的 HTML
+
+use crate::services::markdown_renderer::{self, escape_xml, is_local_url, LinkKind};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 单张幻灯片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Slide {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 幻灯片正文（Markdown）
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// 幻灯片主题
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SlideTheme {
+    #[default]
+    White,
+    Black,
+    Sepia,
+    Night,
+}
+
+impl SlideTheme {
+    fn css(&self) -> &'static str {
+        match self {
+            SlideTheme::White => "--bg:#ffffff;--fg:#222222;--accent:#2a76dd;--code-bg:#f4f4f4;",
+            SlideTheme::Black => "--bg:#191919;--fg:#eeeeee;--accent:#42affa;--code-bg:#2b2b2b;",
+            SlideTheme::Sepia => "--bg:#f7f2d3;--fg:#3b2f2f;--accent:#8b743d;--code-bg:#ece4c0;",
+            SlideTheme::Night => "--bg:#111111;--fg:#dddddd;--accent:#e7ad52;--code-bg:#222222;",
+        }
+    }
+}
+
+/// 幻灯片导出选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlideOptions {
+    #[serde(default)]
+    pub theme: SlideTheme,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+const DECK_CSS: &str = r#"html,body{margin:0;height:100%;background:var(--bg);color:var(--fg);font-family:"Source Sans Pro",Helvetica,sans-serif;}
+.deck{position:relative;width:100%;height:100%;overflow:hidden;}
+.slide{position:absolute;inset:0;display:none;flex-direction:column;justify-content:center;padding:4vh 8vw;box-sizing:border-box;font-size:3.2vh;line-height:1.4;}
+.slide.active{display:flex;}
+.slide h1,.slide h2,.slide h3{color:var(--accent);margin:0 0 0.6em;}
+.slide h1{font-size:2.4em;}.slide h2{font-size:1.8em;}
+.slide pre{background:var(--code-bg);padding:0.8em;border-radius:6px;overflow:auto;font-size:0.7em;}
+.slide code{font-family:Menlo,Consolas,monospace;}
+.slide img{max-width:100%;max-height:70vh;object-fit:contain;}
+.slide table{border-collapse:collapse;}.slide th,.slide td{border:1px solid var(--fg);padding:0.3em 0.6em;}
+.slide aside.notes{display:none;}
+.progress{position:fixed;left:0;bottom:0;height:4px;background:var(--accent);transition:width .2s;}
+.counter{position:fixed;right:1em;bottom:0.8em;font-size:14px;opacity:.6;}
+.notes-panel{display:none;position:fixed;left:0;right:0;bottom:0;max-height:30vh;overflow:auto;background:rgba(0,0,0,.85);color:#fff;padding:1em 2em;font-size:16px;}
+body.show-notes .notes-panel{display:block;}
+@media print{.slide{display:flex;position:relative;page-break-after:always;height:100vh;}.progress,.counter,.notes-panel{display:none!important;}}
+"#;
+
+const DECK_JS: &str = r#"(function(){
+  var slides=document.querySelectorAll('.slide');
+  var progress=document.querySelector('.progress');
+  var counter=document.querySelector('.counter');
+  var notes=document.querySelector('.notes-panel');
+  var current=0;
+  function show(n){
+    if(!slides.length)return;
+    current=Math.max(0,Math.min(slides.length-1,n));
+    slides.forEach(function(s,i){s.classList.toggle('active',i===current);});
+    progress.style.width=((current+1)/slides.length*100)+'%';
+    counter.textContent=(current+1)+' / '+slides.length;
+    var aside=slides[current].querySelector('aside.notes');
+    notes.innerHTML=aside?aside.innerHTML:'';
+    if(location.hash!=='#'+(current+1))history.replaceState(null,'','#'+(current+1));
+  }
+  document.addEventListener('keydown',function(e){
+    if(['ArrowRight','ArrowDown','PageDown',' ','Enter'].indexOf(e.key)>=0){show(current+1);e.preventDefault();}
+    else if(['ArrowLeft','ArrowUp','PageUp','Backspace'].indexOf(e.key)>=0){show(current-1);e.preventDefault();}
+    else if(e.key==='Home'){show(0);}
+    else if(e.key==='End'){show(slides.length-1);}
+    else if(e.key==='s'||e.key==='S'){document.body.classList.toggle('show-notes');}
+  });
+  document.addEventListener('click',function(e){if(e.target.tagName!=='A')show(current+(e.clientX<window.innerWidth/3?-1:1));});
+  show((parseInt(location.hash.slice(1),10)||1)-1);
+})();
+"#;
+
+pub struct SlidesService;
+
+impl SlidesService {
+    /// 将 Markdown 拆分为幻灯片
+    pub fn split_slides(content: &str) -> Vec<Slide> {
+        let (_, body) = markdown_renderer::split_front_matter(content);
+
+        let mut slides = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        let mut in_fence = false;
+
+        for line in body.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if !in_fence && trimmed == "---" {
+                slides.extend(Self::build_slide(&current));
+                current.clear();
+            } else {
+                current.push(line);
+            }
+        }
+        slides.extend(Self::build_slide(&current));
+        slides
+    }
+
+    fn build_slide(lines: &[&str]) -> Option<Slide> {
+        let mut body = Vec::new();
+        let mut notes: Option<Vec<&str>> = None;
+        let mut in_fence = false;
+
+        for line in lines {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if let Some(notes) = notes.as_mut() {
+                notes.push(*line);
+                continue;
+            }
+            let note_start = trimmed
+                .strip_prefix("Note:")
+                .or_else(|| trimmed.strip_prefix("Notes:"));
+            match note_start {
+                Some(rest) if !in_fence => {
+                    notes = Some(vec![rest.trim_start()]);
+                }
+                _ => body.push(*line),
+            }
+        }
+
+        let body = body.join("\n").trim().to_string();
+        let notes = notes
+            .map(|n| n.join("\n").trim().to_string())
+            .filter(|n| !n.is_empty());
+        if body.is_empty() && notes.is_none() {
+            return None;
+        }
+
+        let mut in_fence = false;
+        let title = body.lines().find_map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            (!in_fence && (1..=6).contains(&level))
+                .then(|| trimmed[level..].trim().to_string())
+                .filter(|t| !t.is_empty())
+        });
+
+        Some(Slide { title, body, notes })
+    }
+
+    /// 生成独立的 HTML 幻灯片，本地图片以 data URI 内嵌
+    pub fn render_deck(content: &str, base_dir: &Path, options: &SlideOptions) -> String {
+        let slides = Self::split_slides(content);
+        let title = options
+            .title
+            .clone()
+            .or_else(|| slides.iter().find_map(|s| s.title.clone()))
+            .unwrap_or_else(|| "Slides".to_string());
+
+        let mut sections = String::new();
+        for (idx, slide) in slides.iter().enumerate() {
+            let body = markdown_renderer::render(&slide.body, |kind, url| match kind {
+                LinkKind::Image => inline_image(base_dir, url),
+                LinkKind::Link => None,
+            });
+            sections.push_str(&format!(
+                "<section class=\"slide\" id=\"slide-{}\">\n{}",
+                idx + 1,
+                body.html
+            ));
+            if let Some(notes) = &slide.notes {
+                let notes = markdown_renderer::render(notes, |_, _| None);
+                sections.push_str(&format!("<aside class=\"notes\">{}</aside>\n", notes.html));
+            }
+            sections.push_str("</section>\n");
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>:root{{{theme}}}
+{css}</style>
+</head>
+<body>
+<div class="deck">
+{sections}</div>
+<div class="progress"></div>
+<div class="counter"></div>
+<div class="notes-panel"></div>
+<script>{js}</script>
+</body>
+</html>
+"#,
+            title = escape_xml(&title),
+            theme = options.theme.css(),
+            css = DECK_CSS,
+            sections = sections,
+            js = DECK_JS
+        )
+    }
+
+    /// 导出 Markdown 文件为 HTML 幻灯片
+    pub fn export_file(
+        source_path: &Path,
+        output_path: &Path,
+        options: &SlideOptions,
+    ) -> Result<(), String> {
+        let content = fs::read_to_string(source_path).map_err(|e| e.to_string())?;
+        let base_dir = source_path.parent().unwrap_or(Path::new("."));
+        let html = Self::render_deck(&content, base_dir, options);
+        fs::write(output_path, html).map_err(|e| e.to_string())
+    }
+}
+
+/// 读取本地图片并转换为 data URI
+fn inline_image(base_dir: &Path, url: &str) -> Option<String> {
+    if !is_local_url(url) {
+        return None;
+    }
+    let path = base_dir.join(url);
+    let mime = match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => return None,
+    };
+    let data = fs::read(&path).ok()?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_slides() {
+        let content = "---\ntitle: Talk\n---\n# Intro\n\nHello\n\nNote: say hi\nand smile\n\n---\n\n## Code\n\n```yaml\n---\nkey: value\n```\n\n---\n\n![chart](chart.png)\n";
+        let slides = SlidesService::split_slides(content);
+
+        assert_eq!(slides.len(), 3);
+        assert_eq!(slides[0].title.as_deref(), Some("Intro"));
+        assert_eq!(slides[0].body, "# Intro\n\nHello");
+        assert_eq!(slides[0].notes.as_deref(), Some("say hi\nand smile"));
+        assert!(slides[1].body.contains("key: value"));
+        assert_eq!(slides[2].title, None);
+    }
+
+    #[test]
+    fn test_render_deck_is_standalone() {
+        let dir = std::env::temp_dir().join(format!("slides-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("chart.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+        let html = SlidesService::render_deck(
+            "# One\n\nNote: secret\n\n---\n\n![chart](chart.png)\n",
+            &dir,
+            &SlideOptions {
+                theme: SlideTheme::Black,
+                title: None,
+            },
+        );
+
+        assert_eq!(html.matches("<section class=\"slide\"").count(), 2);
+        assert!(html.contains("<aside class=\"notes\"><p>secret</p>\n</aside>"));
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw==\""));
+        assert!(html.contains("--bg:#191919"));
+        assert!(!html.contains("http://") && !html.contains("https://"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
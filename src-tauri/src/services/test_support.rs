@@ -0,0 +1,138 @@
+//! 测试辅助：本地 HTTP 桩服务
+//! 按顺序为每个连接返回一个预设响应，并记录收到的请求
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+/// 桩服务收到的请求
+#[derive(Debug)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl StubRequest {
+    /// 按名称（不区分大小写）读取请求头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// 预设响应
+pub struct StubResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl StubResponse {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: body.into(),
+        }
+    }
+
+    /// 服务器推送事件流，`events` 中每一项作为一个 `data:` 事件
+    pub fn sse(events: &[&str]) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/event-stream",
+            body: events.iter().map(|e| format!("data: {}\n\n", e)).collect(),
+        }
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.into(),
+        }
+    }
+}
+
+/// 本地桩服务
+pub struct StubServer {
+    pub url: String,
+    handle: JoinHandle<Vec<StubRequest>>,
+}
+
+impl StubServer {
+    /// 启动桩服务，依次处理 `responses.len()` 个请求
+    pub fn start(responses: Vec<StubResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let request = read_request(&mut BufReader::new(stream.try_clone().unwrap()));
+                    write!(
+                        stream,
+                        "HTTP/1.1 {} Stub\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.status,
+                        response.content_type,
+                        response.body.len(),
+                        response.body
+                    )
+                    .unwrap();
+                    request
+                })
+                .collect()
+        });
+        Self { url, handle }
+    }
+
+    /// 等待所有响应发送完毕并返回收到的请求
+    pub fn requests(self) -> Vec<StubRequest> {
+        self.handle.join().unwrap()
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> StubRequest {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+
+    StubRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8(body).unwrap(),
+    }
+}